@@ -1,4 +1,4 @@
-use crate::core::export_engine::{export_image, ExportFormat};
+use crate::core::export_engine::{export_image, prepare_export, ExportFormat, ExportOptions};
 use crate::types::errors::AppError;
 use crate::state::image_state::ImageState;
 use serde::{Deserialize, Serialize};
@@ -11,6 +11,9 @@ pub struct ExportParams {
     pub output_path: String,
     pub format: String,
     pub quality: u8,
+    /// Export-time steps such as a watermark
+    #[serde(default)]
+    pub options: ExportOptions,
 }
 
 /// Export result
@@ -33,7 +36,7 @@ pub async fn export_image_command(
         .extension()
         .and_then(|e| e.to_str())
         .ok_or_else(|| AppError::InvalidOperation {
-            details: "File path must have an extension (jpg, png, webp)".to_string(),
+            details: "File path must have an extension (jpg, png, webp, gif)".to_string(),
         })?;
 
     let format = ExportFormat::from_extension(extension)?;
@@ -61,9 +64,11 @@ pub async fn export_image_command(
     // Export in blocking thread
     let path_clone = path.clone();
     let quality = params.quality;
+    let options = params.options;
     
     let file_size = tokio::task::spawn_blocking(move || -> Result<u64, AppError> {
-        export_image(&image, &path_clone, format, quality)
+        let image = prepare_export(image, &options)?;
+        export_image(&image, &path_clone, format, quality, &options)
     })
     .await
     .map_err(|e: tokio::task::JoinError| AppError::ProcessingError {
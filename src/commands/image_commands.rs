@@ -3,23 +3,21 @@ use tauri::State;
 use image::GenericImageView;
 
 use crate::state::image_state::ImageState;
-use crate::types::commands::{OpenImageInput, OpenImageOutput, ApplyOperationInput, ApplyOperationOutput};
+use crate::types::commands::{
+    OpenHdrBracketInput, OpenImageInput, OpenImageOutput, ApplyOperationInput, ApplyOperationOutput,
+};
 use crate::types::errors::AppError;
 use crate::types::operations::FilterType;
 use crate::utils::preview::{validate_file_size, validate_format};
 
-/// Open an image file and return metadata with preview
-#[tauri::command]
-pub async fn open_image(
-    input: OpenImageInput,
-    state: State<'_, ImageState>,
-) -> Result<OpenImageOutput, AppError> {
+/// Check that a path names an existing image file of a supported format and size
+pub(crate) fn validate_input_path(file_path: &str) -> Result<PathBuf, AppError> {
     // Validate file path
-    let path = PathBuf::from(&input.file_path);
+    let path = PathBuf::from(file_path);
     if !path.exists() {
         return Err(AppError::ImageLoadError(format!(
             "File not found: {}",
-            input.file_path
+            file_path
         )));
     }
 
@@ -27,7 +25,7 @@ pub async fn open_image(
     if !path.is_file() {
         return Err(AppError::ImageLoadError(format!(
             "Path is not a file: {}",
-            input.file_path
+            file_path
         )));
     }
 
@@ -37,6 +35,17 @@ pub async fn open_image(
     // Validate file size
     validate_file_size(&path)?;
 
+    Ok(path)
+}
+
+/// Open an image file and return metadata with preview
+#[tauri::command]
+pub async fn open_image(
+    input: OpenImageInput,
+    state: State<'_, ImageState>,
+) -> Result<OpenImageOutput, AppError> {
+    let path = validate_input_path(&input.file_path)?;
+
     // Load original image
     let (width, height, format) = state.load_original(&path).await?;
 
@@ -53,6 +62,34 @@ pub async fn open_image(
     })
 }
 
+/// Merge bracketed exposures into a new image and return metadata with preview
+#[tauri::command]
+pub async fn open_hdr_bracket(
+    input: OpenHdrBracketInput,
+    state: State<'_, ImageState>,
+) -> Result<OpenImageOutput, AppError> {
+    let paths = input
+        .file_paths
+        .iter()
+        .map(|file_path| validate_input_path(file_path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Merge exposures into the new original image
+    let (width, height, format) = state.load_bracket(&paths, input.params).await?;
+
+    // Generate preview
+    let preview_base64 = state
+        .generate_preview(input.preview_max_width, input.preview_max_height)
+        .await?;
+
+    Ok(OpenImageOutput {
+        original_width: width,
+        original_height: height,
+        format,
+        preview_base64,
+    })
+}
+
 /// Apply an operation to the current image
 #[tauri::command]
 pub async fn apply_operation(
@@ -97,20 +134,7 @@ pub async fn undo(
     // Perform undo
     state.undo().await?;
 
-    // Generate preview
-    let preview_base64 = state.generate_preview(800, 600).await?;
-
-    // Get current image dimensions
-    let current = state.get_current();
-    let dimensions = current
-        .and_then(|arc| arc.as_ref().as_ref().map(|img| img.dimensions()))
-        .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?;
-
-    Ok(ApplyOperationOutput {
-        preview_base64,
-        new_width: dimensions.0,
-        new_height: dimensions.1,
-    })
+    current_output(&state).await
 }
 
 /// Redo the last undone operation
@@ -121,6 +145,11 @@ pub async fn redo(
     // Perform redo
     state.redo().await?;
 
+    current_output(&state).await
+}
+
+/// Preview and dimensions of the current image after a change
+pub(crate) async fn current_output(state: &ImageState) -> Result<ApplyOperationOutput, AppError> {
     // Generate preview
     let preview_base64 = state.generate_preview(800, 600).await?;
 
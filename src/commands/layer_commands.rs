@@ -0,0 +1,115 @@
+use tauri::State;
+
+use crate::commands::image_commands::validate_input_path;
+use crate::state::image_state::ImageState;
+use crate::types::commands::{
+    AddFillLayerInput, AddImageLayerInput, AddTextLayerInput, LayerIdInput, LayerImageSource,
+    LayerStackOutput, MoveLayerInput, SetLayerPropertiesInput,
+};
+use crate::types::errors::AppError;
+use crate::types::layers::LayerInfo;
+use crate::utils::base64::decode_image;
+
+/// Describe the layer stack from bottom to top
+#[tauri::command]
+pub async fn list_layers(state: State<'_, ImageState>) -> Result<Vec<LayerInfo>, AppError> {
+    Ok(state.layers())
+}
+
+/// Add an image layer on top of the stack and make it active
+#[tauri::command]
+pub async fn add_image_layer(
+    input: AddImageLayerInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    let image = match input.source {
+        LayerImageSource::File { path } => {
+            let path = validate_input_path(&path)?;
+
+            // Load image in blocking thread
+            tokio::task::spawn_blocking(move || {
+                image::open(&path).map_err(|e| AppError::ImageLoadError(e.to_string()))
+            })
+            .await
+            .map_err(|e| AppError::ProcessingError { details: e.to_string() })??
+        }
+        LayerImageSource::Base64 { data } => decode_image(&data)?,
+    };
+
+    state.add_image_layer(input.name, image).await?;
+
+    layer_stack_output(&state).await
+}
+
+/// Add a solid colour layer on top of the stack and make it active
+#[tauri::command]
+pub async fn add_fill_layer(
+    input: AddFillLayerInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    state.add_fill_layer(input.name, input.color).await?;
+
+    layer_stack_output(&state).await
+}
+
+/// Add a text layer on top of the stack and make it active
+#[tauri::command]
+pub async fn add_text_layer(
+    input: AddTextLayerInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    state.add_text_layer(input.name, input.params).await?;
+
+    layer_stack_output(&state).await
+}
+
+/// Remove a layer; the last remaining layer cannot be removed
+#[tauri::command]
+pub async fn remove_layer(
+    input: LayerIdInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    state.remove_layer(&input.id).await?;
+
+    layer_stack_output(&state).await
+}
+
+/// Move a layer to a new position in the stack
+#[tauri::command]
+pub async fn move_layer(
+    input: MoveLayerInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    state.move_layer(&input.id, input.index).await?;
+
+    layer_stack_output(&state).await
+}
+
+/// Make a layer the target of operations, undo and redo
+#[tauri::command]
+pub async fn set_active_layer(
+    input: LayerIdInput,
+    state: State<'_, ImageState>,
+) -> Result<Vec<LayerInfo>, AppError> {
+    state.set_active_layer(&input.id)?;
+
+    Ok(state.layers())
+}
+
+/// Update a layer's name, opacity, visibility, blend mode or offset
+#[tauri::command]
+pub async fn set_layer_properties(
+    input: SetLayerPropertiesInput,
+    state: State<'_, ImageState>,
+) -> Result<LayerStackOutput, AppError> {
+    state.set_layer_properties(&input.id, input.properties).await?;
+
+    layer_stack_output(&state).await
+}
+
+async fn layer_stack_output(state: &ImageState) -> Result<LayerStackOutput, AppError> {
+    Ok(LayerStackOutput {
+        layers: state.layers(),
+        preview_base64: state.generate_preview(800, 600).await?,
+    })
+}
@@ -1,3 +1,5 @@
 // Commands module - Tauri command handlers
 pub mod export_commands;
 pub mod image_commands;
+pub mod layer_commands;
+pub mod selection_commands;
@@ -0,0 +1,88 @@
+use tauri::State;
+use image::GenericImageView;
+
+use crate::commands::image_commands::current_output;
+use crate::core::operations::selection::validate_selection;
+use crate::state::image_state::ImageState;
+use crate::types::commands::{
+    ApplyOperationInput, ApplyOperationOutput, CutSelectionOutput, SelectionImageOutput,
+    SetSelectionInput,
+};
+use crate::types::errors::AppError;
+use crate::types::operations::Selection;
+use crate::utils::base64::encode_image;
+
+/// Replace the active selection, or clear it
+#[tauri::command]
+pub async fn set_selection(
+    input: SetSelectionInput,
+    state: State<'_, ImageState>,
+) -> Result<(), AppError> {
+    if let Some(selection) = &input.selection {
+        validate_selection(selection)?;
+    }
+
+    state.set_selection(input.selection);
+
+    Ok(())
+}
+
+/// Get the active selection, if any
+#[tauri::command]
+pub async fn get_selection(state: State<'_, ImageState>) -> Result<Option<Selection>, AppError> {
+    Ok(state.get_selection())
+}
+
+/// Apply an operation only inside the active selection
+#[tauri::command]
+pub async fn apply_operation_to_selection(
+    input: ApplyOperationInput,
+    state: State<'_, ImageState>,
+) -> Result<ApplyOperationOutput, AppError> {
+    state.apply_operation_to_selection(input.operation).await?;
+
+    current_output(&state).await
+}
+
+/// Crop the active layer to the bounds of the active selection
+#[tauri::command]
+pub async fn crop_to_selection(
+    state: State<'_, ImageState>,
+) -> Result<ApplyOperationOutput, AppError> {
+    state.crop_to_selection().await?;
+
+    current_output(&state).await
+}
+
+/// Copy the selected pixels of the active layer
+#[tauri::command]
+pub async fn copy_selection(
+    state: State<'_, ImageState>,
+) -> Result<SelectionImageOutput, AppError> {
+    let copied = state.copy_selection().await?;
+
+    selection_image(&copied)
+}
+
+/// Copy the selected pixels, then erase them from the active layer
+#[tauri::command]
+pub async fn cut_selection(
+    state: State<'_, ImageState>,
+) -> Result<CutSelectionOutput, AppError> {
+    let copied = state.cut_selection().await?;
+
+    Ok(CutSelectionOutput {
+        copied: selection_image(&copied)?,
+        result: current_output(&state).await?,
+    })
+}
+
+fn selection_image(image: &image::DynamicImage) -> Result<SelectionImageOutput, AppError> {
+    let (width, height) = image.dimensions();
+
+    Ok(SelectionImageOutput {
+        image_base64: encode_image(image)?,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,222 @@
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use crate::types::layers::{BlendMode, LayerProperties};
+
+/// Layer handed to the compositor
+pub struct CompositeLayer<'a> {
+    pub image: &'a DynamicImage,
+    pub properties: &'a LayerProperties,
+}
+
+/// Blend one colour channel (0.0 to 1.0) of a layer onto its backdrop
+///
+/// Formulas follow the separable blend modes of the W3C compositing spec.
+pub fn blend_channel(mode: BlendMode, backdrop: f32, source: f32) -> f32 {
+    let (cb, cs) = (backdrop, source);
+    match mode {
+        BlendMode::Normal => cs,
+        BlendMode::Multiply => cb * cs,
+        BlendMode::Screen => cb + cs - cb * cs,
+        BlendMode::Overlay => blend_channel(BlendMode::HardLight, cs, cb),
+        BlendMode::Darken => cb.min(cs),
+        BlendMode::Lighten => cb.max(cs),
+        BlendMode::ColorDodge => {
+            if cb == 0.0 {
+                0.0
+            } else if cs >= 1.0 {
+                1.0
+            } else {
+                (cb / (1.0 - cs)).min(1.0)
+            }
+        }
+        BlendMode::ColorBurn => {
+            if cb >= 1.0 {
+                1.0
+            } else if cs == 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - cb) / cs).min(1.0)
+            }
+        }
+        BlendMode::HardLight => {
+            if cs <= 0.5 {
+                cb * 2.0 * cs
+            } else {
+                let cs = 2.0 * cs - 1.0;
+                cb + cs - cb * cs
+            }
+        }
+        BlendMode::SoftLight => {
+            if cs <= 0.5 {
+                cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+            } else {
+                let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+                cb + (2.0 * cs - 1.0) * (d - cb)
+            }
+        }
+        BlendMode::Difference => (cb - cs).abs(),
+        BlendMode::Exclusion => cb + cs - 2.0 * cb * cs,
+    }
+}
+
+/// Composite one layer onto the canvas with source-over alpha
+fn draw_layer(canvas: &mut RgbaImage, layer: &CompositeLayer) {
+    let properties = layer.properties;
+    let source = layer.image.to_rgba8();
+    let (canvas_width, canvas_height) = canvas.dimensions();
+
+    for (sx, sy, pixel) in source.enumerate_pixels() {
+        let (x, y) = (sx as i64 + properties.x, sy as i64 + properties.y);
+        if x < 0 || y < 0 || x >= canvas_width as i64 || y >= canvas_height as i64 {
+            continue;
+        }
+
+        let alpha_s = pixel[3] as f32 / 255.0 * properties.opacity;
+        if alpha_s <= 0.0 {
+            continue;
+        }
+
+        let base = canvas.get_pixel_mut(x as u32, y as u32);
+        let alpha_b = base[3] as f32 / 255.0;
+        let alpha_o = alpha_s + alpha_b * (1.0 - alpha_s);
+
+        for c in 0..3 {
+            let cb = base[c] as f32 / 255.0;
+            let cs = pixel[c] as f32 / 255.0;
+            let mixed = (1.0 - alpha_b) * cs + alpha_b * blend_channel(properties.blend_mode, cb, cs);
+            let co = (alpha_s * mixed + alpha_b * cb * (1.0 - alpha_s)) / alpha_o;
+            base[c] = (co * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        base[3] = (alpha_o * 255.0).round() as u8;
+    }
+}
+
+/// Composite a layer stack onto a transparent canvas
+///
+/// # Parameters
+/// - `width`, `height`: Canvas size
+/// - `layers`: Layers from bottom to top; hidden layers are skipped
+///
+/// # Returns
+/// Flattened image. A single opaque layer covering the canvas is returned
+/// unchanged so its pixel format is preserved.
+pub fn composite(width: u32, height: u32, layers: &[CompositeLayer]) -> DynamicImage {
+    let visible: Vec<&CompositeLayer> = layers
+        .iter()
+        .filter(|layer| layer.properties.visible && layer.properties.opacity > 0.0)
+        .collect();
+
+    if let [layer] = visible.as_slice() {
+        let properties = layer.properties;
+        if properties.opacity >= 1.0
+            && properties.x == 0
+            && properties.y == 0
+            && layer.image.dimensions() == (width, height)
+        {
+            return layer.image.clone();
+        }
+    }
+
+    let mut canvas = RgbaImage::new(width, height);
+    for layer in visible {
+        draw_layer(&mut canvas, layer);
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(pixel)))
+    }
+
+    fn properties(blend_mode: BlendMode, opacity: f32) -> LayerProperties {
+        LayerProperties { blend_mode, opacity, ..LayerProperties::named("layer") }
+    }
+
+    fn blend_pixel(mode: BlendMode, base: [u8; 4], top: [u8; 4]) -> Rgba<u8> {
+        let (base, top) = (solid(1, 1, base), solid(1, 1, top));
+        let (base_props, top_props) = (properties(BlendMode::Normal, 1.0), properties(mode, 1.0));
+        let layers = [
+            CompositeLayer { image: &base, properties: &base_props },
+            CompositeLayer { image: &top, properties: &top_props },
+        ];
+        *composite(1, 1, &layers).to_rgba8().get_pixel(0, 0)
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let base = [200, 100, 50, 255];
+        let top = [100, 100, 255, 255];
+
+        assert_eq!(blend_pixel(BlendMode::Normal, base, top), Rgba(top));
+        assert_eq!(blend_pixel(BlendMode::Multiply, base, top), Rgba([78, 39, 50, 255]));
+        assert_eq!(blend_pixel(BlendMode::Screen, base, top), Rgba([222, 161, 255, 255]));
+        assert_eq!(blend_pixel(BlendMode::Difference, base, top), Rgba([100, 0, 205, 255]));
+        assert_eq!(blend_pixel(BlendMode::Darken, base, top), Rgba([100, 100, 50, 255]));
+        // Overlay onto a mid-grey backdrop passes the source through
+        assert_eq!(blend_pixel(BlendMode::Overlay, [127, 127, 127, 255], top)[0], 100);
+    }
+
+    #[test]
+    fn test_soft_modes_stay_in_range() {
+        let modes = [
+            BlendMode::Overlay,
+            BlendMode::ColorDodge,
+            BlendMode::ColorBurn,
+            BlendMode::HardLight,
+            BlendMode::SoftLight,
+            BlendMode::Exclusion,
+        ];
+        for mode in modes {
+            for cb in [0.0, 0.2, 0.5, 0.9, 1.0] {
+                for cs in [0.0, 0.3, 0.5, 0.8, 1.0] {
+                    let value = blend_channel(mode, cb, cs);
+                    assert!((0.0..=1.0).contains(&value), "{:?}({}, {}) = {}", mode, cb, cs, value);
+                }
+            }
+        }
+        assert_eq!(blend_channel(BlendMode::SoftLight, 0.3, 0.5), 0.3);
+    }
+
+    #[test]
+    fn test_opacity_offset_and_visibility() {
+        let base = solid(4, 4, [0, 0, 0, 255]);
+        let top = solid(2, 2, [255, 255, 255, 255]);
+        let base_props = properties(BlendMode::Normal, 1.0);
+        let mut top_props = LayerProperties { x: 2, y: 3, ..properties(BlendMode::Normal, 0.5) };
+
+        let result = composite(4, 4, &[
+            CompositeLayer { image: &base, properties: &base_props },
+            CompositeLayer { image: &top, properties: &top_props },
+        ])
+        .to_rgba8();
+        assert_eq!(*result.get_pixel(2, 3), Rgba([128, 128, 128, 255]));
+        assert_eq!(*result.get_pixel(1, 3), Rgba([0, 0, 0, 255]));
+
+        top_props.visible = false;
+        let result = composite(4, 4, &[
+            CompositeLayer { image: &base, properties: &base_props },
+            CompositeLayer { image: &top, properties: &top_props },
+        ]);
+        assert_eq!(result, base);
+    }
+
+    #[test]
+    fn test_translucent_layers_over_transparency() {
+        let top = solid(2, 2, [255, 0, 0, 128]);
+        let props = properties(BlendMode::Multiply, 1.0);
+        let result = composite(3, 2, &[CompositeLayer { image: &top, properties: &props }]).to_rgba8();
+
+        assert_eq!(*result.get_pixel(0, 0), Rgba([255, 0, 0, 128]));
+        assert_eq!(result.get_pixel(2, 0)[3], 0);
+
+        // A single full-size layer keeps its pixel format
+        let rgb = DynamicImage::new_rgb8(3, 2);
+        let props = properties(BlendMode::Normal, 1.0);
+        assert_eq!(composite(3, 2, &[CompositeLayer { image: &rgb, properties: &props }]), rgb);
+    }
+}
@@ -4,8 +4,20 @@ use image::codecs::png::{PngEncoder, CompressionType};
 use image::codecs::webp::WebPEncoder;
 use std::fs::File;
 use std::io::BufWriter;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
+use crate::core::operations::alpha::flatten;
+use crate::core::operations::quantize::{IndexedImage, index};
+use crate::core::operations::watermark::watermark;
 use crate::types::errors::AppError;
+use crate::types::operations::{Color, QuantizeParams, WatermarkParams};
+
+/// Background that transparent pixels are flattened onto for formats without alpha
+pub const DEFAULT_BACKGROUND: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+fn default_background() -> Color {
+    DEFAULT_BACKGROUND
+}
 
 /// Export format options
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -13,6 +25,7 @@ pub enum ExportFormat {
     Jpeg,
     Png,
     WebP,
+    Gif,
 }
 
 impl ExportFormat {
@@ -22,20 +35,59 @@ impl ExportFormat {
             "jpg" | "jpeg" => Ok(ExportFormat::Jpeg),
             "png" => Ok(ExportFormat::Png),
             "webp" => Ok(ExportFormat::WebP),
+            "gif" => Ok(ExportFormat::Gif),
             _ => Err(AppError::UnsupportedFormat {
-                format: format!("{}. Supported: jpeg, png, webp", ext),
+                format: format!("{}. Supported: jpeg, png, webp, gif", ext),
             }),
         }
     }
 }
 
+/// Steps applied to the exported image only, leaving the edit history untouched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Watermark stamped on every exported image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<WatermarkParams>,
+    /// Colour behind transparent pixels when exporting to JPEG
+    #[serde(default = "default_background")]
+    pub background: Color,
+    /// Palette for writing an indexed PNG; GIFs fall back to a 256 colour median cut
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<QuantizeParams>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { watermark: None, background: DEFAULT_BACKGROUND, palette: None }
+    }
+}
+
+/// Apply export-time steps to the image about to be written
+pub fn prepare_export(
+    image: DynamicImage,
+    options: &ExportOptions,
+) -> Result<DynamicImage, AppError> {
+    let mut image = image;
+    if let Some(params) = &options.watermark {
+        image = watermark(&image, params)?;
+    }
+
+    Ok(image)
+}
+
 /// Export an image to a file with the specified format and quality
 ///
+/// Files are encoded from the pixels alone, so no metadata or embedded
+/// thumbnail of the unedited original (such as an EXIF preview of a
+/// redacted area) is ever written.
+///
 /// # Arguments
 /// * `image` - The image to export
 /// * `path` - Output file path
-/// * `format` - Export format (JPEG, PNG, WebP)
+/// * `format` - Export format (JPEG, PNG, WebP, GIF)
 /// * `quality` - Quality parameter (1-100, used for JPEG and WebP)
+/// * `options` - Encoding options; the JPEG background and the PNG/GIF palette
 ///
 /// # Returns
 /// File size in bytes on success
@@ -44,6 +96,7 @@ pub fn export_image(
     path: &Path,
     format: ExportFormat,
     quality: u8,
+    options: &ExportOptions,
 ) -> Result<u64, AppError> {
     // Validate quality
     if quality == 0 || quality > 100 {
@@ -63,9 +116,17 @@ pub fn export_image(
 
     // Export based on format
     match format {
-        ExportFormat::Jpeg => export_jpeg(image, path, quality)?,
-        ExportFormat::Png => export_png(image, path)?,
+        ExportFormat::Jpeg => export_jpeg(image, path, quality, options.background)?,
+        ExportFormat::Png => match &options.palette {
+            Some(params) => export_indexed_png(&index(image, params)?, path)?,
+            None => export_png(image, path)?,
+        },
         ExportFormat::WebP => export_webp(image, path, quality)?,
+        ExportFormat::Gif => {
+            let default = QuantizeParams::default();
+            let params = options.palette.as_ref().unwrap_or(&default);
+            export_gif(&index(image, params)?, path)?
+        }
     }
 
     // Get file size
@@ -75,13 +136,22 @@ pub fn export_image(
     Ok(metadata.len())
 }
 
-/// Export image as JPEG with quality setting
-fn export_jpeg(image: &DynamicImage, path: &Path, quality: u8) -> Result<(), AppError> {
+/// Export image as JPEG with quality setting, flattening any transparency
+fn export_jpeg(
+    image: &DynamicImage,
+    path: &Path,
+    quality: u8,
+    background: Color,
+) -> Result<(), AppError> {
     let file = File::create(path)
         .map_err(|e| AppError::ImageSaveError(e.to_string()))?;
     let writer = BufWriter::new(file);
 
-    let rgb_image = image.to_rgb8();
+    let rgb_image = if image.color().has_alpha() {
+        flatten(image, background)
+    } else {
+        image.to_rgb8()
+    };
     let encoder = JpegEncoder::new_with_quality(writer, quality);
     
     encoder
@@ -143,11 +213,68 @@ fn export_webp(image: &DynamicImage, path: &Path, _quality: u8) -> Result<(), Ap
     Ok(())
 }
 
+/// Export an indexed image as a palette PNG
+fn export_indexed_png(indexed: &IndexedImage, path: &Path) -> Result<(), AppError> {
+    let file = File::create(path)
+        .map_err(|e| AppError::ImageSaveError(e.to_string()))?;
+    let writer = BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(writer, indexed.width, indexed.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::High);
+    encoder.set_palette(indexed.palette.concat());
+    if let Some(transparent) = indexed.transparent {
+        // Entries after the last one listed are opaque
+        let mut alpha = vec![255u8; transparent as usize + 1];
+        alpha[transparent as usize] = 0;
+        encoder.set_trns(alpha);
+    }
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&indexed.indices))
+        .map_err(|e| AppError::ProcessingError { details: e.to_string() })?;
+
+    Ok(())
+}
+
+/// Export an indexed image as a single frame GIF
+fn export_gif(indexed: &IndexedImage, path: &Path) -> Result<(), AppError> {
+    let (Ok(width), Ok(height)) = (u16::try_from(indexed.width), u16::try_from(indexed.height))
+    else {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "GIF images are limited to 65535x65535, got {}x{}",
+                indexed.width, indexed.height
+            ),
+        });
+    };
+
+    let file = File::create(path)
+        .map_err(|e| AppError::ImageSaveError(e.to_string()))?;
+    let writer = BufWriter::new(file);
+
+    let frame = gif::Frame {
+        width,
+        height,
+        buffer: std::borrow::Cow::Borrowed(&indexed.indices),
+        transparent: indexed.transparent,
+        ..gif::Frame::default()
+    };
+    gif::Encoder::new(writer, width, height, &indexed.palette.concat())
+        .and_then(|mut encoder| encoder.write_frame(&frame))
+        .map_err(|e| AppError::ProcessingError { details: e.to_string() })?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::RgbaImage;
+    use image::{Rgba, RgbaImage};
     use std::fs;
+    use std::io::BufReader;
     use tempfile::tempdir;
 
     #[test]
@@ -157,7 +284,8 @@ mod tests {
         assert_eq!(ExportFormat::from_extension("JPG").unwrap(), ExportFormat::Jpeg);
         assert_eq!(ExportFormat::from_extension("png").unwrap(), ExportFormat::Png);
         assert_eq!(ExportFormat::from_extension("webp").unwrap(), ExportFormat::WebP);
-        assert!(ExportFormat::from_extension("gif").is_err());
+        assert_eq!(ExportFormat::from_extension("gif").unwrap(), ExportFormat::Gif);
+        assert!(ExportFormat::from_extension("bmp").is_err());
     }
 
     #[test]
@@ -166,7 +294,8 @@ mod tests {
         let path = dir.path().join("test.jpg");
         
         let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 100));
-        let size = export_image(&image, &path, ExportFormat::Jpeg, 95).unwrap();
+        let options = ExportOptions::default();
+        let size = export_image(&image, &path, ExportFormat::Jpeg, 95, &options).unwrap();
         
         assert!(path.exists());
         assert!(size > 0);
@@ -178,7 +307,8 @@ mod tests {
         let path = dir.path().join("test.png");
         
         let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 100));
-        let size = export_image(&image, &path, ExportFormat::Png, 100).unwrap();
+        let options = ExportOptions::default();
+        let size = export_image(&image, &path, ExportFormat::Png, 100, &options).unwrap();
         
         assert!(path.exists());
         assert!(size > 0);
@@ -189,18 +319,139 @@ mod tests {
         let dir = tempdir().unwrap();
         let path = dir.path().join("test.jpg");
         let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 100));
+        let options = ExportOptions::default();
+        let export = |quality| export_image(&image, &path, ExportFormat::Jpeg, quality, &options);
         
         // Quality 0 should fail
-        assert!(export_image(&image, &path, ExportFormat::Jpeg, 0).is_err());
+        assert!(export(0).is_err());
         
         // Quality 101 should fail
-        assert!(export_image(&image, &path, ExportFormat::Jpeg, 101).is_err());
+        assert!(export(101).is_err());
         
         // Quality 1 should work
-        assert!(export_image(&image, &path, ExportFormat::Jpeg, 1).is_ok());
+        assert!(export(1).is_ok());
         
         // Quality 100 should work
         let _ = fs::remove_file(&path);
-        assert!(export_image(&image, &path, ExportFormat::Jpeg, 100).is_ok());
+        assert!(export(100).is_ok());
+    }
+
+    #[test]
+    fn test_prepare_export_watermark() {
+        use crate::types::operations::{Anchor, WatermarkMark, WatermarkPlacement};
+
+        let dir = tempdir().unwrap();
+        let logo = dir.path().join("logo.png");
+        RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255])).save(&logo).unwrap();
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255])));
+
+        // No options leaves the image untouched
+        let unchanged = prepare_export(image.clone(), &ExportOptions::default()).unwrap();
+        assert_eq!(unchanged, image);
+
+        let options = ExportOptions {
+            watermark: Some(WatermarkParams {
+                mark: WatermarkMark::Image {
+                    path: logo.to_string_lossy().into_owned(),
+                    scale: 0.1,
+                },
+                placement: WatermarkPlacement::Anchored { anchor: Anchor::TopLeft, margin: 0.0 },
+                opacity: 1.0,
+                blend_mode: Default::default(),
+            }),
+            ..ExportOptions::default()
+        };
+        let marked = prepare_export(image, &options).unwrap().to_rgba8();
+        assert_eq!(*marked.get_pixel(5, 5), Rgba([255, 255, 255, 255]));
+        assert_eq!(*marked.get_pixel(50, 50), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_export_writes_no_metadata() {
+        let dir = tempdir().unwrap();
+        let pixels = RgbaImage::from_pixel(64, 64, Rgba([10, 20, 30, 255]));
+        let image = DynamicImage::ImageRgba8(pixels);
+
+        // JPEG stuffs 0xFF bytes in scan data, so an APP1 marker can only be an EXIF block
+        let jpeg = dir.path().join("test.jpg");
+        export_image(&image, &jpeg, ExportFormat::Jpeg, 90, &ExportOptions::default()).unwrap();
+        let bytes = fs::read(&jpeg).unwrap();
+        assert!(!bytes.windows(2).any(|marker| marker == [0xFF, 0xE1]));
+
+        let png = dir.path().join("test.png");
+        export_image(&image, &png, ExportFormat::Png, 100, &ExportOptions::default()).unwrap();
+        let bytes = fs::read(&png).unwrap();
+        for chunk in [b"eXIf", b"tEXt", b"iTXt", b"zTXt"] {
+            assert!(!bytes.windows(4).any(|name| name == chunk));
+        }
+    }
+
+    #[test]
+    fn test_jpeg_flattens_transparency_onto_background() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.jpg");
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 16, |x, _| {
+            if x < 16 { Rgba([0, 0, 0, 0]) } else { Rgba([0, 0, 0, 128]) }
+        }));
+        let background = Color { r: 0, g: 200, b: 0, a: 255 };
+        let options = ExportOptions { background, ..ExportOptions::default() };
+        export_image(&image, &path, ExportFormat::Jpeg, 100, &options).unwrap();
+
+        let exported = image::open(&path).unwrap().to_rgb8();
+        let clear = exported.get_pixel(4, 8);
+        assert!(clear[0] < 8 && clear[1] > 192 && clear[2] < 8, "{:?}", clear);
+        let half = exported.get_pixel(28, 8);
+        assert!((92..=108).contains(&half[1]), "{:?}", half);
+
+        // Options from older clients without a background default to white
+        let options: ExportOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options.background, DEFAULT_BACKGROUND);
+    }
+
+    #[test]
+    fn test_png_and_webp_keep_alpha() {
+        let dir = tempdir().unwrap();
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 8, |x, _| {
+            Rgba([200, 100, 50, (x * 32) as u8])
+        }));
+
+        let formats = [("keyed.png", ExportFormat::Png), ("keyed.webp", ExportFormat::WebP)];
+        for (name, format) in formats {
+            let path = dir.path().join(name);
+            export_image(&image, &path, format, 100, &ExportOptions::default()).unwrap();
+            assert_eq!(image::open(&path).unwrap().to_rgba8(), image.to_rgba8(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_indexed_png_and_gif() {
+        use crate::types::operations::{DitherMode, PaletteSource};
+
+        let dir = tempdir().unwrap();
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, y| {
+            if x < 8 { Rgba([0, 0, 0, 0]) } else { Rgba([(x * 8) as u8, (y * 8) as u8, 64, 255]) }
+        }));
+        let palette = QuantizeParams {
+            palette: PaletteSource::MedianCut { colors: 16 },
+            dither: DitherMode::FloydSteinberg,
+        };
+        let options = ExportOptions { palette: Some(palette), ..ExportOptions::default() };
+
+        let png_path = dir.path().join("indexed.png");
+        export_image(&image, &png_path, ExportFormat::Png, 100, &options).unwrap();
+        let decoder = png::Decoder::new(BufReader::new(File::open(&png_path).unwrap()));
+        let info = decoder.read_info().unwrap().info().clone();
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert!(info.palette.unwrap().len() <= 17 * 3);
+
+        // GIFs are indexed even without a palette option
+        let gif_path = dir.path().join("indexed.gif");
+        export_image(&image, &gif_path, ExportFormat::Gif, 100, &ExportOptions::default()).unwrap();
+
+        for path in [png_path, gif_path] {
+            let decoded = image::open(&path).unwrap().to_rgba8();
+            assert_eq!(decoded.get_pixel(2, 2)[3], 0, "{:?}", path);
+            assert_eq!(decoded.get_pixel(20, 20)[3], 255, "{:?}", path);
+        }
     }
 }
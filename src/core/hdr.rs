@@ -0,0 +1,705 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, RgbImage};
+use rayon::prelude::*;
+use crate::types::errors::AppError;
+use crate::types::hdr::{HdrMerge, HdrParams};
+
+/// Most exposures a bracket may hold
+const MAX_FRAMES: usize = 9;
+
+/// Spread of Mertens' well-exposedness curve around mid-grey
+const EXPOSEDNESS_SIGMA: f32 = 0.2;
+
+/// Pixels this close to the median are noise to the threshold bitmaps
+const ALIGN_NOISE: u8 = 4;
+
+/// Alignment halves the frames down to this size, searching one pixel per level
+const ALIGN_MIN_SIZE: usize = 16;
+const ALIGN_MAX_LEVELS: usize = 6;
+
+/// Encoded values trusted when comparing two exposures
+const WELL_EXPOSED: std::ops::RangeInclusive<f32> = 0.05..=0.95;
+
+/// Float image with interleaved channels
+#[derive(Debug, Clone)]
+struct Plane {
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: Vec<f32>,
+}
+
+impl Plane {
+    /// Build a plane row by row in parallel
+    fn from_fn<F>(width: usize, height: usize, channels: usize, f: F) -> Self
+    where
+        F: Fn(usize, usize, &mut [f32]) + Sync,
+    {
+        let mut data = vec![0.0; width * height * channels];
+        data.par_chunks_mut(width * channels).enumerate().for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(channels).enumerate() {
+                f(x, y, pixel);
+            }
+        });
+        Self { width, height, channels, data }
+    }
+
+    /// Encoded RGB values in 0-1
+    fn from_image(image: &DynamicImage) -> Self {
+        let rgb = image.to_rgb32f();
+        let (width, height) = (rgb.width() as usize, rgb.height() as usize);
+        Self { width, height, channels: 3, data: rgb.into_raw() }
+    }
+
+    fn to_rgb8(&self) -> RgbImage {
+        let data = self.data.iter().map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8).collect();
+        RgbImage::from_raw(self.width as u32, self.height as u32, data)
+            .expect("plane holds width * height RGB values")
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> &[f32] {
+        let start = (y * self.width + x) * self.channels;
+        &self.data[start..start + self.channels]
+    }
+
+    /// Pixel at an offset position, repeating the edge outside the plane
+    fn clamped(&self, x: isize, y: isize) -> &[f32] {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixel(x, y)
+    }
+
+    /// Blur with a 5-tap binomial kernel and halve
+    fn reduce(&self) -> Plane {
+        const KERNEL: [f32; 5] = [0.0625, 0.25, 0.375, 0.25, 0.0625];
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+
+        let rows = Plane::from_fn(width, self.height, self.channels, |x, y, out| {
+            for (k, weight) in KERNEL.iter().enumerate() {
+                let source = self.clamped((2 * x + k) as isize - 2, y as isize);
+                for (value, sample) in out.iter_mut().zip(source) {
+                    *value += weight * sample;
+                }
+            }
+        });
+        Plane::from_fn(width, height, self.channels, |x, y, out| {
+            for (k, weight) in KERNEL.iter().enumerate() {
+                let source = rows.clamped(x as isize, (2 * y + k) as isize - 2);
+                for (value, sample) in out.iter_mut().zip(source) {
+                    *value += weight * sample;
+                }
+            }
+        })
+    }
+
+    /// Bilinear upsample to the size of the next finer level
+    fn expand(&self, width: usize, height: usize) -> Plane {
+        Plane::from_fn(width, height, self.channels, |x, y, out| {
+            let sx = ((x as f32 + 0.5) / 2.0 - 0.5).max(0.0);
+            let sy = ((y as f32 + 0.5) / 2.0 - 0.5).max(0.0);
+            let (x0, y0) = ((sx as usize).min(self.width - 1), (sy as usize).min(self.height - 1));
+            let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+            let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+
+            for (c, value) in out.iter_mut().enumerate() {
+                let top = self.pixel(x0, y0)[c] * (1.0 - fx) + self.pixel(x1, y0)[c] * fx;
+                let bottom = self.pixel(x0, y1)[c] * (1.0 - fx) + self.pixel(x1, y1)[c] * fx;
+                *value = top * (1.0 - fy) + bottom * fy;
+            }
+        })
+    }
+
+    fn add(&mut self, other: &Plane) {
+        self.data.par_iter_mut().zip(&other.data).for_each(|(value, other)| *value += other);
+    }
+
+    fn subtract(&mut self, other: &Plane) {
+        self.data.par_iter_mut().zip(&other.data).for_each(|(value, other)| *value -= other);
+    }
+
+    /// Multiply every channel by a single-channel weight plane
+    fn weighted(&self, weights: &Plane) -> Plane {
+        Plane::from_fn(self.width, self.height, self.channels, |x, y, out| {
+            let weight = weights.pixel(x, y)[0];
+            for (value, source) in out.iter_mut().zip(self.pixel(x, y)) {
+                *value = source * weight;
+            }
+        })
+    }
+}
+
+/// Pyramid levels for an image, stopping once the next level would be under `smallest`
+fn pyramid_depth(width: usize, height: usize, smallest: usize, most: usize) -> usize {
+    let mut size = width.min(height);
+    let mut depth = 1;
+    while size / 2 >= smallest && depth < most {
+        size /= 2;
+        depth += 1;
+    }
+    depth
+}
+
+fn gaussian_pyramid(plane: &Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = vec![plane.clone()];
+    for _ in 1..levels {
+        let next = pyramid[pyramid.len() - 1].reduce();
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+/// Band-pass levels from fine to coarse, ending with the coarsest Gaussian level
+fn laplacian_pyramid(plane: &Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = gaussian_pyramid(plane, levels);
+    for level in 0..levels - 1 {
+        let (width, height) = (pyramid[level].width, pyramid[level].height);
+        let expanded = pyramid[level + 1].expand(width, height);
+        pyramid[level].subtract(&expanded);
+    }
+    pyramid
+}
+
+fn collapse(mut pyramid: Vec<Plane>) -> Plane {
+    let mut image = pyramid.pop().expect("pyramid has at least one level");
+    while let Some(mut level) = pyramid.pop() {
+        level.add(&image.expand(level.width, level.height));
+        image = level;
+    }
+    image
+}
+
+/// Check the merge settings against the number of exposures
+pub fn validate(params: &HdrParams, frames: usize) -> Result<(), AppError> {
+    if !(2..=MAX_FRAMES).contains(&frames) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "A bracket needs between 2 and {} exposures, got {}",
+                MAX_FRAMES, frames
+            ),
+        });
+    }
+
+    match &params.merge {
+        HdrMerge::Fusion { contrast, saturation, exposure } => {
+            let weights = [*contrast, *saturation, *exposure];
+            if !weights.iter().all(|weight| (0.0..=10.0).contains(weight)) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Fusion weights must be between 0 and 10, got {:?}", weights),
+                });
+            }
+        }
+        HdrMerge::ToneMapped { exposure_times, key, white } => {
+            if let Some(times) = exposure_times {
+                if times.len() != frames {
+                    return Err(AppError::InvalidOperation {
+                        details: format!(
+                            "Expected {} exposure times, one per exposure, got {}",
+                            frames,
+                            times.len()
+                        ),
+                    });
+                }
+                if !times.iter().all(|time| time.is_finite() && *time > 0.0) {
+                    return Err(AppError::InvalidOperation {
+                        details: format!("Exposure times must be positive, got {:?}", times),
+                    });
+                }
+            }
+            if !(0.01..=1.0).contains(key) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Key must be between 0.01 and 1, got {}", key),
+                });
+            }
+            if let Some(white) = white
+                && !(white.is_finite() && *white > 0.0)
+            {
+                return Err(AppError::InvalidOperation {
+                    details: format!("White point must be positive, got {}", white),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Median threshold bitmap with its mask of pixels far enough from the median to count
+struct ThresholdBitmap {
+    width: usize,
+    height: usize,
+    above: Vec<bool>,
+    counted: Vec<bool>,
+}
+
+impl ThresholdBitmap {
+    fn new(gray: &GrayImage) -> Self {
+        let mut histogram = [0usize; 256];
+        for pixel in gray.pixels() {
+            histogram[pixel[0] as usize] += 1;
+        }
+        let half = gray.pixels().len() / 2;
+        let mut seen = 0;
+        let median = histogram
+            .iter()
+            .position(|count| {
+                seen += count;
+                seen > half
+            })
+            .unwrap_or(128) as u8;
+
+        Self {
+            width: gray.width() as usize,
+            height: gray.height() as usize,
+            above: gray.pixels().map(|pixel| pixel[0] > median).collect(),
+            counted: gray.pixels().map(|pixel| pixel[0].abs_diff(median) > ALIGN_NOISE).collect(),
+        }
+    }
+
+    /// Fraction of compared pixels that disagree with `frame` moved by `offset`
+    fn mismatch(&self, frame: &ThresholdBitmap, (dx, dy): (isize, isize)) -> f64 {
+        let (differing, compared) = (0..self.height)
+            .into_par_iter()
+            .map(|y| {
+                let fy = y as isize + dy;
+                if fy < 0 || fy >= frame.height as isize {
+                    return (0usize, 0usize);
+                }
+                let mut counts = (0, 0);
+                for x in 0..self.width {
+                    let fx = x as isize + dx;
+                    if fx < 0 || fx >= frame.width as isize {
+                        continue;
+                    }
+                    let (i, j) = (y * self.width + x, fy as usize * frame.width + fx as usize);
+                    if self.counted[i] && frame.counted[j] {
+                        counts.0 += (self.above[i] != frame.above[j]) as usize;
+                        counts.1 += 1;
+                    }
+                }
+                counts
+            })
+            .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+        if compared == 0 { 1.0 } else { differing as f64 / compared as f64 }
+    }
+}
+
+/// Average 2x2 blocks
+fn halve(gray: &GrayImage) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    GrayImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(|(dx, dy)| {
+                let sx = (2 * x + dx).min(width - 1);
+                let sy = (2 * y + dy).min(height - 1);
+                gray.get_pixel(sx, sy)[0] as u32
+            })
+            .sum();
+        Luma([((sum + 2) / 4) as u8])
+    })
+}
+
+/// Offset at which `frame` lines up with `reference`
+///
+/// Ward's median threshold bitmaps split each exposure at its own median, so
+/// frames of different brightness still compare. The search starts on the
+/// smallest level and refines by one pixel on each larger one.
+fn find_offset(reference: &GrayImage, frame: &GrayImage) -> (isize, isize) {
+    let (width, height) = (reference.width() as usize, reference.height() as usize);
+    let levels = pyramid_depth(width, height, ALIGN_MIN_SIZE, ALIGN_MAX_LEVELS);
+
+    let mut pyramids = (vec![reference.clone()], vec![frame.clone()]);
+    for level in 1..levels {
+        pyramids.0.push(halve(&pyramids.0[level - 1]));
+        pyramids.1.push(halve(&pyramids.1[level - 1]));
+    }
+
+    let mut offset = (0, 0);
+    for level in (0..levels).rev() {
+        let reference = ThresholdBitmap::new(&pyramids.0[level]);
+        let frame = ThresholdBitmap::new(&pyramids.1[level]);
+        let centre = (offset.0 * 2, offset.1 * 2);
+
+        offset = centre;
+        let mut best = reference.mismatch(&frame, centre);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let candidate = (centre.0 + dx, centre.1 + dy);
+                let error = reference.mismatch(&frame, candidate);
+                if error < best {
+                    (offset, best) = (candidate, error);
+                }
+            }
+        }
+    }
+
+    offset
+}
+
+/// Shift every frame onto the middle one and crop all to the area they share
+fn align(frames: &[DynamicImage]) -> Result<Vec<DynamicImage>, AppError> {
+    let grays: Vec<GrayImage> = frames.par_iter().map(|frame| frame.to_luma8()).collect();
+    let reference = frames.len() / 2;
+    let offsets: Vec<(isize, isize)> = grays
+        .iter()
+        .enumerate()
+        .map(|(i, gray)| if i == reference { (0, 0) } else { find_offset(&grays[reference], gray) })
+        .collect();
+
+    let (width, height) = frames[reference].dimensions();
+    let (width, height) = (width as isize, height as isize);
+    let left = offsets.iter().map(|(dx, _)| (-dx).max(0)).max().unwrap_or(0);
+    let top = offsets.iter().map(|(_, dy)| (-dy).max(0)).max().unwrap_or(0);
+    let right = offsets.iter().map(|(dx, _)| (width - dx).min(width)).min().unwrap_or(width);
+    let bottom = offsets.iter().map(|(_, dy)| (height - dy).min(height)).min().unwrap_or(height);
+    if right <= left || bottom <= top {
+        return Err(AppError::ProcessingError {
+            details: "Exposures do not overlap once aligned".to_string(),
+        });
+    }
+
+    Ok(frames
+        .iter()
+        .zip(&offsets)
+        .map(|(frame, (dx, dy))| {
+            frame.crop_imm(
+                (left + dx) as u32,
+                (top + dy) as u32,
+                (right - left) as u32,
+                (bottom - top) as u32,
+            )
+        })
+        .collect())
+}
+
+/// Mertens exposure fusion
+///
+/// Each frame is weighted per pixel by local contrast, colour saturation and
+/// closeness to mid-grey, each raised to its own power. Blending the frames'
+/// Laplacian pyramids with Gaussian pyramids of the weights avoids seams where
+/// the weights change quickly.
+fn fuse(frames: &[Plane], contrast: f32, saturation: f32, exposure: f32) -> Plane {
+    let (width, height) = (frames[0].width, frames[0].height);
+
+    let mut weights: Vec<Plane> = frames
+        .iter()
+        .map(|frame| {
+            let gray = Plane::from_fn(width, height, 1, |x, y, out| {
+                let pixel = frame.pixel(x, y);
+                out[0] = 0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2];
+            });
+
+            Plane::from_fn(width, height, 1, |x, y, out| {
+                let (sx, sy) = (x as isize, y as isize);
+                let laplacian = gray.clamped(sx - 1, sy)[0]
+                    + gray.clamped(sx + 1, sy)[0]
+                    + gray.clamped(sx, sy - 1)[0]
+                    + gray.clamped(sx, sy + 1)[0]
+                    - 4.0 * gray.pixel(x, y)[0];
+
+                let pixel = frame.pixel(x, y);
+                let mean = pixel.iter().sum::<f32>() / 3.0;
+                let spread = (pixel.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / 3.0).sqrt();
+                let exposedness: f32 = pixel
+                    .iter()
+                    .map(|v| (-(v - 0.5).powi(2) / (2.0 * EXPOSEDNESS_SIGMA.powi(2))).exp())
+                    .product();
+
+                out[0] = laplacian.abs().powf(contrast)
+                    * spread.powf(saturation)
+                    * exposedness.powf(exposure)
+                    + 1e-12;
+            })
+        })
+        .collect();
+
+    for i in 0..width * height {
+        let total: f32 = weights.iter().map(|weight| weight.data[i]).sum();
+        for weight in weights.iter_mut() {
+            weight.data[i] /= total;
+        }
+    }
+
+    let levels = pyramid_depth(width, height, 1, 12);
+    let mut blended: Option<Vec<Plane>> = None;
+    for (frame, weight) in frames.iter().zip(&weights) {
+        let bands = laplacian_pyramid(frame, levels);
+        let weight = gaussian_pyramid(weight, levels);
+        let contribution: Vec<Plane> =
+            bands.iter().zip(&weight).map(|(band, weight)| band.weighted(weight)).collect();
+
+        match blended.as_mut() {
+            Some(blended) => {
+                for (level, band) in blended.iter_mut().zip(&contribution) {
+                    level.add(band);
+                }
+            }
+            None => blended = Some(contribution),
+        }
+    }
+
+    collapse(blended.expect("bracket has at least two frames"))
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn luminance(pixel: &[f32]) -> f32 {
+    0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2]
+}
+
+/// Relative exposure of each frame, estimated from the frames themselves
+///
+/// Frames are ordered by brightness and each is compared with the next darker
+/// one, taking the median ratio of linear values both exposed well.
+fn estimate_exposure_times(frames: &[Plane]) -> Vec<f32> {
+    let brightness: Vec<f32> = frames
+        .iter()
+        .map(|frame| {
+            frame.data.iter().map(|v| srgb_to_linear(*v)).sum::<f32>() / frame.data.len() as f32
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by(|a, b| brightness[*a].total_cmp(&brightness[*b]));
+
+    let mut times = vec![1.0; frames.len()];
+    for pair in order.windows(2) {
+        let (darker, brighter) = (pair[0], pair[1]);
+        let mut ratios: Vec<f32> = frames[brighter]
+            .data
+            .iter()
+            .zip(&frames[darker].data)
+            .filter(|(bright, dark)| WELL_EXPOSED.contains(*bright) && WELL_EXPOSED.contains(*dark))
+            .map(|(bright, dark)| srgb_to_linear(*bright) / srgb_to_linear(*dark))
+            .collect();
+
+        let ratio = if ratios.is_empty() {
+            brightness[brighter] / brightness[darker].max(1e-6)
+        } else {
+            let middle = ratios.len() / 2;
+            *ratios.select_nth_unstable_by(middle, |a, b| a.total_cmp(b)).1
+        };
+        times[brighter] = times[darker] * ratio.max(1.0);
+    }
+
+    times
+}
+
+/// Linear scene radiance, assuming an sRGB camera response
+///
+/// Every frame contributes its linear value divided by its exposure time,
+/// weighted by distance from black and white so clipped values count least.
+fn radiance(frames: &[Plane], times: &[f32]) -> Plane {
+    let by_time = |a: &(usize, &f32), b: &(usize, &f32)| a.1.total_cmp(b.1);
+    let shortest = times.iter().enumerate().min_by(by_time).map_or(0, |(i, _)| i);
+    let longest = times.iter().enumerate().max_by(by_time).map_or(0, |(i, _)| i);
+
+    Plane::from_fn(frames[0].width, frames[0].height, 3, |x, y, out| {
+        for (c, value) in out.iter_mut().enumerate() {
+            let (mut sum, mut total) = (0.0, 0.0);
+            for (frame, time) in frames.iter().zip(times) {
+                let encoded = frame.pixel(x, y)[c];
+                let weight = encoded.min(1.0 - encoded);
+                sum += weight * srgb_to_linear(encoded) / time;
+                total += weight;
+            }
+
+            *value = if total > 0.0 {
+                sum / total
+            } else {
+                // Clipped in every frame; the shortest exposure is the least
+                // wrong for highlights and the longest for shadows
+                let frame = if frames[longest].pixel(x, y)[c] >= 0.5 { shortest } else { longest };
+                srgb_to_linear(frames[frame].pixel(x, y)[c]) / times[frame]
+            };
+        }
+    })
+}
+
+/// Global Reinhard operator, returning encoded values
+///
+/// Luminance is scaled so its log-average lands on `key`, then compressed
+/// with `L (1 + L / white²) / (1 + L)`. Colour keeps its ratio to luminance.
+fn reinhard(radiance: &Plane, key: f32, white: Option<f32>) -> Plane {
+    let log_sum: f64 = radiance
+        .data
+        .par_chunks(3)
+        .map(|pixel| (1e-4 + luminance(pixel) as f64).ln())
+        .sum();
+    let average = (log_sum / (radiance.width * radiance.height) as f64).exp() as f32;
+    let scale = key / average;
+    let white = white
+        .unwrap_or_else(|| {
+            radiance.data.chunks(3).map(|pixel| luminance(pixel) * scale).fold(0.0, f32::max)
+        })
+        .max(1e-6);
+
+    Plane::from_fn(radiance.width, radiance.height, 3, |x, y, out| {
+        let pixel = radiance.pixel(x, y);
+        let world = luminance(pixel);
+        let scaled = world * scale;
+        let display = scaled * (1.0 + scaled / (white * white)) / (1.0 + scaled);
+        let ratio = if world > 0.0 { display / world } else { 0.0 };
+        for (value, channel) in out.iter_mut().zip(pixel) {
+            *value = linear_to_srgb((channel * ratio).clamp(0.0, 1.0));
+        }
+    })
+}
+
+/// Merge bracketed exposures of one scene into a single image
+pub fn merge_bracket(
+    frames: Vec<DynamicImage>,
+    params: &HdrParams,
+) -> Result<DynamicImage, AppError> {
+    validate(params, frames.len())?;
+
+    let size = frames[0].dimensions();
+    if let Some(frame) = frames.iter().find(|frame| frame.dimensions() != size) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "All exposures must be the same size; expected {}x{}, got {}x{}",
+                size.0,
+                size.1,
+                frame.width(),
+                frame.height()
+            ),
+        });
+    }
+
+    let frames = if params.align { align(&frames)? } else { frames };
+    let planes: Vec<Plane> = frames.iter().map(Plane::from_image).collect();
+
+    let merged = match &params.merge {
+        HdrMerge::Fusion { contrast, saturation, exposure } => {
+            fuse(&planes, *contrast, *saturation, *exposure)
+        }
+        HdrMerge::ToneMapped { exposure_times, key, white } => {
+            let times = exposure_times.clone().unwrap_or_else(|| estimate_exposure_times(&planes));
+            reinhard(&radiance(&planes, &times), *key, *white)
+        }
+    };
+
+    Ok(DynamicImage::ImageRgb8(merged.to_rgb8()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Blotchy pattern with detail at several scales, in linear light 0-1
+    fn scene(x: u32, y: u32) -> f32 {
+        let hash = |x: u32, y: u32| {
+            let h = x.wrapping_mul(374761393) ^ y.wrapping_mul(668265263);
+            (h.wrapping_mul(1274126177) >> 24) as f32 / 255.0
+        };
+        (hash(x / 8, y / 8) * 0.6 + hash(x / 3, y / 3) * 0.3 + hash(x, y) * 0.1).powi(2)
+    }
+
+    /// The scene photographed at an exposure, cropped at an offset
+    fn exposure(time: f32, left: u32, top: u32, width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let value = linear_to_srgb((scene(x + left, y + top) * time).min(1.0));
+            let encoded = (value * 255.0).round() as u8;
+            Rgb([encoded, encoded, encoded.saturating_sub(10)])
+        }))
+    }
+
+    fn mean(image: &DynamicImage) -> f32 {
+        let gray = image.to_luma8();
+        gray.pixels().map(|pixel| pixel[0] as f32).sum::<f32>() / gray.pixels().len() as f32
+    }
+
+    #[test]
+    fn test_alignment_recovers_translation() {
+        let reference = exposure(1.0, 8, 8, 64, 64).to_luma8();
+        // Darker frame whose content sits 3 pixels left and 2 pixels lower
+        let shifted = exposure(0.4, 11, 6, 64, 64).to_luma8();
+        assert_eq!(find_offset(&reference, &shifted), (-3, 2));
+
+        let frames = vec![exposure(0.4, 11, 6, 64, 64), exposure(1.0, 8, 8, 64, 64)];
+        let aligned = align(&frames).unwrap();
+        assert_eq!(aligned[0].dimensions(), (61, 62));
+        assert_eq!(aligned[1].dimensions(), (61, 62));
+        // Both frames now start at the same scene position
+        let first = |image: &DynamicImage| image.to_rgb8().get_pixel(0, 0)[0];
+        assert_eq!(first(&aligned[1]), first(&exposure(1.0, 11, 8, 1, 1)));
+        assert_eq!(first(&aligned[0]), first(&exposure(0.4, 11, 8, 1, 1)));
+    }
+
+    #[test]
+    fn test_fusion_favours_well_exposed_frames() {
+        let under = exposure(0.25, 0, 0, 48, 48);
+        let over = exposure(8.0, 0, 0, 48, 48);
+        let params = HdrParams { merge: HdrMerge::default(), align: false };
+        let fused = merge_bracket(vec![under.clone(), over.clone()], &params).unwrap();
+
+        assert_eq!(fused.dimensions(), (48, 48));
+        let distance = |image: &DynamicImage| (mean(image) - 128.0).abs();
+        assert!(distance(&fused) < distance(&under), "{} vs {}", mean(&fused), mean(&under));
+        assert!(distance(&fused) < distance(&over), "{} vs {}", mean(&fused), mean(&over));
+
+        // With only well-exposedness weighted, a mid-grey frame wins outright
+        let grey = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([128, 128, 128])));
+        let black = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([0, 0, 0])));
+        let merge = HdrMerge::Fusion { contrast: 0.0, saturation: 0.0, exposure: 1.0 };
+        let params = HdrParams { merge, align: false };
+        let fused = merge_bracket(vec![grey, black], &params).unwrap().to_rgb8();
+        let centre = fused.get_pixel(4, 4);
+        assert!(fused.pixels().all(|pixel| pixel[0].abs_diff(128) <= 1), "{:?}", centre);
+    }
+
+    #[test]
+    fn test_tone_mapping_keeps_scene_order() {
+        // Radiance ramp far wider than one exposure can hold
+        let ramp = |time: f32| {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(64, 1, |x, _| {
+                let radiance = 0.002 * 1.12f32.powi(x as i32);
+                let encoded = (linear_to_srgb((radiance * time).min(1.0)) * 255.0).round() as u8;
+                Rgb([encoded; 3])
+            }))
+        };
+        let frames = vec![ramp(0.25), ramp(1.0), ramp(4.0)];
+
+        let planes: Vec<Plane> = frames.iter().map(Plane::from_image).collect();
+        let times = estimate_exposure_times(&planes);
+        assert!((times[1] / times[0] - 4.0).abs() < 0.4, "{:?}", times);
+        assert!((times[2] / times[1] - 4.0).abs() < 0.4, "{:?}", times);
+
+        for exposure_times in [None, Some(vec![0.25, 1.0, 4.0])] {
+            let merge = HdrMerge::ToneMapped { exposure_times, key: 0.18, white: None };
+            let params = HdrParams { merge, align: false };
+            let mapped = merge_bracket(frames.clone(), &params).unwrap().to_rgb8();
+            let values: Vec<u8> = mapped.pixels().map(|pixel| pixel[1]).collect();
+            assert!(values.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", values);
+            assert!(values[0] < 40 && values[63] == 255, "{:?}", values);
+            // Every exposure alone clips or crushes part of the ramp; the merge separates them
+            let distinct = values.iter().collect::<std::collections::HashSet<_>>().len();
+            assert!(distinct > 40, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_brackets() {
+        let frame = exposure(1.0, 0, 0, 16, 16);
+        let params = HdrParams::default();
+        assert!(merge_bracket(vec![frame.clone()], &params).is_err());
+
+        let small = exposure(1.0, 0, 0, 8, 16);
+        assert!(merge_bracket(vec![frame.clone(), small], &params).is_err());
+
+        let exposure_times = Some(vec![1.0]);
+        let merge = HdrMerge::ToneMapped { exposure_times, key: 0.18, white: None };
+        assert!(validate(&HdrParams { merge, align: true }, 2).is_err());
+        let merge = HdrMerge::ToneMapped { exposure_times: None, key: 2.0, white: None };
+        assert!(validate(&HdrParams { merge, align: true }, 2).is_err());
+        let merge = HdrMerge::Fusion { contrast: -1.0, saturation: 1.0, exposure: 1.0 };
+        assert!(validate(&HdrParams { merge, align: true }, 2).is_err());
+        assert!(validate(&params, 10).is_err());
+    }
+}
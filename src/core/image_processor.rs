@@ -1,8 +1,18 @@
 use image::DynamicImage;
 
 use crate::types::errors::AppError;
-use crate::types::operations::{AdjustmentParams, CropRect, FilterType, OperationType, TransformType};
-use crate::core::operations::{adjustments, crop, filters, transform};
+use crate::types::operations::{
+    AdjustmentParams, AlphaOperation, CanvasParams, ChromaKeyParams, CloneStampParams,
+    ContentAwareResizeParams, CropParams, DrawParams, EditOperation, EraseParams, FilterType,
+    FrameParams, HealParams, LensParams, Mask, OperationType, PerspectiveParams, PixelArtScaler,
+    QuantizeParams, RedEyeParams, RedactParams, ReplaceColorParams, ResizeParams, SmartCropParams,
+    TextParams, TransformType, WatermarkParams,
+};
+use crate::core::operations::{
+    adjustments, alpha, auto_crop, canvas, chroma_key, clone_stamp, crop, draw, filters, frame,
+    heal, lens, mask, perspective, pixel_art, quantize, red_eye, redact, replace_color, resize,
+    seam_carving, selection, smart_crop, text, transform, watermark,
+};
 
 /// Image processor for applying operations
 pub struct ImageProcessor;
@@ -95,8 +105,142 @@ impl ImageProcessor {
     }
     
     /// Apply a crop to an image
-    pub fn apply_crop(img: &DynamicImage, rect: &CropRect) -> Result<DynamicImage, AppError> {
-        crop::crop_image(img, rect)
+    pub fn apply_crop(img: &DynamicImage, params: &CropParams) -> Result<DynamicImage, AppError> {
+        crop::crop(img, params)
+    }
+    
+    /// Apply a resize to an image
+    pub fn apply_resize(img: &DynamicImage, params: &ResizeParams) -> Result<DynamicImage, AppError> {
+        resize::resize(img, params)
+    }
+    
+    /// Apply a canvas change to an image
+    pub fn apply_canvas(img: &DynamicImage, params: &CanvasParams) -> Result<DynamicImage, AppError> {
+        canvas::canvas(img, params)
+    }
+    
+    /// Apply a perspective correction to an image
+    pub fn apply_perspective(img: &DynamicImage, params: &PerspectiveParams) -> Result<DynamicImage, AppError> {
+        perspective::perspective(img, params)
+    }
+    
+    /// Apply a lens correction to an image
+    pub fn apply_lens(img: &DynamicImage, params: &LensParams) -> Result<DynamicImage, AppError> {
+        lens::lens_correction(img, params)
+    }
+    
+    /// Apply a content-aware resize to an image
+    pub fn apply_content_aware_resize(
+        img: &DynamicImage,
+        params: &ContentAwareResizeParams,
+    ) -> Result<DynamicImage, AppError> {
+        seam_carving::content_aware_resize(img, params)
+    }
+    
+    /// Apply a smart crop to an image
+    pub fn apply_smart_crop(img: &DynamicImage, params: &SmartCropParams) -> Result<DynamicImage, AppError> {
+        smart_crop::smart_crop(img, params)
+    }
+    
+    /// Apply spot healing to an image
+    pub fn apply_heal(img: &DynamicImage, params: &HealParams) -> Result<DynamicImage, AppError> {
+        heal::heal(img, params)
+    }
+    
+    /// Apply clone stamp strokes to an image
+    pub fn apply_clone_stamp(img: &DynamicImage, params: &CloneStampParams) -> Result<DynamicImage, AppError> {
+        clone_stamp::clone_stamp(img, params)
+    }
+    
+    /// Apply red-eye removal to an image
+    pub fn apply_red_eye(img: &DynamicImage, params: &RedEyeParams) -> Result<DynamicImage, AppError> {
+        red_eye::red_eye(img, params)
+    }
+    
+    /// Erase the selected pixels of an image
+    pub fn apply_erase(img: &DynamicImage, params: &EraseParams) -> Result<DynamicImage, AppError> {
+        selection::erase(img, params)
+    }
+    
+    /// Draw text onto an image
+    pub fn apply_text(img: &DynamicImage, params: &TextParams) -> Result<DynamicImage, AppError> {
+        text::text(img, params)
+    }
+    
+    /// Overlay a watermark on an image
+    pub fn apply_watermark(img: &DynamicImage, params: &WatermarkParams) -> Result<DynamicImage, AppError> {
+        watermark::watermark(img, params)
+    }
+    
+    /// Draw shapes onto an image
+    pub fn apply_draw(img: &DynamicImage, params: &DrawParams) -> Result<DynamicImage, AppError> {
+        draw::draw(img, params)
+    }
+    
+    /// Hide regions of an image
+    pub fn apply_redact(img: &DynamicImage, params: &RedactParams) -> Result<DynamicImage, AppError> {
+        redact::redact(img, params)
+    }
+    
+    /// Frame an image with a border, strokes, rounded corners and a shadow
+    pub fn apply_frame(img: &DynamicImage, params: &FrameParams) -> Result<DynamicImage, AppError> {
+        frame::frame(img, params)
+    }
+    
+    /// Key out a colour or a uniform background to transparency
+    pub fn apply_chroma_key(img: &DynamicImage, params: &ChromaKeyParams) -> Result<DynamicImage, AppError> {
+        chroma_key::chroma_key(img, params)
+    }
+    
+    /// Shift pixels near one colour towards another
+    pub fn apply_replace_color(img: &DynamicImage, params: &ReplaceColorParams) -> Result<DynamicImage, AppError> {
+        replace_color::replace_color(img, params)
+    }
+    
+    /// Work on the alpha channel explicitly
+    pub fn apply_alpha(img: &DynamicImage, operation: &AlphaOperation) -> Result<DynamicImage, AppError> {
+        alpha::alpha(img, operation)
+    }
+    
+    /// Reduce an image to a palette, optionally dithered
+    pub fn apply_quantize(img: &DynamicImage, params: &QuantizeParams) -> Result<DynamicImage, AppError> {
+        quantize::quantize(img, params)
+    }
+    
+    /// Upscale pixel art with an edge-preserving scaler
+    pub fn apply_upscale(img: &DynamicImage, scaler: PixelArtScaler) -> Result<DynamicImage, AppError> {
+        pixel_art::upscale(img, scaler)
+    }
+    
+    /// Resolve automatic operations against an image
+    ///
+    /// Auto-trim, auto-straighten and transparent trim become the concrete crop
+    /// they produce on `img`, while smart crop and automatic red-eye record the
+    /// window or circles they picked, so replaying history does not depend on
+    /// re-running detection. Watermark logos, alpha channels and palettes
+    /// taken from a file are embedded rather than read again. Other operations
+    /// are returned unchanged.
+    pub fn resolve_operation(img: &DynamicImage, operation_type: &OperationType) -> Result<OperationType, AppError> {
+        let resolved = match operation_type {
+            OperationType::AutoTrim(params) => {
+                OperationType::Crop(auto_crop::trim_rect(img, params.tolerance).into())
+            }
+            OperationType::AutoStraighten(params) => OperationType::Crop(auto_crop::straighten(img, params)?),
+            OperationType::Alpha(AlphaOperation::Trim { threshold }) => {
+                OperationType::Crop(alpha::trim_rect(img, *threshold)?.into())
+            }
+            OperationType::Alpha(AlphaOperation::Replace { source, invert }) => {
+                let source = alpha::resolve_source(source)?;
+                OperationType::Alpha(AlphaOperation::Replace { source, invert: *invert })
+            }
+            OperationType::SmartCrop(params) => OperationType::SmartCrop(smart_crop::resolve(img, params)?),
+            OperationType::RedEye(params) => OperationType::RedEye(red_eye::resolve(img, params)),
+            OperationType::Watermark(params) => OperationType::Watermark(watermark::resolve(params)?),
+            OperationType::Quantize(params) => OperationType::Quantize(quantize::resolve(params)?),
+            other => other.clone(),
+        };
+        
+        Ok(resolved)
     }
     
     /// Apply an operation to an image
@@ -105,7 +249,30 @@ impl ImageProcessor {
             OperationType::Filter(filter) => Self::apply_filter(img, filter),
             OperationType::Adjustment(params) => Self::apply_adjustment(img, params),
             OperationType::Transform(transform) => Self::apply_transform(img, transform),
-            OperationType::Crop(rect) => Self::apply_crop(img, rect),
+            OperationType::Crop(params) => Self::apply_crop(img, params),
+            OperationType::Resize(params) => Self::apply_resize(img, params),
+            OperationType::Canvas(params) => Self::apply_canvas(img, params),
+            OperationType::Perspective(params) => Self::apply_perspective(img, params),
+            OperationType::Lens(params) => Self::apply_lens(img, params),
+            OperationType::ContentAwareResize(params) => Self::apply_content_aware_resize(img, params),
+            OperationType::SmartCrop(params) => Self::apply_smart_crop(img, params),
+            OperationType::Heal(params) => Self::apply_heal(img, params),
+            OperationType::CloneStamp(params) => Self::apply_clone_stamp(img, params),
+            OperationType::RedEye(params) => Self::apply_red_eye(img, params),
+            OperationType::Erase(params) => Self::apply_erase(img, params),
+            OperationType::Text(params) => Self::apply_text(img, params),
+            OperationType::Watermark(params) => Self::apply_watermark(img, params),
+            OperationType::Draw(params) => Self::apply_draw(img, params),
+            OperationType::Redact(params) => Self::apply_redact(img, params),
+            OperationType::Frame(params) => Self::apply_frame(img, params),
+            OperationType::ChromaKey(params) => Self::apply_chroma_key(img, params),
+            OperationType::ReplaceColor(params) => Self::apply_replace_color(img, params),
+            OperationType::Alpha(operation) => Self::apply_alpha(img, operation),
+            OperationType::Quantize(params) => Self::apply_quantize(img, params),
+            OperationType::Upscale(scaler) => Self::apply_upscale(img, *scaler),
+            OperationType::AutoTrim(_) | OperationType::AutoStraighten(_) => {
+                Self::apply_operation(img, &Self::resolve_operation(img, operation_type)?)
+            }
         }
     }
     
@@ -123,11 +290,48 @@ impl ImageProcessor {
         
         Ok(current)
     }
+    
+    /// Apply an operation, blending it with the input through a mask if given
+    pub fn apply_masked_operation(
+        img: &DynamicImage,
+        operation_type: &OperationType,
+        operation_mask: Option<&Mask>,
+    ) -> Result<DynamicImage, AppError> {
+        // A soft mask would blend original pixels back into the redacted area
+        if operation_mask.is_some() && matches!(operation_type, OperationType::Redact(_)) {
+            return Err(AppError::InvalidOperation {
+                details: "Redaction cannot be masked; list the areas as its regions".to_string(),
+            });
+        }
+        
+        let result = Self::apply_operation(img, operation_type)?;
+        
+        match operation_mask {
+            Some(operation_mask) => mask::apply_masked(img, &result, operation_mask),
+            None => Ok(result),
+        }
+    }
+    
+    /// Apply multiple edit operations in sequence, honouring their masks
+    pub fn apply_edits(img: &DynamicImage, edits: &[EditOperation]) -> Result<DynamicImage, AppError> {
+        let mut current = img.clone();
+        
+        for edit in edits {
+            current = Self::apply_masked_operation(&current, &edit.operation, edit.mask.as_ref())?;
+        }
+        
+        Ok(current)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::operations::{
+        AdjustmentParams, AutoTrimParams, CropRect, MaskLayer, MaskMode, MaskShape, Point,
+        RedactMode, Region,
+    };
+    use image::{Rgb, RgbImage};
     
     #[test]
     fn test_apply_grayscale() {
@@ -136,6 +340,95 @@ mod tests {
         assert!(result.is_ok());
     }
     
+    #[test]
+    fn test_resolve_auto_trim() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(10, 10, |x, y| {
+            if x >= 2 && y >= 3 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 0]) }
+        }));
+        let operation = OperationType::AutoTrim(AutoTrimParams { tolerance: 0 });
+        
+        let resolved = ImageProcessor::resolve_operation(&img, &operation).unwrap();
+        assert_eq!(
+            resolved,
+            OperationType::Crop(CropRect { x: 2, y: 3, width: 8, height: 7 }.into())
+        );
+        
+        let result = ImageProcessor::apply_operation(&img, &operation).unwrap();
+        assert_eq!(result.width(), 8);
+        assert_eq!(result.height(), 7);
+    }
+    
+    #[test]
+    fn test_resolve_transparent_trim() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(10, 10, |x, y| {
+            image::Rgba([255, 0, 0, if x < 4 && y >= 5 { 255 } else { 0 }])
+        }));
+        let operation = OperationType::Alpha(AlphaOperation::Trim { threshold: 0 });
+        
+        let resolved = ImageProcessor::resolve_operation(&img, &operation).unwrap();
+        assert_eq!(
+            resolved,
+            OperationType::Crop(CropRect { x: 0, y: 5, width: 4, height: 5 }.into())
+        );
+    }
+    
+    #[test]
+    fn test_apply_edits_with_mask() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(10, 4, Rgb([100, 100, 100])));
+        let brighten = OperationType::Adjustment(AdjustmentParams {
+            brightness: Some(2.0),
+            contrast: None,
+            saturation: None,
+            hue: None,
+            gamma: None,
+        });
+        let left_half = Mask {
+            layers: vec![MaskLayer {
+                shape: MaskShape::Linear { start: Point { x: 4.0, y: 0.0 }, end: Point { x: 5.0, y: 0.0 } },
+                mode: MaskMode::Add,
+                invert: false,
+            }],
+            invert: false,
+        };
+        let edit = EditOperation {
+            id: "1".to_string(),
+            operation: brighten,
+            timestamp: 0,
+            mask: Some(left_half),
+        };
+        
+        let result = ImageProcessor::apply_edits(&img, std::slice::from_ref(&edit)).unwrap().to_rgba8();
+        assert!(result.get_pixel(1, 1)[0] > 100);
+        assert_eq!(result.get_pixel(8, 1)[0], 100);
+        
+        let json = serde_json::to_value(&edit).unwrap();
+        assert_eq!(json["mask"]["layers"][0]["shape"]["type"], "linear");
+        assert_eq!(serde_json::from_value::<EditOperation>(json).unwrap(), edit);
+        
+        let unmasked = EditOperation { mask: None, ..edit };
+        assert!(serde_json::to_value(&unmasked).unwrap().get("mask").is_none());
+    }
+    
+    #[test]
+    fn test_redact_rejects_masks() {
+        let img = DynamicImage::new_rgb8(10, 10);
+        let redact = OperationType::Redact(RedactParams {
+            regions: vec![Region::Rect { x: 0.0, y: 0.0, width: 4.0, height: 4.0 }],
+            mode: RedactMode::Pixelate { block_size: 2 },
+        });
+        let mask = Mask {
+            layers: vec![MaskLayer {
+                shape: MaskShape::Luminance { min: 0.0, max: 1.0, feather: 0.0 },
+                mode: MaskMode::Add,
+                invert: false,
+            }],
+            invert: false,
+        };
+        
+        assert!(ImageProcessor::apply_masked_operation(&img, &redact, None).is_ok());
+        assert!(ImageProcessor::apply_masked_operation(&img, &redact, Some(&mask)).is_err());
+    }
+    
     #[test]
     fn test_blur_validation() {
         let img = DynamicImage::new_rgb8(10, 10);
@@ -1,4 +1,6 @@
+pub mod compositor;
 pub mod export_engine;
+pub mod hdr;
 pub mod history_manager;
 pub mod image_processor;
 pub mod operations;
@@ -1,6 +1,18 @@
-use image::{DynamicImage, GenericImageView, ImageBuffer};
+use image::DynamicImage;
 use rayon::prelude::*;
 
+/// Map the colour channels of every pixel in parallel, leaving alpha untouched
+fn map_rgb(img: &DynamicImage, map: impl Fn([u8; 3]) -> [u8; 3] + Sync) -> DynamicImage {
+    let mut output = img.to_rgba8();
+    
+    output.par_chunks_mut(4).for_each(|pixel| {
+        let mapped = map([pixel[0], pixel[1], pixel[2]]);
+        pixel[..3].copy_from_slice(&mapped);
+    });
+    
+    DynamicImage::ImageRgba8(output)
+}
+
 /// Adjust brightness of an image
 /// 
 /// # Parameters
@@ -10,25 +22,9 @@ use rayon::prelude::*;
 /// # Returns
 /// New image with adjusted brightness
 pub fn brightness(img: &DynamicImage, factor: f32) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
-    let pixels: Vec<u8> = rgb_img.into_raw();
-    
-    let adjusted: Vec<u8> = pixels
-        .par_chunks(3)
-        .flat_map(|pixel| {
-            [
-                (pixel[0] as f32 * factor).clamp(0.0, 255.0) as u8,
-                (pixel[1] as f32 * factor).clamp(0.0, 255.0) as u8,
-                (pixel[2] as f32 * factor).clamp(0.0, 255.0) as u8,
-            ]
-        })
-        .collect();
-    
-    DynamicImage::ImageRgb8(
-        ImageBuffer::from_raw(width, height, adjusted)
-            .expect("Failed to create image buffer")
-    )
+    map_rgb(img, |channels| {
+        channels.map(|value| (value as f32 * factor).clamp(0.0, 255.0) as u8)
+    })
 }
 
 /// Adjust contrast of an image
@@ -40,30 +36,14 @@ pub fn brightness(img: &DynamicImage, factor: f32) -> DynamicImage {
 /// # Returns
 /// New image with adjusted contrast
 pub fn contrast(img: &DynamicImage, factor: f32) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
-    let pixels: Vec<u8> = rgb_img.into_raw();
-    
     // Contrast adjustment around middle gray (128)
-    let adjusted: Vec<u8> = pixels
-        .par_chunks(3)
-        .flat_map(|pixel| {
-            [
-                ((pixel[0] as f32 - 128.0) * factor + 128.0).clamp(0.0, 255.0) as u8,
-                ((pixel[1] as f32 - 128.0) * factor + 128.0).clamp(0.0, 255.0) as u8,
-                ((pixel[2] as f32 - 128.0) * factor + 128.0).clamp(0.0, 255.0) as u8,
-            ]
-        })
-        .collect();
-    
-    DynamicImage::ImageRgb8(
-        ImageBuffer::from_raw(width, height, adjusted)
-            .expect("Failed to create image buffer")
-    )
+    map_rgb(img, |channels| {
+        channels.map(|value| ((value as f32 - 128.0) * factor + 128.0).clamp(0.0, 255.0) as u8)
+    })
 }
 
 /// Convert RGB to HSL color space
-fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+pub(crate) fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
     let r = r as f32 / 255.0;
     let g = g as f32 / 255.0;
     let b = b as f32 / 255.0;
@@ -99,7 +79,7 @@ fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
 }
 
 /// Convert HSL to RGB color space
-fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+pub(crate) fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
     let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
     let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
     let m = l - c / 2.0;
@@ -134,24 +114,12 @@ fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
 /// # Returns
 /// New image with adjusted saturation
 pub fn saturation(img: &DynamicImage, factor: f32) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
-    let pixels: Vec<u8> = rgb_img.into_raw();
-    
-    let adjusted: Vec<u8> = pixels
-        .par_chunks(3)
-        .flat_map(|pixel| {
-            let (h, s, l) = rgb_to_hsl(pixel[0], pixel[1], pixel[2]);
-            let new_s = (s * factor).clamp(0.0, 1.0);
-            let (r, g, b) = hsl_to_rgb(h, new_s, l);
-            [r, g, b]
-        })
-        .collect();
-    
-    DynamicImage::ImageRgb8(
-        ImageBuffer::from_raw(width, height, adjusted)
-            .expect("Failed to create image buffer")
-    )
+    map_rgb(img, |[r, g, b]| {
+        let (h, s, l) = rgb_to_hsl(r, g, b);
+        let new_s = (s * factor).clamp(0.0, 1.0);
+        let (r, g, b) = hsl_to_rgb(h, new_s, l);
+        [r, g, b]
+    })
 }
 
 /// Adjust hue of an image
@@ -163,24 +131,12 @@ pub fn saturation(img: &DynamicImage, factor: f32) -> DynamicImage {
 /// # Returns
 /// New image with adjusted hue
 pub fn hue(img: &DynamicImage, shift: i32) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
-    let pixels: Vec<u8> = rgb_img.into_raw();
-    
-    let adjusted: Vec<u8> = pixels
-        .par_chunks(3)
-        .flat_map(|pixel| {
-            let (h, s, l) = rgb_to_hsl(pixel[0], pixel[1], pixel[2]);
-            let new_h = (h + shift as f32 + 360.0) % 360.0;
-            let (r, g, b) = hsl_to_rgb(new_h, s, l);
-            [r, g, b]
-        })
-        .collect();
-    
-    DynamicImage::ImageRgb8(
-        ImageBuffer::from_raw(width, height, adjusted)
-            .expect("Failed to create image buffer")
-    )
+    map_rgb(img, |[r, g, b]| {
+        let (h, s, l) = rgb_to_hsl(r, g, b);
+        let new_h = (h + shift as f32 + 360.0) % 360.0;
+        let (r, g, b) = hsl_to_rgb(new_h, s, l);
+        [r, g, b]
+    })
 }
 
 /// Apply gamma correction to an image
@@ -192,10 +148,6 @@ pub fn hue(img: &DynamicImage, shift: i32) -> DynamicImage {
 /// # Returns
 /// New image with gamma correction applied
 pub fn gamma(img: &DynamicImage, gamma: f32) -> DynamicImage {
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
-    let pixels: Vec<u8> = rgb_img.into_raw();
-    
     // Precompute gamma lookup table for performance
     let gamma_lut: Vec<u8> = (0..256)
         .map(|i| {
@@ -205,15 +157,7 @@ pub fn gamma(img: &DynamicImage, gamma: f32) -> DynamicImage {
         })
         .collect();
     
-    let adjusted: Vec<u8> = pixels
-        .par_iter()
-        .map(|&pixel| gamma_lut[pixel as usize])
-        .collect();
-    
-    DynamicImage::ImageRgb8(
-        ImageBuffer::from_raw(width, height, adjusted)
-            .expect("Failed to create image buffer")
-    )
+    map_rgb(img, |channels| channels.map(|value| gamma_lut[value as usize]))
 }
 
 #[cfg(test)]
@@ -248,4 +192,22 @@ mod tests {
         assert!((g as i32 - 128).abs() <= 1);
         assert!((b as i32 - 128).abs() <= 1);
     }
+    
+    #[test]
+    fn test_adjustments_keep_alpha() {
+        use image::{Rgba, RgbaImage};
+        
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([200, 100, 50, 90])));
+        let results = [
+            brightness(&img, 0.5),
+            contrast(&img, 1.5),
+            saturation(&img, 0.0),
+            hue(&img, 90),
+            gamma(&img, 2.0),
+        ];
+        for result in results {
+            assert_eq!(result.to_rgba8().get_pixel(1, 1)[3], 90);
+        }
+        assert_eq!(*brightness(&img, 0.5).to_rgba8().get_pixel(0, 0), Rgba([100, 50, 25, 90]));
+    }
 }
@@ -0,0 +1,253 @@
+use image::{DynamicImage, GenericImageView, GrayImage, Luma, Rgb, RgbImage, imageops};
+use imageproc::filter::gaussian_blur_f32;
+use crate::core::operations::selection::selection_bounds;
+use crate::types::errors::AppError;
+use crate::types::operations::{AlphaOperation, AlphaSource, Color, CropRect};
+use crate::utils::base64::{decode_image, encode_image};
+use crate::utils::preview::{validate_file_size, validate_format};
+
+/// Composite an image over an opaque background colour
+pub fn flatten(image: &DynamicImage, background: Color) -> RgbImage {
+    let rgba = image.to_rgba8();
+    let backdrop = [background.r, background.g, background.b];
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let alpha = pixel[3] as f32 / 255.0;
+        Rgb(std::array::from_fn(|c| {
+            (pixel[c] as f32 * alpha + backdrop[c] as f32 * (1.0 - alpha)).round() as u8
+        }))
+    })
+}
+
+/// Smallest rectangle holding every pixel with alpha above `threshold`
+pub fn trim_rect(img: &DynamicImage, threshold: u8) -> Result<CropRect, AppError> {
+    let rgba = img.to_rgba8();
+    let visible = GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        Luma([if rgba.get_pixel(x, y)[3] > threshold { 255 } else { 0 }])
+    });
+
+    selection_bounds(&visible).ok_or_else(|| AppError::InvalidOperation {
+        details: "Nothing to trim to; every pixel is transparent".to_string(),
+    })
+}
+
+fn load_source_file(path: &str) -> Result<DynamicImage, AppError> {
+    validate_format(path)?;
+    validate_file_size(path)?;
+    image::open(path).map_err(|e| AppError::ImageLoadError(format!("{}: {}", path, e)))
+}
+
+/// Alpha of an image if it has one, luminance otherwise
+fn source_channel(source: &DynamicImage) -> GrayImage {
+    if source.color().has_alpha() {
+        let rgba = source.to_rgba8();
+        GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| Luma([rgba.get_pixel(x, y)[3]]))
+    } else {
+        source.to_luma8()
+    }
+}
+
+/// Embed a file source's channel in the source
+///
+/// History records alpha replacements this way, so that replaying them
+/// neither reads the file again nor depends on it being unchanged.
+pub fn resolve_source(source: &AlphaSource) -> Result<AlphaSource, AppError> {
+    let AlphaSource::File { path } = source else {
+        return Ok(source.clone());
+    };
+
+    let channel = source_channel(&load_source_file(path)?);
+    Ok(AlphaSource::Embedded { data: encode_image(&DynamicImage::ImageLuma8(channel))? })
+}
+
+/// Build the replacement alpha channel at the image size
+fn alpha_source(img: &DynamicImage, source: &AlphaSource) -> Result<GrayImage, AppError> {
+    let channel = match source {
+        AlphaSource::Luminance => return Ok(img.to_luma8()),
+        AlphaSource::File { path } => source_channel(&load_source_file(path)?),
+        AlphaSource::Embedded { data } => source_channel(&decode_image(data)?),
+    };
+
+    let (width, height) = img.dimensions();
+    if channel.dimensions() == (width, height) {
+        Ok(channel)
+    } else {
+        Ok(imageops::resize(&channel, width, height, imageops::FilterType::Triangle))
+    }
+}
+
+/// Apply an operation to the alpha channel
+///
+/// Every other operation keeps alpha where it can; these are the ones that
+/// change it on purpose.
+pub fn alpha(img: &DynamicImage, operation: &AlphaOperation) -> Result<DynamicImage, AppError> {
+    let mut output = img.to_rgba8();
+
+    match operation {
+        AlphaOperation::Flatten { color } => {
+            return Ok(DynamicImage::ImageRgb8(flatten(img, *color)));
+        }
+        AlphaOperation::Extract => {
+            let channel = GrayImage::from_fn(output.width(), output.height(), |x, y| {
+                Luma([output.get_pixel(x, y)[3]])
+            });
+            return Ok(DynamicImage::ImageLuma8(channel));
+        }
+        AlphaOperation::Replace { source, invert } => {
+            let channel = alpha_source(img, source)?;
+            for (pixel, value) in output.pixels_mut().zip(channel.pixels()) {
+                pixel[3] = if *invert { 255 - value[0] } else { value[0] };
+            }
+        }
+        AlphaOperation::Threshold { level } => {
+            for pixel in output.pixels_mut() {
+                pixel[3] = if pixel[3] >= *level { 255 } else { 0 };
+            }
+        }
+        AlphaOperation::Feather { radius } => {
+            if !(0.1..=100.0).contains(radius) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Feather radius must be between 0.1 and 100, got {}", radius),
+                });
+            }
+            let channel = GrayImage::from_fn(output.width(), output.height(), |x, y| {
+                Luma([output.get_pixel(x, y)[3]])
+            });
+            let feathered = gaussian_blur_f32(&channel, *radius);
+            for (pixel, value) in output.pixels_mut().zip(feathered.pixels()) {
+                pixel[3] = value[0];
+            }
+        }
+        AlphaOperation::Premultiply => {
+            for pixel in output.pixels_mut() {
+                let alpha = pixel[3] as u32;
+                for c in 0..3 {
+                    pixel[c] = ((pixel[c] as u32 * alpha + 127) / 255) as u8;
+                }
+            }
+        }
+        AlphaOperation::Unpremultiply => {
+            for pixel in output.pixels_mut() {
+                let alpha = pixel[3] as u32;
+                if alpha == 0 {
+                    continue;
+                }
+                for c in 0..3 {
+                    pixel[c] = ((pixel[c] as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+        AlphaOperation::Trim { threshold } => {
+            let rect = trim_rect(img, *threshold)?;
+            return Ok(img.crop_imm(rect.x, rect.y, rect.width, rect.height));
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+    use tempfile::tempdir;
+
+    /// Opaque red square in the middle of a transparent 10x10 image
+    fn sticker() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(10, 10, |x, y| {
+            if (3..7).contains(&x) && (2..6).contains(&y) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 0])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_flatten_and_extract() {
+        let white = Color { r: 255, g: 255, b: 255, a: 255 };
+        let flat = alpha(&sticker(), &AlphaOperation::Flatten { color: white }).unwrap();
+        assert!(!flat.color().has_alpha());
+        assert_eq!(*flat.to_rgb8().get_pixel(0, 0), Rgb([255, 255, 255]));
+        assert_eq!(*flat.to_rgb8().get_pixel(4, 4), Rgb([255, 0, 0]));
+
+        let extracted = alpha(&sticker(), &AlphaOperation::Extract).unwrap().to_luma8();
+        assert_eq!(extracted.get_pixel(0, 0)[0], 0);
+        assert_eq!(extracted.get_pixel(4, 4)[0], 255);
+    }
+
+    #[test]
+    fn test_replace_alpha_from_file_and_luminance() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mask.png");
+        // Grayscale mask at half size, stretched over the image
+        GrayImage::from_fn(5, 5, |x, _| Luma([if x < 2 { 0 } else { 255 }])).save(&path).unwrap();
+
+        let source = AlphaSource::File { path: path.to_string_lossy().into_owned() };
+        let replace = AlphaOperation::Replace { source, invert: false };
+        let result = alpha(&sticker(), &replace).unwrap().to_rgba8();
+        assert_eq!(result.get_pixel(0, 5)[3], 0);
+        assert_eq!(result.get_pixel(9, 5)[3], 255);
+
+        let replace = AlphaOperation::Replace { source: AlphaSource::Luminance, invert: true };
+        let result = alpha(&sticker(), &replace).unwrap().to_rgba8();
+        // Rec. 709 luma puts red at 54 and blue at 18
+        assert_eq!(result.get_pixel(4, 4)[3], 255 - 54);
+        assert_eq!(result.get_pixel(0, 0)[3], 255 - 18);
+    }
+
+    #[test]
+    fn test_resolve_embeds_file_source() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mask.png");
+        GrayImage::from_fn(5, 5, |x, _| Luma([if x < 2 { 0 } else { 255 }])).save(&path).unwrap();
+
+        let source = AlphaSource::File { path: path.to_string_lossy().into_owned() };
+        let replace = AlphaOperation::Replace { source: source.clone(), invert: false };
+        let expected = alpha(&sticker(), &replace).unwrap();
+        let resolved = resolve_source(&source).unwrap();
+        assert!(matches!(resolved, AlphaSource::Embedded { .. }));
+
+        // The recorded source no longer depends on the file
+        drop(dir);
+        let replace = AlphaOperation::Replace { source: resolved, invert: false };
+        assert_eq!(alpha(&sticker(), &replace).unwrap().to_rgba8(), expected.to_rgba8());
+    }
+
+    #[test]
+    fn test_threshold_and_feather() {
+        let soft = DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 1, |x, _| {
+            Rgba([0, 0, 0, [60, 128, 200][x as usize]])
+        }));
+        let result = alpha(&soft, &AlphaOperation::Threshold { level: 128 }).unwrap().to_rgba8();
+        let levels: Vec<u8> = result.pixels().map(|p| p[3]).collect();
+        assert_eq!(levels, vec![0, 255, 255]);
+
+        let feather = AlphaOperation::Feather { radius: 1.0 };
+        let feathered = alpha(&sticker(), &feather).unwrap().to_rgba8();
+        let edge = feathered.get_pixel(3, 4)[3];
+        assert!(edge > 0 && edge < 255, "{}", edge);
+        assert_eq!(feathered.get_pixel(4, 4)[0], 255);
+        assert!(alpha(&sticker(), &AlphaOperation::Feather { radius: 0.0 }).is_err());
+    }
+
+    #[test]
+    fn test_premultiply_round_trip() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([200, 100, 50, 128])));
+        let premultiplied = alpha(&img, &AlphaOperation::Premultiply).unwrap();
+        assert_eq!(*premultiplied.to_rgba8().get_pixel(0, 0), Rgba([100, 50, 25, 128]));
+
+        let restored = alpha(&premultiplied, &AlphaOperation::Unpremultiply).unwrap().to_rgba8();
+        assert_eq!(*restored.get_pixel(0, 0), Rgba([199, 100, 50, 128]));
+    }
+
+    #[test]
+    fn test_trim_transparent_edges() {
+        let trimmed = alpha(&sticker(), &AlphaOperation::Trim { threshold: 0 }).unwrap();
+        assert_eq!(trimmed.dimensions(), (4, 4));
+        assert_eq!(*trimmed.to_rgba8().get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+
+        let empty = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        assert!(alpha(&empty, &AlphaOperation::Trim { threshold: 0 }).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+use imageproc::edges::canny;
+use imageproc::hough::{LineDetectionOptions, detect_lines};
+use crate::types::errors::AppError;
+use crate::types::operations::{AutoStraightenParams, CropParams, CropRect, CropUnits};
+use crate::utils::preview::resize_to_fit;
+
+/// Long edge of the working copy used for line detection
+const DETECTION_SIZE: u32 = 512;
+
+/// Find the rectangle left after trimming uniform borders
+///
+/// The top-left pixel is taken as the border colour. Rows and columns are
+/// trimmed while every pixel stays within `tolerance` of it on all channels.
+///
+/// # Returns
+/// Trimmed CropRect, or the full image when it is entirely uniform
+pub fn trim_rect(img: &DynamicImage, tolerance: u8) -> CropRect {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let border = *rgba.get_pixel(0, 0);
+
+    let is_border = |pixel: &Rgba<u8>| {
+        pixel.0.iter().zip(border.0.iter()).all(|(a, b)| a.abs_diff(*b) <= tolerance)
+    };
+    let row_is_border = |y: u32| (0..width).all(|x| is_border(rgba.get_pixel(x, y)));
+
+    let Some(top) = (0..height).find(|&y| !row_is_border(y)) else {
+        return CropRect { x: 0, y: 0, width, height };
+    };
+    let bottom = (0..height).rev().find(|&y| !row_is_border(y)).unwrap_or(top) + 1;
+
+    let column_is_border = |x: u32| (top..bottom).all(|y| is_border(rgba.get_pixel(x, y)));
+    let left = (0..width).find(|&x| !column_is_border(x)).unwrap_or(0);
+    let right = (0..width).rev().find(|&x| !column_is_border(x)).unwrap_or(width - 1) + 1;
+
+    CropRect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+/// Estimate how far the image content is tilted
+///
+/// Runs a Hough transform on Canny edges and takes the median deviation of
+/// near-horizontal and near-vertical lines.
+///
+/// # Returns
+/// Clockwise tilt in degrees, or 0.0 if no dominant line is found
+pub fn detect_tilt(img: &DynamicImage, max_angle: f32) -> f32 {
+    let working = resize_to_fit(img, DETECTION_SIZE, DETECTION_SIZE);
+    let edges = canny(&working.to_luma8(), 50.0, 100.0);
+    let (width, height) = edges.dimensions();
+
+    let options = LineDetectionOptions {
+        vote_threshold: (width.min(height) / 4).max(10),
+        suppression_radius: 8,
+    };
+
+    // Line angles are of the line normal: ~90 for horizontals, ~0 or ~180 for verticals
+    let mut deviations: Vec<f32> = detect_lines(&edges, options)
+        .into_iter()
+        .map(|line| {
+            let angle = line.angle_in_degrees as f32;
+            if angle < 45.0 {
+                angle
+            } else if angle < 135.0 {
+                angle - 90.0
+            } else {
+                angle - 180.0
+            }
+        })
+        .filter(|deviation| deviation.abs() <= max_angle)
+        .collect();
+
+    if deviations.is_empty() {
+        return 0.0;
+    }
+
+    deviations.sort_by(|a, b| a.total_cmp(b));
+    deviations[deviations.len() / 2]
+}
+
+/// Resolve auto-straighten to a rotated crop
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Maximum tilt to correct
+///
+/// # Returns
+/// Crop parameters rotating by the detected tilt and keeping the largest
+/// rectangle of the original aspect ratio that stays inside the image
+pub fn straighten(img: &DynamicImage, params: &AutoStraightenParams) -> Result<CropParams, AppError> {
+    if !(0.0..=45.0).contains(&params.max_angle) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Maximum straighten angle must be between 0 and 45, got {}", params.max_angle),
+        });
+    }
+
+    let (width, height) = img.dimensions();
+    let angle = detect_tilt(img, params.max_angle);
+
+    let (sin, cos) = angle.to_radians().abs().sin_cos();
+    let (w, h) = (width as f32, height as f32);
+    let scale = (w / (w * cos + h * sin)).min(h / (w * sin + h * cos));
+    let crop_width = (w * scale).floor().max(1.0);
+    let crop_height = (h * scale).floor().max(1.0);
+
+    Ok(CropParams {
+        x: ((w - crop_width) / 2.0).floor(),
+        y: ((h - crop_height) / 2.0).floor(),
+        width: crop_width,
+        height: crop_height,
+        units: CropUnits::Pixels,
+        angle: (angle != 0.0).then_some(angle),
+        aspect: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use imageproc::drawing::draw_line_segment_mut;
+
+    #[test]
+    fn test_trim_rect() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(20, 10, |x, y| {
+            if (5..12).contains(&x) && (2..6).contains(&y) {
+                Rgba([0, 0, 0, 255])
+            } else if x == 19 {
+                // Slightly off-white column
+                Rgba([250, 250, 250, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        }));
+
+        assert_eq!(trim_rect(&img, 10), CropRect { x: 5, y: 2, width: 7, height: 4 });
+        assert_eq!(trim_rect(&img, 0), CropRect { x: 5, y: 0, width: 15, height: 10 });
+    }
+
+    #[test]
+    fn test_trim_rect_uniform_image() {
+        let img = DynamicImage::new_rgb8(8, 6);
+        assert_eq!(trim_rect(&img, 0), CropRect { x: 0, y: 0, width: 8, height: 6 });
+    }
+
+    #[test]
+    fn test_detect_tilt() {
+        let mut canvas = RgbaImage::from_pixel(200, 200, Rgba([255, 255, 255, 255]));
+        let slope = 5f32.to_radians().tan();
+        for y in (20..160).step_by(30) {
+            let y = y as f32;
+            draw_line_segment_mut(&mut canvas, (0.0, y), (199.0, y + 199.0 * slope), Rgba([0, 0, 0, 255]));
+        }
+        let img = DynamicImage::ImageRgba8(canvas);
+
+        let tilt = detect_tilt(&img, 10.0);
+        assert!((tilt - 5.0).abs() <= 1.0, "tilt was {}", tilt);
+
+        let params = straighten(&img, &AutoStraightenParams { max_angle: 10.0 }).unwrap();
+        assert_eq!(params.angle, Some(tilt));
+        assert!(params.width < 200.0 && params.height < 200.0);
+    }
+
+    #[test]
+    fn test_straighten_without_lines() {
+        let img = DynamicImage::new_rgb8(50, 40);
+        let params = straighten(&img, &AutoStraightenParams { max_angle: 10.0 }).unwrap();
+
+        assert_eq!(params.angle, None);
+        assert_eq!((params.width, params.height), (50.0, 40.0));
+        assert!(straighten(&img, &AutoStraightenParams { max_angle: 60.0 }).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use image::{DynamicImage, GenericImageView, RgbaImage, imageops};
+use crate::types::errors::AppError;
+use crate::types::operations::{Anchor, CanvasFill, CanvasMode, CanvasParams};
+use crate::utils::preview::validate_dimensions;
+
+/// Calculate the top-left position of an inner box placed inside an outer box
+///
+/// Offsets are negative when the inner box is larger than the outer one.
+pub fn anchor_offset(anchor: Anchor, outer: (u32, u32), inner: (u32, u32)) -> (i64, i64) {
+    let dx = outer.0 as i64 - inner.0 as i64;
+    let dy = outer.1 as i64 - inner.1 as i64;
+
+    let x = match anchor {
+        Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+        Anchor::Top | Anchor::Center | Anchor::Bottom => dx / 2,
+        Anchor::TopRight | Anchor::Right | Anchor::BottomRight => dx,
+    };
+    let y = match anchor {
+        Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+        Anchor::Left | Anchor::Center | Anchor::Right => dy / 2,
+        Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => dy,
+    };
+
+    (x, y)
+}
+
+/// Calculate the canvas size and the position of the image on it
+///
+/// # Returns
+/// Canvas (width, height, x, y)
+fn canvas_layout(width: u32, height: u32, params: &CanvasParams) -> Result<(u32, u32, i64, i64), AppError> {
+    let (canvas_width, canvas_height) = match params.mode {
+        CanvasMode::Size { width: w, height: h } => (w, h),
+        CanvasMode::Padding { top, right, bottom, left } => (
+            width.saturating_add(left).saturating_add(right),
+            height.saturating_add(top).saturating_add(bottom),
+        ),
+        CanvasMode::AspectRatio { width: rw, height: rh } => {
+            if rw == 0 || rh == 0 {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Aspect ratio must be positive, got {}:{}", rw, rh),
+                });
+            }
+            // Grow whichever side is too short; compare w/h against rw/rh without floats
+            if width as u64 * rh as u64 >= height as u64 * rw as u64 {
+                let h = (width as u64 * rh as u64).div_ceil(rw as u64);
+                (width, h.min(u32::MAX as u64) as u32)
+            } else {
+                let w = (height as u64 * rw as u64).div_ceil(rh as u64);
+                (w.min(u32::MAX as u64) as u32, height)
+            }
+        }
+    };
+
+    validate_dimensions(canvas_width, canvas_height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    let (x, y) = match params.mode {
+        // Padding places the image explicitly, the anchor doesn't apply
+        CanvasMode::Padding { top, left, .. } => (left as i64, top as i64),
+        _ => anchor_offset(params.anchor, (canvas_width, canvas_height), (width, height)),
+    };
+
+    Ok((canvas_width, canvas_height, x, y))
+}
+
+/// Build the canvas background
+fn background(img: &DynamicImage, width: u32, height: u32, fill: &CanvasFill) -> Result<RgbaImage, AppError> {
+    match fill {
+        CanvasFill::Transparent => Ok(RgbaImage::new(width, height)),
+        CanvasFill::Color { color } => Ok(RgbaImage::from_pixel(width, height, color.to_rgba())),
+        CanvasFill::Blur { radius } => {
+            if *radius <= 0.0 || *radius > 100.0 {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Blur radius must be between 0 and 100, got {}", radius),
+                });
+            }
+            // Cover the canvas, then blur so the backdrop doesn't compete with the subject
+            let cover = img.resize_to_fill(width, height, imageops::FilterType::Triangle);
+            Ok(imageops::blur(&cover.to_rgba8(), *radius))
+        }
+    }
+}
+
+/// Change the canvas size of an image
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Canvas mode, anchor and background fill
+///
+/// # Returns
+/// RGBA image on the new canvas. Areas outside the canvas are cropped.
+pub fn canvas(img: &DynamicImage, params: &CanvasParams) -> Result<DynamicImage, AppError> {
+    let (width, height) = img.dimensions();
+    let (canvas_width, canvas_height, x, y) = canvas_layout(width, height, params)?;
+
+    let mut output = background(img, canvas_width, canvas_height, &params.fill)?;
+    let source = img.to_rgba8();
+
+    match params.fill {
+        // Nothing to blend with, keep the source alpha as-is
+        CanvasFill::Transparent => imageops::replace(&mut output, &source, x, y),
+        _ => imageops::overlay(&mut output, &source, x, y),
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::Color;
+    use image::Rgba;
+
+    fn params(mode: CanvasMode, anchor: Anchor, fill: CanvasFill) -> CanvasParams {
+        CanvasParams { mode, anchor, fill }
+    }
+
+    fn white(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255])))
+    }
+
+    #[test]
+    fn test_anchor_offset() {
+        assert_eq!(anchor_offset(Anchor::TopLeft, (10, 10), (4, 4)), (0, 0));
+        assert_eq!(anchor_offset(Anchor::Center, (10, 10), (4, 4)), (3, 3));
+        assert_eq!(anchor_offset(Anchor::BottomRight, (10, 10), (4, 4)), (6, 6));
+        assert_eq!(anchor_offset(Anchor::Right, (4, 4), (10, 10)), (-6, -3));
+    }
+
+    #[test]
+    fn test_canvas_size_with_color_fill() {
+        let img = white(4, 4);
+        let red = Color { r: 255, g: 0, b: 0, a: 255 };
+        let p = params(
+            CanvasMode::Size { width: 10, height: 6 },
+            Anchor::TopLeft,
+            CanvasFill::Color { color: red },
+        );
+
+        let result = canvas(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (10, 6));
+        assert_eq!(result.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(result.get_pixel(9, 5), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_canvas_smaller_crops_at_anchor() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(10, 10, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }));
+        let p = params(CanvasMode::Size { width: 4, height: 4 }, Anchor::BottomRight, CanvasFill::Transparent);
+
+        let result = canvas(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (4, 4));
+        assert_eq!(result.get_pixel(0, 0), &Rgba([6, 6, 0, 255]));
+    }
+
+    #[test]
+    fn test_canvas_padding() {
+        let img = white(4, 4);
+        let p = params(
+            CanvasMode::Padding { top: 1, right: 2, bottom: 3, left: 4 },
+            Anchor::Center,
+            CanvasFill::Transparent,
+        );
+
+        let result = canvas(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (10, 8));
+        assert_eq!(result.get_pixel(4, 1)[3], 255);
+        assert_eq!(result.get_pixel(3, 1)[3], 0);
+    }
+
+    #[test]
+    fn test_canvas_aspect_ratio_with_blur_fill() {
+        let img = white(100, 50);
+        let p = params(
+            CanvasMode::AspectRatio { width: 4, height: 5 },
+            Anchor::Center,
+            CanvasFill::Blur { radius: 5.0 },
+        );
+
+        let result = canvas(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (100, 125));
+        // The blurred backdrop fills the letterbox
+        assert_eq!(result.get_pixel(50, 0)[3], 255);
+
+        let square = params(CanvasMode::AspectRatio { width: 1, height: 1 }, Anchor::Center, CanvasFill::Transparent);
+        assert_eq!(canvas(&white(30, 70), &square).unwrap().dimensions(), (70, 70));
+    }
+
+    #[test]
+    fn test_canvas_validation() {
+        let img = white(4, 4);
+
+        let zero = params(CanvasMode::Size { width: 0, height: 4 }, Anchor::Center, CanvasFill::Transparent);
+        assert!(canvas(&img, &zero).is_err());
+
+        let ratio = params(CanvasMode::AspectRatio { width: 0, height: 1 }, Anchor::Center, CanvasFill::Transparent);
+        assert!(canvas(&img, &ratio).is_err());
+
+        let blur = params(CanvasMode::Size { width: 8, height: 8 }, Anchor::Center, CanvasFill::Blur { radius: 0.0 });
+        assert!(canvas(&img, &blur).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+use image::{DynamicImage, Rgba, RgbaImage};
+use crate::types::errors::AppError;
+use crate::types::operations::{ChromaKeyMode, ChromaKeyParams};
+
+fn validate(params: &ChromaKeyParams) -> Result<(), AppError> {
+    if !(0.0..=1.0).contains(&params.tolerance) || !(0.0..=1.0).contains(&params.softness) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "Key tolerance and softness must be between 0 and 1, got {} and {}",
+                params.tolerance, params.softness
+            ),
+        });
+    }
+
+    if let ChromaKeyMode::Color { spill, .. } = params.mode
+        && !(0.0..=1.0).contains(&spill)
+    {
+        return Err(AppError::InvalidOperation {
+            details: format!("Spill suppression must be between 0 and 1, got {}", spill),
+        });
+    }
+
+    Ok(())
+}
+
+/// RGB distance scaled so black to white is 1.0
+fn distance(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let sum = (0..3).map(|c| (a[c] as f32 - b[c] as f32).powi(2)).sum::<f32>();
+    sum.sqrt() / (255.0 * 3f32.sqrt())
+}
+
+/// Fraction of a pixel kept at a given distance from the key
+fn keep(distance: f32, tolerance: f32, softness: f32) -> f32 {
+    if distance <= tolerance {
+        0.0
+    } else if softness > 0.0 {
+        ((distance - tolerance) / softness).min(1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Remove the key colour's cast from a pixel
+///
+/// The pixel's chroma is projected onto the key's chroma and that component
+/// is subtracted, which leaves neutral tones and the pixel's mean untouched.
+fn suppress_spill(pixel: &mut Rgba<u8>, key: [f32; 3], amount: f32) {
+    let mean = (pixel[0] as f32 + pixel[1] as f32 + pixel[2] as f32) / 3.0;
+    let cast = (0..3).map(|c| (pixel[c] as f32 - mean) * key[c]).sum::<f32>();
+    if cast <= 0.0 {
+        return;
+    }
+
+    for (c, direction) in key.iter().enumerate() {
+        let value = pixel[c] as f32 - amount * cast * direction;
+        pixel[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+/// Unit vector of the key colour's chroma, or `None` for a neutral key
+fn key_direction(key: &Rgba<u8>) -> Option<[f32; 3]> {
+    let mean = (key[0] as f32 + key[1] as f32 + key[2] as f32) / 3.0;
+    let chroma = [0, 1, 2].map(|c| key[c] as f32 - mean);
+    let length = chroma.iter().map(|v| v * v).sum::<f32>().sqrt();
+    (length > 1.0).then(|| chroma.map(|v| v / length))
+}
+
+/// Kept fraction per pixel after flood-filling from each corner
+///
+/// Every fill compares against its own corner, so a backdrop that shades from
+/// one corner to another is still removed where each corner reaches.
+fn background_keep(source: &RgbaImage, tolerance: f32, softness: f32) -> Vec<f32> {
+    let (width, height) = source.dimensions();
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let mut kept = vec![1.0f32; (width * height) as usize];
+
+    let corners = [(0, 0), (width - 1, 0), (0, height - 1), (width - 1, height - 1)];
+    for (cx, cy) in corners {
+        let reference = *source.get_pixel(cx, cy);
+        let mut visited = vec![false; kept.len()];
+        let mut queue = VecDeque::from([(cx, cy)]);
+        visited[index(cx, cy)] = true;
+
+        while let Some((x, y)) = queue.pop_front() {
+            let amount = keep(distance(source.get_pixel(x, y), &reference), tolerance, softness);
+            let slot = &mut kept[index(x, y)];
+            *slot = slot.min(amount);
+            if amount >= 1.0 {
+                continue;
+            }
+
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbours {
+                if nx < width && ny < height && !visited[index(nx, ny)] {
+                    visited[index(nx, ny)] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+
+    kept
+}
+
+/// Make a key colour or a uniform background transparent
+///
+/// Alpha is only ever lowered, so the result should be exported to a format
+/// with an alpha channel such as PNG or WebP.
+pub fn chroma_key(img: &DynamicImage, params: &ChromaKeyParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+
+    let mut output = img.to_rgba8();
+    let (tolerance, softness) = (params.tolerance, params.softness);
+
+    match &params.mode {
+        ChromaKeyMode::Color { color, spill } => {
+            let key = color.to_rgba();
+            let direction = key_direction(&key);
+            for pixel in output.pixels_mut() {
+                let amount = keep(distance(pixel, &key), tolerance, softness);
+                if let Some(direction) = direction
+                    && amount > 0.0
+                {
+                    suppress_spill(pixel, direction, *spill);
+                }
+                pixel[3] = (pixel[3] as f32 * amount).round() as u8;
+            }
+        }
+        ChromaKeyMode::Background => {
+            let kept = background_keep(&output, tolerance, softness);
+            for (pixel, amount) in output.pixels_mut().zip(kept) {
+                pixel[3] = (pixel[3] as f32 * amount).round() as u8;
+            }
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::Color;
+
+    const GREEN: Color = Color { r: 0, g: 255, b: 0, a: 255 };
+
+    fn key(tolerance: f32, softness: f32, spill: f32) -> ChromaKeyParams {
+        ChromaKeyParams { mode: ChromaKeyMode::Color { color: GREEN, spill }, tolerance, softness }
+    }
+
+    #[test]
+    fn test_key_colour_becomes_transparent() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 1, |x, _| match x {
+            0 => Rgba([0, 255, 0, 255]),
+            1 => Rgba([10, 240, 10, 255]),
+            2 => Rgba([0, 160, 0, 255]),
+            _ => Rgba([200, 50, 50, 255]),
+        }));
+        let result = chroma_key(&img, &key(0.1, 0.2, 0.0)).unwrap().to_rgba8();
+
+        assert_eq!(result.get_pixel(0, 0)[3], 0);
+        assert_eq!(result.get_pixel(1, 0)[3], 0);
+        // 95/441 of the range away: 0.115 past the tolerance, over half the softness
+        let faded = result.get_pixel(2, 0)[3];
+        assert!((140..=155).contains(&faded), "{}", faded);
+        assert_eq!(*result.get_pixel(3, 0), Rgba([200, 50, 50, 255]));
+    }
+
+    #[test]
+    fn test_spill_suppression_removes_green_cast() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 { Rgba([150, 200, 150, 255]) } else { Rgba([120, 120, 120, 255]) }
+        }));
+        let result = chroma_key(&img, &key(0.1, 0.0, 1.0)).unwrap().to_rgba8();
+
+        // The green cast is pulled back to grey without changing the mean
+        let fringe = result.get_pixel(0, 0);
+        assert!(fringe[1].abs_diff(fringe[0]) <= 1 && fringe[2].abs_diff(fringe[0]) <= 1);
+        let sum = fringe.0[..3].iter().map(|&v| v as u32).sum::<u32>();
+        assert!((498..=502).contains(&sum), "{:?}", fringe);
+        assert_eq!(*result.get_pixel(1, 0), Rgba([120, 120, 120, 255]));
+    }
+
+    #[test]
+    fn test_background_removal_only_reaches_connected_pixels() {
+        // White backdrop with a red square holding a white hole in the middle
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(20, 20, |x, y| {
+            let inside = (5..15).contains(&x) && (5..15).contains(&y);
+            let hole = (9..11).contains(&x) && (9..11).contains(&y);
+            if inside && !hole { Rgba([200, 0, 0, 255]) } else { Rgba([250, 250, 250, 255]) }
+        }));
+        let mode = ChromaKeyMode::Background;
+        let params = ChromaKeyParams { mode, tolerance: 0.05, softness: 0.0 };
+        let result = chroma_key(&img, &params).unwrap().to_rgba8();
+
+        assert_eq!(result.get_pixel(0, 0)[3], 0);
+        assert_eq!(result.get_pixel(19, 10)[3], 0);
+        assert_eq!(result.get_pixel(6, 6)[3], 255);
+        assert_eq!(result.get_pixel(10, 10)[3], 255);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_parameters() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        assert!(chroma_key(&img, &key(1.5, 0.0, 0.0)).is_err());
+        assert!(chroma_key(&img, &key(0.1, -0.1, 0.0)).is_err());
+        assert!(chroma_key(&img, &key(0.1, 0.1, 2.0)).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use image::{DynamicImage, RgbaImage};
+use crate::core::operations::region::{Coverage, paint_stroke};
+use crate::types::errors::AppError;
+use crate::types::operations::{CloneStampParams, CloneStroke};
+
+fn validate_stroke(stroke: &CloneStroke) -> Result<(), AppError> {
+    if stroke.points.is_empty() {
+        return Err(AppError::InvalidOperation {
+            details: "Clone stroke needs at least 1 point".to_string(),
+        });
+    }
+    if !(stroke.size > 0.0 && stroke.size <= 2000.0) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Brush size must be between 0 and 2000, got {}", stroke.size),
+        });
+    }
+    if !(0.0..=1.0).contains(&stroke.hardness) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Brush hardness must be between 0 and 1, got {}", stroke.hardness),
+        });
+    }
+    if !(0.0..=1.0).contains(&stroke.opacity) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Stroke opacity must be between 0 and 1, got {}", stroke.opacity),
+        });
+    }
+
+    let offset = stroke.source_offset;
+    if stroke.points.iter().chain([&offset]).any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return Err(AppError::InvalidOperation {
+            details: "Clone stroke coordinates must be finite".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Paint one stroke, copying pixels from the image as it was before the stroke
+fn apply_stroke(output: &mut RgbaImage, stroke: &CloneStroke) {
+    let (width, height) = output.dimensions();
+    let mut coverage = Coverage::new(width, height);
+    paint_stroke(&mut coverage, &stroke.points, stroke.size / 2.0, stroke.hardness);
+
+    let source = output.clone();
+    let (dx, dy) = (stroke.source_offset.x.round() as i64, stroke.source_offset.y.round() as i64);
+    for (x, y, strength) in coverage.enumerate_pixels() {
+        let alpha = strength[0] * stroke.opacity;
+        let (sx, sy) = (x as i64 + dx, y as i64 + dy);
+        if alpha <= 0.0 || sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+            continue;
+        }
+
+        let from = source.get_pixel(sx as u32, sy as u32).0;
+        let pixel = output.get_pixel_mut(x, y);
+        for (value, copied) in pixel.0.iter_mut().zip(from) {
+            *value = (*value as f32 + (copied as f32 - *value as f32) * alpha).round() as u8;
+        }
+    }
+}
+
+/// Replay clone stamp strokes onto an image
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Strokes to paint, in order
+///
+/// # Returns
+/// RGBA image with the strokes applied
+pub fn clone_stamp(img: &DynamicImage, params: &CloneStampParams) -> Result<DynamicImage, AppError> {
+    if params.strokes.is_empty() {
+        return Err(AppError::InvalidOperation {
+            details: "Clone stamp needs at least one stroke".to_string(),
+        });
+    }
+    for stroke in &params.strokes {
+        validate_stroke(stroke)?;
+    }
+
+    let mut output = img.to_rgba8();
+    for stroke in &params.strokes {
+        apply_stroke(&mut output, stroke);
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::Point;
+    use image::Rgba;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// Red left half, blue right half
+    fn halves() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 20, |x, _| if x < 20 { RED } else { BLUE }))
+    }
+
+    fn stroke(hardness: f32, opacity: f32, points: Vec<Point>) -> CloneStroke {
+        CloneStroke {
+            source_offset: Point { x: -20.0, y: 0.0 },
+            size: 8.0,
+            hardness,
+            opacity,
+            points,
+        }
+    }
+
+    #[test]
+    fn test_clone_stamp_copies_source() {
+        let params = CloneStampParams {
+            strokes: vec![stroke(1.0, 1.0, vec![Point { x: 25.0, y: 10.0 }, Point { x: 35.0, y: 10.0 }])],
+        };
+
+        let result = clone_stamp(&halves(), &params).unwrap().to_rgba8();
+        assert_eq!(*result.get_pixel(25, 10), RED);
+        assert_eq!(*result.get_pixel(30, 12), RED);
+        assert_eq!(*result.get_pixel(30, 18), BLUE);
+        assert_eq!(*result.get_pixel(5, 10), RED);
+    }
+
+    #[test]
+    fn test_clone_stamp_opacity_and_softness() {
+        let half = CloneStampParams { strokes: vec![stroke(1.0, 0.5, vec![Point { x: 30.0, y: 10.0 }])] };
+        let result = clone_stamp(&halves(), &half).unwrap().to_rgba8();
+        assert_eq!(*result.get_pixel(30, 10), Rgba([128, 0, 128, 255]));
+
+        let soft = CloneStampParams { strokes: vec![stroke(0.0, 1.0, vec![Point { x: 30.0, y: 10.0 }])] };
+        let result = clone_stamp(&halves(), &soft).unwrap().to_rgba8();
+        let edge = result.get_pixel(33, 10);
+        assert!(edge[0] > 0 && edge[0] < 255, "edge was {:?}", edge);
+    }
+
+    #[test]
+    fn test_clone_stamp_replays_deterministically() {
+        let json = r#"{"strokes": [
+            {"source_offset": {"x": -20, "y": 0}, "size": 6, "points": [{"x": 24, "y": 4}, {"x": 36, "y": 16}]},
+            {"source_offset": {"x": 0, "y": 5}, "size": 4, "opacity": 0.7, "points": [{"x": 30, "y": 3}]}
+        ]}"#;
+        let params: CloneStampParams = serde_json::from_str(json).unwrap();
+        assert_eq!(params.strokes[0].hardness, 0.5);
+
+        let first = clone_stamp(&halves(), &params).unwrap();
+        let second = clone_stamp(&halves(), &params).unwrap();
+        assert_eq!(first.as_bytes(), second.as_bytes());
+    }
+
+    #[test]
+    fn test_clone_stamp_validation() {
+        let img = halves();
+
+        assert!(clone_stamp(&img, &CloneStampParams { strokes: vec![] }).is_err());
+        let no_points = CloneStampParams { strokes: vec![stroke(0.5, 1.0, vec![])] };
+        assert!(clone_stamp(&img, &no_points).is_err());
+        let opacity = CloneStampParams { strokes: vec![stroke(0.5, 1.5, vec![Point { x: 1.0, y: 1.0 }])] };
+        assert!(clone_stamp(&img, &opacity).is_err());
+    }
+}
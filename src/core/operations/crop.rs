@@ -1,6 +1,9 @@
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use imageproc::geometric_transformations::{self, Interpolation};
 use crate::types::errors::AppError;
-use crate::types::operations::CropRect;
+use crate::core::operations::canvas::anchor_offset;
+use crate::types::operations::{Anchor, CropParams, CropRect, CropUnits};
+use crate::utils::preview::validate_dimensions;
 
 /// Crop an image to a rectangular region
 /// 
@@ -46,22 +49,160 @@ pub fn crop_image(img: &DynamicImage, rect: &CropRect) -> Result<DynamicImage, A
     Ok(cropped)
 }
 
+/// Crop a rotated rectangle out of an image
+/// 
+/// # Parameters
+/// - `img`: Input image
+/// - `rect`: Crop rectangle before rotation
+/// - `angle`: Clockwise rotation of the rectangle around its centre, in degrees
+/// 
+/// # Returns
+/// RGBA image of `rect` size; areas outside the source are transparent
+pub fn crop_rotated(img: &DynamicImage, rect: &CropRect, angle: f32) -> Result<DynamicImage, AppError> {
+    if !(-180.0..=180.0).contains(&angle) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Crop angle must be between -180 and 180, got {}", angle),
+        });
+    }
+    
+    validate_dimensions(rect.width, rect.height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+    
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let half_width = rect.width as f32 / 2.0;
+    let half_height = rect.height as f32 / 2.0;
+    let cx = rect.x as f32 + half_width;
+    let cy = rect.y as f32 + half_height;
+    
+    // Map each output pixel centre through the rectangle's rotation into the source
+    let mapping = move |u: f32, v: f32| {
+        let dx = u + 0.5 - half_width;
+        let dy = v + 0.5 - half_height;
+        (cx + dx * cos - dy * sin - 0.5, cy + dx * sin + dy * cos - 0.5)
+    };
+    
+    let mut output = RgbaImage::new(rect.width, rect.height);
+    geometric_transformations::warp_into_with(
+        &img.to_rgba8(),
+        mapping,
+        Interpolation::Bilinear,
+        Rgba([0, 0, 0, 0]),
+        &mut output,
+    );
+    
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+/// Resolve crop parameters to a pixel rectangle
+/// 
+/// Converts normalised coordinates to pixels, clamps the rectangle to the
+/// image and then applies the aspect ratio constraint around the clamped
+/// rectangle's centre, so the preset holds for the area actually cropped.
+/// Rotated rectangles are not clamped, since `crop_rotated` fills whatever
+/// falls outside the source with transparency.
+/// 
+/// # Returns
+/// CropRect in original image pixels, or error if the parameters are invalid
+pub fn resolve_crop_rect(img_width: u32, img_height: u32, params: &CropParams) -> Result<CropRect, AppError> {
+    let values = [params.x, params.y, params.width, params.height];
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Crop rectangle values must be non-negative, got {:?}", values),
+        });
+    }
+    
+    let (x, y, width, height) = match params.units {
+        CropUnits::Pixels => (params.x, params.y, params.width, params.height),
+        CropUnits::Normalized => {
+            if values.iter().any(|v| *v > 1.0) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Normalized crop values must be between 0 and 1, got {:?}", values),
+                });
+            }
+            let (w, h) = (img_width as f32, img_height as f32);
+            (params.x * w, params.y * h, params.width * w, params.height * h)
+        }
+    };
+    
+    let mut rect = CropRect {
+        x: x.round() as u32,
+        y: y.round() as u32,
+        width: width.round() as u32,
+        height: height.round() as u32,
+    };
+    
+    if !params.angle.is_some_and(|angle| angle != 0.0) {
+        if rect.x >= img_width || rect.y >= img_height {
+            return Err(AppError::InvalidOperation {
+                details: format!(
+                    "Crop position ({}, {}) is outside image bounds ({}x{})",
+                    rect.x, rect.y, img_width, img_height
+                ),
+            });
+        }
+        rect.width = rect.width.min(img_width - rect.x);
+        rect.height = rect.height.min(img_height - rect.y);
+    }
+    
+    let Some(aspect) = params.aspect else {
+        return Ok(rect);
+    };
+    
+    let ratio = aspect.ratio();
+    if !ratio.is_finite() || ratio <= 0.0 {
+        return Err(AppError::InvalidOperation {
+            details: format!("Aspect ratio must be positive, got {:?}", aspect),
+        });
+    }
+    if rect.width == 0 || rect.height == 0 {
+        return Err(AppError::InvalidOperation {
+            details: "Crop dimensions must be at least 1x1".to_string(),
+        });
+    }
+    
+    let inner = crop_with_aspect_ratio(rect.width, rect.height, ratio, Anchor::Center);
+    
+    Ok(CropRect {
+        x: rect.x + inner.x,
+        y: rect.y + inner.y,
+        width: inner.width.max(1),
+        height: inner.height.max(1),
+    })
+}
+
+/// Crop an image with optional normalised coordinates, aspect ratio and rotation
+/// 
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Crop parameters
+/// 
+/// # Returns
+/// Cropped image or error if the parameters are invalid
+pub fn crop(img: &DynamicImage, params: &CropParams) -> Result<DynamicImage, AppError> {
+    let (img_width, img_height) = img.dimensions();
+    let rect = resolve_crop_rect(img_width, img_height, params)?;
+    
+    match params.angle {
+        Some(angle) if angle != 0.0 => crop_rotated(img, &rect, angle),
+        _ => crop_image(img, &rect),
+    }
+}
+
 /// Calculate crop rectangle maintaining aspect ratio
 /// 
 /// # Parameters
 /// - `img_width`: Original image width
 /// - `img_height`: Original image height
 /// - `desired_aspect`: Desired aspect ratio (width/height)
-/// - `from_center`: If true, crop from center; otherwise from top-left
+/// - `anchor`: Where the crop sits within the image
 /// 
 /// # Returns
 /// CropRect that maintains the desired aspect ratio
-#[allow(dead_code)]
 pub fn crop_with_aspect_ratio(
     img_width: u32,
     img_height: u32,
     desired_aspect: f32,
-    from_center: bool,
+    anchor: Anchor,
 ) -> CropRect {
     let img_aspect = img_width as f32 / img_height as f32;
     
@@ -75,19 +216,12 @@ pub fn crop_with_aspect_ratio(
         (img_width, new_height)
     };
     
-    let (x, y) = if from_center {
-        // Center the crop
-        let x = (img_width - crop_width) / 2;
-        let y = (img_height - crop_height) / 2;
-        (x, y)
-    } else {
-        // Crop from top-left
-        (0, 0)
-    };
+    // The crop never exceeds the image, so offsets are non-negative
+    let (x, y) = anchor_offset(anchor, (img_width, img_height), (crop_width, crop_height));
     
     CropRect {
-        x,
-        y,
+        x: x as u32,
+        y: y as u32,
         width: crop_width,
         height: crop_height,
     }
@@ -96,6 +230,7 @@ pub fn crop_with_aspect_ratio(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::operations::AspectRatio;
     
     #[test]
     fn test_crop_image() {
@@ -146,7 +281,7 @@ mod tests {
     #[test]
     fn test_crop_with_aspect_ratio_wider() {
         // 16:9 aspect ratio on a square image
-        let rect = crop_with_aspect_ratio(100, 100, 16.0 / 9.0, true);
+        let rect = crop_with_aspect_ratio(100, 100, 16.0 / 9.0, Anchor::Center);
         
         // Should crop height to maintain 16:9
         assert_eq!(rect.width, 100);
@@ -159,7 +294,7 @@ mod tests {
     #[test]
     fn test_crop_with_aspect_ratio_taller() {
         // 1:2 aspect ratio on a square image
-        let rect = crop_with_aspect_ratio(100, 100, 0.5, true);
+        let rect = crop_with_aspect_ratio(100, 100, 0.5, Anchor::Center);
         
         // Should crop width to maintain 1:2
         assert!(rect.width < 100);
@@ -168,4 +303,90 @@ mod tests {
         // Should be centered
         assert!(rect.x > 0);
     }
+    
+    #[test]
+    fn test_crop_with_aspect_ratio_anchor() {
+        let rect = crop_with_aspect_ratio(200, 100, 1.0, Anchor::TopLeft);
+        assert_eq!(rect, CropRect { x: 0, y: 0, width: 100, height: 100 });
+        
+        let rect = crop_with_aspect_ratio(200, 100, 1.0, Anchor::Right);
+        assert_eq!(rect, CropRect { x: 100, y: 0, width: 100, height: 100 });
+    }
+    
+    #[test]
+    fn test_crop_params_accepts_plain_rect() {
+        let params: CropParams = serde_json::from_str(r#"{"x": 10, "y": 20, "width": 30, "height": 40}"#).unwrap();
+        
+        assert_eq!(params, CropParams::from(CropRect { x: 10, y: 20, width: 30, height: 40 }));
+    }
+    
+    #[test]
+    fn test_crop_normalized_with_aspect() {
+        let img = DynamicImage::new_rgb8(200, 100);
+        let params = CropParams {
+            x: 0.0,
+            y: 0.0,
+            width: 0.5,
+            height: 1.0,
+            units: CropUnits::Normalized,
+            angle: None,
+            aspect: Some(AspectRatio::Square),
+        };
+        
+        let rect = resolve_crop_rect(200, 100, &params).unwrap();
+        assert_eq!(rect, CropRect { x: 0, y: 0, width: 100, height: 100 });
+        
+        let params = CropParams { aspect: Some(AspectRatio::Widescreen), ..params };
+        let cropped = crop(&img, &params).unwrap();
+        assert_eq!(cropped.dimensions(), (100, 56));
+        
+        let invalid = CropParams { width: 1.5, ..params };
+        assert!(crop(&img, &invalid).is_err());
+    }
+    
+    #[test]
+    fn test_crop_overhanging_rect_keeps_aspect() {
+        let img = DynamicImage::new_rgb8(200, 100);
+        // Reaches 60 pixels past the right edge before the preset is applied
+        let params = CropParams {
+            x: 0.5,
+            y: 0.0,
+            width: 0.8,
+            height: 1.0,
+            units: CropUnits::Normalized,
+            angle: None,
+            aspect: Some(AspectRatio::Square),
+        };
+        
+        let rect = resolve_crop_rect(200, 100, &params).unwrap();
+        assert_eq!(rect, CropRect { x: 100, y: 0, width: 100, height: 100 });
+        assert_eq!(crop(&img, &params).unwrap().dimensions(), (100, 100));
+        
+        let outside = CropParams { x: 1.0, ..params };
+        assert!(resolve_crop_rect(200, 100, &outside).is_err());
+    }
+    
+    #[test]
+    fn test_crop_rotated() {
+        // Left half black, right half white
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 40, |x, _| {
+            if x < 20 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        }));
+        let params = CropParams {
+            x: 10.0,
+            y: 10.0,
+            width: 20.0,
+            height: 20.0,
+            units: CropUnits::Pixels,
+            angle: Some(180.0),
+            aspect: None,
+        };
+        
+        let cropped = crop(&img, &params).unwrap().to_rgba8();
+        assert_eq!(cropped.dimensions(), (20, 20));
+        assert_eq!(cropped.get_pixel(2, 10), &Rgba([255, 255, 255, 255]));
+        assert_eq!(cropped.get_pixel(17, 10), &Rgba([0, 0, 0, 255]));
+        
+        assert!(crop_rotated(&img, &CropRect { x: 0, y: 0, width: 10, height: 10 }, 270.0).is_err());
+    }
 }
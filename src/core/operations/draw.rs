@@ -0,0 +1,413 @@
+use image::{DynamicImage, GrayImage, Luma, RgbaImage};
+use imageproc::drawing::draw_polygon_mut;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::core::operations::region::polygon_pixels;
+use crate::core::operations::text::blend_over;
+use crate::types::errors::AppError;
+use crate::types::operations::{Color, DrawParams, Point, Shape, ShapeGeometry};
+
+/// Subpixels per pixel along each axis when anti-aliasing
+const SUPERSAMPLE: u32 = 4;
+
+fn invalid(details: impl Into<String>) -> AppError {
+    AppError::InvalidOperation { details: details.into() }
+}
+
+fn validate_shape(shape: &Shape) -> Result<(), AppError> {
+    let finite = |values: &[f32]| values.iter().all(|value| value.is_finite());
+    match &shape.geometry {
+        ShapeGeometry::Rectangle { x, y, width, height, corner_radius } => {
+            if !finite(&[*x, *y, *width, *height, *corner_radius]) {
+                return Err(invalid("Rectangle coordinates must be finite"));
+            }
+            if *width <= 0.0 || *height <= 0.0 {
+                let details = format!("Rectangle size must be positive, got {}x{}", width, height);
+                return Err(invalid(details));
+            }
+            if *corner_radius < 0.0 {
+                let details = format!("Corner radius must not be negative, got {}", corner_radius);
+                return Err(invalid(details));
+            }
+        }
+        ShapeGeometry::Ellipse { center, radius_x, radius_y } => {
+            let (radius_x, radius_y) = (*radius_x, *radius_y);
+            if !finite(&[center.x, center.y, radius_x, radius_y]) || radius_x.min(radius_y) <= 0.0 {
+                return Err(invalid("Ellipse needs a finite centre and positive radii"));
+            }
+        }
+        ShapeGeometry::Line { start, end } | ShapeGeometry::Arrow { start, end, .. } => {
+            if !finite(&[start.x, start.y, end.x, end.y]) {
+                return Err(invalid("Line coordinates must be finite"));
+            }
+            if shape.stroke.is_none() {
+                return Err(invalid("Lines and arrows need a stroke colour"));
+            }
+        }
+    }
+
+    if let ShapeGeometry::Arrow { head_size: Some(size), .. } = shape.geometry
+        && !(size > 0.0 && size <= 2000.0)
+    {
+        return Err(invalid(format!("Arrowhead size must be between 0 and 2000, got {}", size)));
+    }
+    if shape.stroke.is_some() && !(shape.stroke_width > 0.0 && shape.stroke_width <= 500.0) {
+        let width = shape.stroke_width;
+        return Err(invalid(format!("Stroke width must be between 0 and 500, got {}", width)));
+    }
+    if shape.stroke.is_none() && shape.fill.is_none() {
+        return Err(invalid("Shape needs a stroke or fill colour"));
+    }
+
+    Ok(())
+}
+
+/// Number of segments approximating a curve of the given radius and sweep
+fn segments(radius: f32, sweep: f32) -> usize {
+    ((radius.max(1.0) * sweep).sqrt() * 4.0).clamp(4.0, 512.0) as usize
+}
+
+fn ellipse_path(center: Point, radius_x: f32, radius_y: f32) -> Vec<Point> {
+    let count = segments(radius_x.max(radius_y), 2.0 * PI);
+    (0..count)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / count as f32;
+            Point { x: center.x + radius_x * angle.cos(), y: center.y + radius_y * angle.sin() }
+        })
+        .collect()
+}
+
+fn rounded_rect_path(x: f32, y: f32, width: f32, height: f32, radius: f32) -> Vec<Point> {
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    if radius <= 0.0 {
+        return vec![
+            Point { x, y },
+            Point { x: x + width, y },
+            Point { x: x + width, y: y + height },
+            Point { x, y: y + height },
+        ];
+    }
+
+    // Quarter arcs clockwise from the top-right corner
+    let corners = [
+        (x + width - radius, y + radius, -FRAC_PI_2),
+        (x + width - radius, y + height - radius, 0.0),
+        (x + radius, y + height - radius, FRAC_PI_2),
+        (x + radius, y + radius, PI),
+    ];
+    let count = segments(radius, FRAC_PI_2);
+    corners
+        .iter()
+        .flat_map(|&(cx, cy, start)| {
+            (0..=count).map(move |i| {
+                let angle = start + FRAC_PI_2 * i as f32 / count as f32;
+                Point { x: cx + radius * angle.cos(), y: cy + radius * angle.sin() }
+            })
+        })
+        .collect()
+}
+
+/// Rectangle of the given width around the segment from `start` to `end`
+fn segment_path(start: Point, end: Point, width: f32) -> Vec<Point> {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return Vec::new();
+    }
+
+    let (nx, ny) = (-dy / length * width / 2.0, dx / length * width / 2.0);
+    vec![
+        Point { x: start.x + nx, y: start.y + ny },
+        Point { x: end.x + nx, y: end.y + ny },
+        Point { x: end.x - nx, y: end.y - ny },
+        Point { x: start.x - nx, y: start.y - ny },
+    ]
+}
+
+/// Coverage of one shape part, rasterised over its bounding box
+struct Raster {
+    x0: f32,
+    y0: f32,
+    scale: u32,
+    mask: GrayImage,
+}
+
+impl Raster {
+    /// Supersampled canvas covering the paths' bounds clipped to the image
+    fn covering(paths: &[Vec<Point>], width: u32, height: u32, scale: u32) -> Option<Self> {
+        let points = || paths.iter().flatten();
+        let min_x = points().map(|p| p.x).fold(f32::INFINITY, f32::min).floor().max(0.0);
+        let min_y = points().map(|p| p.y).fold(f32::INFINITY, f32::min).floor().max(0.0);
+        let max_x = points().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max).ceil();
+        let max_y = points().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max).ceil();
+        let (max_x, max_y) = (max_x.min(width as f32), max_y.min(height as f32));
+        if max_x <= min_x || max_y <= min_y {
+            return None;
+        }
+
+        let mask_width = (max_x - min_x) as u32 * scale;
+        let mask_height = (max_y - min_y) as u32 * scale;
+        Some(Self { x0: min_x, y0: min_y, scale, mask: GrayImage::new(mask_width, mask_height) })
+    }
+
+    /// Fill a polygon with `value`; 0 cuts a hole in what was drawn before
+    fn polygon(&mut self, path: &[Point], value: u8) {
+        let scale = self.scale as f64;
+        let (x0, y0) = (self.x0 as f64, self.y0 as f64);
+        let path: Vec<(f64, f64)> =
+            path.iter().map(|p| ((p.x as f64 - x0) * scale, (p.y as f64 - y0) * scale)).collect();
+
+        let points = polygon_pixels(&path, self.mask.width(), self.mask.height());
+        if !points.is_empty() {
+            draw_polygon_mut(&mut self.mask, &points, Luma([value]));
+        }
+    }
+
+    /// Average the subpixels and paint `color` onto the image
+    fn blend(&self, output: &mut RgbaImage, color: Color) {
+        let samples = (self.scale * self.scale) as f32 * 255.0;
+        for y in 0..self.mask.height() / self.scale {
+            for x in 0..self.mask.width() / self.scale {
+                let mut sum = 0u32;
+                for sy in 0..self.scale {
+                    for sx in 0..self.scale {
+                        let (mx, my) = (x * self.scale + sx, y * self.scale + sy);
+                        sum += self.mask.get_pixel(mx, my)[0] as u32;
+                    }
+                }
+                if sum > 0 {
+                    let pixel = output.get_pixel_mut(self.x0 as u32 + x, self.y0 as u32 + y);
+                    blend_over(pixel, color, sum as f32 / samples);
+                }
+            }
+        }
+    }
+}
+
+/// Paint the union of `fill` minus `holes` in one colour
+fn paint(
+    output: &mut RgbaImage,
+    fill: &[Vec<Point>],
+    holes: &[Vec<Point>],
+    color: Color,
+    scale: u32,
+) {
+    let (width, height) = output.dimensions();
+    if let Some(mut raster) = Raster::covering(fill, width, height, scale) {
+        for path in fill {
+            raster.polygon(path, 255);
+        }
+        for path in holes {
+            raster.polygon(path, 0);
+        }
+        raster.blend(output, color);
+    }
+}
+
+fn draw_shape(output: &mut RgbaImage, shape: &Shape) {
+    let scale = if shape.antialias { SUPERSAMPLE } else { 1 };
+    let half = shape.stroke_width / 2.0;
+
+    match shape.geometry {
+        ShapeGeometry::Rectangle { x, y, width, height, corner_radius } => {
+            let path = |grow: f32| {
+                let radius = if corner_radius > 0.0 { corner_radius + grow } else { 0.0 };
+                let (grown_width, grown_height) = (width + grow * 2.0, height + grow * 2.0);
+                rounded_rect_path(x - grow, y - grow, grown_width, grown_height, radius)
+            };
+            if let Some(color) = shape.fill {
+                paint(output, &[path(0.0)], &[], color, scale);
+            }
+            if let Some(color) = shape.stroke {
+                let inner = if width > shape.stroke_width && height > shape.stroke_width {
+                    vec![path(-half)]
+                } else {
+                    Vec::new()
+                };
+                paint(output, &[path(half)], &inner, color, scale);
+            }
+        }
+        ShapeGeometry::Ellipse { center, radius_x, radius_y } => {
+            if let Some(color) = shape.fill {
+                paint(output, &[ellipse_path(center, radius_x, radius_y)], &[], color, scale);
+            }
+            if let Some(color) = shape.stroke {
+                let outer = ellipse_path(center, radius_x + half, radius_y + half);
+                let inner = if radius_x > half && radius_y > half {
+                    vec![ellipse_path(center, radius_x - half, radius_y - half)]
+                } else {
+                    Vec::new()
+                };
+                paint(output, &[outer], &inner, color, scale);
+            }
+        }
+        ShapeGeometry::Line { start, end } => {
+            if let Some(color) = shape.stroke {
+                let paths = [
+                    segment_path(start, end, shape.stroke_width),
+                    ellipse_path(start, half, half),
+                    ellipse_path(end, half, half),
+                ];
+                paint(output, &paths, &[], color, scale);
+            }
+        }
+        ShapeGeometry::Arrow { start, end, head_size } => {
+            let Some(color) = shape.stroke else { return };
+            let (dx, dy) = (end.x - start.x, end.y - start.y);
+            let length = (dx * dx + dy * dy).sqrt();
+            if length == 0.0 {
+                return;
+            }
+
+            // The shaft stops where the head begins so its end is hidden under the head
+            let head = head_size.unwrap_or(shape.stroke_width * 4.0).min(length);
+            let (ux, uy) = (dx / length, dy / length);
+            let base = Point { x: end.x - ux * head, y: end.y - uy * head };
+            let wing = head * 0.6;
+            let head_path = vec![
+                end,
+                Point { x: base.x - uy * wing, y: base.y + ux * wing },
+                Point { x: base.x + uy * wing, y: base.y - ux * wing },
+            ];
+            let paths = [
+                segment_path(start, base, shape.stroke_width),
+                ellipse_path(start, half, half),
+                head_path,
+            ];
+            paint(output, &paths, &[], color, scale);
+        }
+    }
+}
+
+/// Draw shapes onto an image
+///
+/// All shapes are validated before any is drawn, so a bad shape leaves the
+/// image untouched.
+pub fn draw(img: &DynamicImage, params: &DrawParams) -> Result<DynamicImage, AppError> {
+    if params.shapes.is_empty() {
+        return Err(invalid("Draw needs at least 1 shape"));
+    }
+    for shape in &params.shapes {
+        validate_shape(shape)?;
+    }
+
+    let mut output = img.to_rgba8();
+    for shape in &params.shapes {
+        draw_shape(&mut output, shape);
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0, a: 255 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255, a: 255 };
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn white(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(WHITE)))
+    }
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32, corner_radius: f32) -> ShapeGeometry {
+        ShapeGeometry::Rectangle { x, y, width, height, corner_radius }
+    }
+
+    fn shape(geometry: ShapeGeometry, stroke: Option<Color>, fill: Option<Color>) -> Shape {
+        Shape { geometry, stroke, stroke_width: 4.0, fill, antialias: true }
+    }
+
+    fn draw_one(img: &DynamicImage, shape: Shape) -> RgbaImage {
+        draw(img, &DrawParams { shapes: vec![shape] }).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn test_rectangle_stroke_and_fill() {
+        let rect = rect(10.0, 10.0, 40.0, 20.0, 0.0);
+        let result = draw_one(&white(60, 40), shape(rect, Some(RED), Some(BLUE)));
+
+        // The stroke straddles the edge at x = 10, covering 8..12
+        assert_eq!(*result.get_pixel(9, 20), RED.to_rgba());
+        assert_eq!(*result.get_pixel(11, 20), RED.to_rgba());
+        assert_eq!(*result.get_pixel(30, 20), BLUE.to_rgba());
+        assert_eq!(*result.get_pixel(6, 20), Rgba(WHITE));
+        assert_eq!(*result.get_pixel(30, 36), Rgba(WHITE));
+    }
+
+    #[test]
+    fn test_rounded_corners_and_ellipse() {
+        let rounded = rect(0.0, 0.0, 40.0, 40.0, 15.0);
+        let result = draw_one(&white(40, 40), shape(rounded, None, Some(RED)));
+        assert_eq!(*result.get_pixel(0, 0), Rgba(WHITE));
+        assert_eq!(*result.get_pixel(0, 20), RED.to_rgba());
+        assert_eq!(*result.get_pixel(20, 20), RED.to_rgba());
+
+        let center = point(30.0, 20.0);
+        let ellipse = ShapeGeometry::Ellipse { center, radius_x: 25.0, radius_y: 10.0 };
+        let result = draw_one(&white(60, 40), shape(ellipse, Some(BLUE), None));
+        assert_eq!(*result.get_pixel(30, 10), BLUE.to_rgba());
+        assert_eq!(*result.get_pixel(5, 20), BLUE.to_rgba());
+        assert_eq!(*result.get_pixel(30, 20), Rgba(WHITE));
+        assert_eq!(*result.get_pixel(30, 2), Rgba(WHITE));
+
+        // Anti-aliased edges blend with the background
+        assert!(result.pixels().any(|p| p[0] > 0 && p[0] < 255));
+    }
+
+    #[test]
+    fn test_lines_and_arrows() {
+        let line = ShapeGeometry::Line { start: point(5.0, 20.0), end: point(55.0, 20.0) };
+        let result = draw_one(&white(60, 40), shape(line, Some(RED), None));
+        assert_eq!(*result.get_pixel(30, 19), RED.to_rgba());
+        assert_eq!(*result.get_pixel(30, 24), Rgba(WHITE));
+        // Round caps extend past the end points
+        assert_eq!(*result.get_pixel(4, 20), RED.to_rgba());
+
+        let arrow = ShapeGeometry::Arrow {
+            start: point(5.0, 20.0),
+            end: point(55.0, 20.0),
+            head_size: Some(20.0),
+        };
+        let result = draw_one(&white(60, 40), shape(arrow, Some(RED), None));
+        assert_eq!(*result.get_pixel(37, 28), RED.to_rgba());
+        assert_eq!(*result.get_pixel(20, 28), Rgba(WHITE));
+        assert_eq!(*result.get_pixel(57, 20), Rgba(WHITE));
+    }
+
+    #[test]
+    fn test_far_off_points_are_clipped() {
+        // Edges reaching a billion pixels away are clipped before rasterising
+        let line = ShapeGeometry::Line { start: point(-1e9, 20.0), end: point(1e9, 20.0) };
+        let result = draw_one(&white(60, 40), shape(line, Some(RED), None));
+        assert_eq!(*result.get_pixel(0, 20), RED.to_rgba());
+        assert_eq!(*result.get_pixel(59, 20), RED.to_rgba());
+        assert_eq!(*result.get_pixel(30, 30), Rgba(WHITE));
+
+        let huge = rect(-1e9, -1e9, 2e9, 2e9, 0.0);
+        let result = draw_one(&white(60, 40), shape(huge, Some(BLUE), Some(RED)));
+        assert!(result.pixels().all(|p| *p == RED.to_rgba()));
+    }
+
+    #[test]
+    fn test_aliased_drawing_and_validation() {
+        let rect = rect(2.5, 2.5, 10.0, 10.0, 0.0);
+        let aliased = Shape { antialias: false, ..shape(rect.clone(), None, Some(RED)) };
+        let result = draw_one(&white(20, 20), aliased);
+        assert!(result.pixels().all(|p| *p == Rgba(WHITE) || *p == RED.to_rgba()));
+
+        let img = white(20, 20);
+        let line = ShapeGeometry::Line { start: point(0.0, 0.0), end: point(5.0, 5.0) };
+        assert!(draw(&img, &DrawParams { shapes: vec![] }).is_err());
+        assert!(draw(&img, &DrawParams { shapes: vec![shape(line, None, Some(RED))] }).is_err());
+        assert!(draw(&img, &DrawParams { shapes: vec![shape(rect.clone(), None, None)] }).is_err());
+        let thin = Shape { stroke_width: 0.0, ..shape(rect, Some(RED), None) };
+        assert!(draw(&img, &DrawParams { shapes: vec![thin] }).is_err());
+    }
+}
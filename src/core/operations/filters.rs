@@ -3,7 +3,7 @@ use rayon::prelude::*;
 
 /// Apply grayscale filter
 pub fn grayscale(img: &DynamicImage) -> DynamicImage {
-    DynamicImage::ImageLuma8(img.to_luma8())
+    DynamicImage::ImageLumaA8(img.to_luma_alpha8())
 }
 
 /// Apply sepia filter
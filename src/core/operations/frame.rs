@@ -0,0 +1,302 @@
+use image::{DynamicImage, GrayImage, Luma, RgbaImage, imageops};
+use imageproc::filter::gaussian_blur_f32;
+use crate::core::operations::text::blend_over;
+use crate::types::errors::AppError;
+use crate::types::operations::{BorderWidth, Color, FrameParams, StrokePosition};
+use crate::utils::preview::validate_dimensions;
+
+/// Upper limit for border and stroke widths in pixels
+const MAX_WIDTH: u32 = 10000;
+
+/// Border widths as (top, right, bottom, left)
+fn border_sides(params: &FrameParams) -> (u32, u32, u32, u32) {
+    match params.border.map(|border| border.width) {
+        None => (0, 0, 0, 0),
+        Some(BorderWidth::Uniform { width }) => (width, width, width, width),
+        Some(BorderWidth::Sides { top, right, bottom, left }) => (top, right, bottom, left),
+        // Saturate so oversized widths fail validation instead of overflowing
+        Some(BorderWidth::Polaroid { width }) => (width, width, width.saturating_mul(7) / 2, width),
+    }
+}
+
+fn validate(params: &FrameParams) -> Result<(), AppError> {
+    let (top, right, bottom, left) = border_sides(params);
+    if [top, right, bottom, left].iter().any(|width| *width > MAX_WIDTH) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Border widths must be at most {}", MAX_WIDTH),
+        });
+    }
+    if let Some(stroke) = &params.stroke
+        && !(1..=MAX_WIDTH).contains(&stroke.width)
+    {
+        let width = stroke.width;
+        return Err(AppError::InvalidOperation {
+            details: format!("Stroke width must be between 1 and {}, got {}", MAX_WIDTH, width),
+        });
+    }
+    if !(params.corner_radius >= 0.0 && params.corner_radius.is_finite()) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Corner radius must be 0 or more, got {}", params.corner_radius),
+        });
+    }
+    if let Some(shadow) = &params.shadow {
+        let offset_limit = MAX_WIDTH as f32;
+        if !(shadow.offset_x.abs() <= offset_limit && shadow.offset_y.abs() <= offset_limit) {
+            return Err(AppError::InvalidOperation {
+                details: format!("Shadow offset must be at most {} pixels", MAX_WIDTH),
+            });
+        }
+        if !(0.0..=100.0).contains(&shadow.blur) {
+            return Err(AppError::InvalidOperation {
+                details: format!("Shadow blur must be between 0 and 100, got {}", shadow.blur),
+            });
+        }
+    }
+
+    let has_border = top + right + bottom + left > 0;
+    let has_corners = params.corner_radius > 0.0;
+    if !has_border && !has_corners && params.stroke.is_none() && params.shadow.is_none() {
+        return Err(AppError::InvalidOperation {
+            details: "Frame needs a border, stroke, rounded corners or shadow".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Signed distance from a pixel centre to a rounded rectangle, negative inside
+fn rounded_rect_distance(x: u32, y: u32, rect: (f32, f32, f32, f32), radius: f32) -> f32 {
+    let (left, top, width, height) = rect;
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    let qx = (x as f32 + 0.5 - (left + width / 2.0)).abs() - (width / 2.0 - radius);
+    let qy = (y as f32 + 0.5 - (top + height / 2.0)).abs() - (height / 2.0 - radius);
+
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+/// Fraction of a pixel covered by the area at a signed distance
+fn inside(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// Cast a blurred shadow of `framed` onto a larger canvas
+fn drop_shadow(framed: &RgbaImage, params: &FrameParams) -> Result<RgbaImage, AppError> {
+    let Some(shadow) = params.shadow else {
+        return Ok(framed.clone());
+    };
+
+    let spread = shadow.blur * 3.0;
+    let pad_left = (spread - shadow.offset_x).max(0.0).ceil() as u32;
+    let pad_top = (spread - shadow.offset_y).max(0.0).ceil() as u32;
+    let pad_right = (spread + shadow.offset_x).max(0.0).ceil() as u32;
+    let pad_bottom = (spread + shadow.offset_y).max(0.0).ceil() as u32;
+    let width = framed.width() + pad_left + pad_right;
+    let height = framed.height() + pad_top + pad_bottom;
+    validate_dimensions(width, height).map_err(|details| AppError::InvalidOperation { details })?;
+
+    let mut silhouette = GrayImage::new(width, height);
+    let x = pad_left as i64 + shadow.offset_x.round() as i64;
+    let y = pad_top as i64 + shadow.offset_y.round() as i64;
+    let alpha = GrayImage::from_fn(framed.width(), framed.height(), |fx, fy| {
+        Luma([framed.get_pixel(fx, fy)[3]])
+    });
+    imageops::replace(&mut silhouette, &alpha, x, y);
+    if shadow.blur > 0.0 {
+        silhouette = gaussian_blur_f32(&silhouette, shadow.blur);
+    }
+
+    let mut output = RgbaImage::new(width, height);
+    for (x, y, value) in silhouette.enumerate_pixels() {
+        blend_over(output.get_pixel_mut(x, y), shadow.color, value[0] as f32 / 255.0);
+    }
+    imageops::overlay(&mut output, framed, pad_left as i64, pad_top as i64);
+
+    Ok(output)
+}
+
+/// Frame an image with strokes, a border, rounded corners and a drop shadow
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Frame layers, from the inner stroke out to the shadow
+///
+/// # Returns
+/// RGBA image, transparent outside rounded corners and around the shadow
+/// unless a background colour is given
+pub fn frame(img: &DynamicImage, params: &FrameParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+
+    let source = img.to_rgba8();
+    let (width, height) = source.dimensions();
+    let (top, right, bottom, left) = border_sides(params);
+    let outer_stroke = match params.stroke {
+        Some(stroke) if stroke.position == StrokePosition::Outer => stroke.width,
+        _ => 0,
+    };
+
+    let framed_width = width + outer_stroke * 2 + left + right;
+    let framed_height = height + outer_stroke * 2 + top + bottom;
+    validate_dimensions(framed_width, framed_height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    let fill = params.border.map_or(Color { r: 0, g: 0, b: 0, a: 0 }, |border| border.color);
+    let mut framed = RgbaImage::from_pixel(framed_width, framed_height, fill.to_rgba());
+
+    // Inner corners are rounded by whatever radius the surrounding layers leave over
+    let radius = params.corner_radius;
+    let border_min = top.min(right).min(bottom).min(left) as f32;
+    let image_x = left + outer_stroke;
+    let image_y = top + outer_stroke;
+    let image_rect = (image_x as f32, image_y as f32, width as f32, height as f32);
+    let image_radius = (radius - border_min - outer_stroke as f32).max(0.0);
+
+    if let Some(stroke) = params.stroke
+        && outer_stroke > 0
+    {
+        let ring_size = (width + outer_stroke * 2, height + outer_stroke * 2);
+        let ring_rect = (left as f32, top as f32, ring_size.0 as f32, ring_size.1 as f32);
+        let ring_radius = (radius - border_min).max(0.0);
+        for (x, y, pixel) in framed.enumerate_pixels_mut() {
+            let coverage = inside(rounded_rect_distance(x, y, ring_rect, ring_radius));
+            blend_over(pixel, stroke.color, coverage);
+        }
+    }
+    imageops::replace(&mut framed, &source, image_x as i64, image_y as i64);
+
+    if let Some(stroke) = params.stroke
+        && stroke.position == StrokePosition::Inner
+    {
+        for y in image_y..image_y + height {
+            for x in image_x..image_x + width {
+                let distance = rounded_rect_distance(x, y, image_rect, image_radius);
+                let depth = (distance + stroke.width as f32 + 0.5).clamp(0.0, 1.0);
+                blend_over(framed.get_pixel_mut(x, y), stroke.color, inside(distance) * depth);
+            }
+        }
+    }
+
+    if radius > 0.0 {
+        let outer_rect = (0.0, 0.0, framed_width as f32, framed_height as f32);
+        for (x, y, pixel) in framed.enumerate_pixels_mut() {
+            let coverage = inside(rounded_rect_distance(x, y, outer_rect, radius));
+            pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+        }
+    }
+
+    let mut output = drop_shadow(&framed, params)?;
+    if let Some(background) = params.background {
+        let (width, height) = output.dimensions();
+        let mut backdrop = RgbaImage::from_pixel(width, height, background.to_rgba());
+        imageops::overlay(&mut backdrop, &output, 0, 0);
+        output = backdrop;
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{FrameBorder, FrameShadow, FrameStroke};
+    use image::{GenericImageView, Rgba};
+
+    const RED: Color = Color { r: 255, g: 0, b: 0, a: 255 };
+    const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+    const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+
+    fn grey(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([128, 128, 128, 255])))
+    }
+
+    fn params() -> FrameParams {
+        FrameParams {
+            border: None,
+            stroke: None,
+            corner_radius: 0.0,
+            shadow: None,
+            background: None,
+        }
+    }
+
+    fn border(width: BorderWidth) -> Option<FrameBorder> {
+        Some(FrameBorder { width, color: WHITE })
+    }
+
+    #[test]
+    fn test_borders() {
+        let img = grey(20, 10);
+        let uniform = FrameParams { border: border(BorderWidth::Uniform { width: 5 }), ..params() };
+        let result = frame(&img, &uniform).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (30, 20));
+        assert_eq!(*result.get_pixel(4, 4), WHITE.to_rgba());
+        assert_eq!(*result.get_pixel(5, 5), Rgba([128, 128, 128, 255]));
+
+        let sides = BorderWidth::Sides { top: 1, right: 2, bottom: 3, left: 4 };
+        let result = frame(&img, &FrameParams { border: border(sides), ..params() }).unwrap();
+        assert_eq!(result.dimensions(), (26, 14));
+
+        let polaroid = BorderWidth::Polaroid { width: 4 };
+        let polaroid = FrameParams { border: border(polaroid), ..params() };
+        assert_eq!(frame(&img, &polaroid).unwrap().dimensions(), (28, 28));
+    }
+
+    #[test]
+    fn test_inner_and_outer_strokes() {
+        let img = grey(20, 20);
+        let inner = Some(FrameStroke { width: 2, color: RED, position: StrokePosition::Inner });
+        let result = frame(&img, &FrameParams { stroke: inner, ..params() }).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (20, 20));
+        assert_eq!(*result.get_pixel(1, 10), RED.to_rgba());
+        assert_eq!(*result.get_pixel(2, 10), Rgba([128, 128, 128, 255]));
+
+        let outer = Some(FrameStroke { width: 2, color: RED, position: StrokePosition::Outer });
+        let uniform = border(BorderWidth::Uniform { width: 3 });
+        let framed = FrameParams { stroke: outer, border: uniform, ..params() };
+        let result = frame(&img, &framed).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (30, 30));
+        assert_eq!(*result.get_pixel(2, 15), WHITE.to_rgba());
+        assert_eq!(*result.get_pixel(3, 15), RED.to_rgba());
+        assert_eq!(*result.get_pixel(4, 15), RED.to_rgba());
+        assert_eq!(*result.get_pixel(5, 15), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_rounded_corners_keep_alpha_or_fill() {
+        let img = grey(40, 40);
+        let rounded = FrameParams { corner_radius: 10.0, ..params() };
+        let result = frame(&img, &rounded).unwrap().to_rgba8();
+        assert_eq!(result.get_pixel(0, 0)[3], 0);
+        assert_eq!(result.get_pixel(39, 39)[3], 0);
+        assert_eq!(result.get_pixel(0, 20)[3], 255);
+        let edge = result.get_pixel(2, 3)[3];
+        assert!(edge > 0 && edge < 255, "corner edge alpha {}", edge);
+
+        let filled = FrameParams { background: Some(BLACK), ..rounded };
+        let result = frame(&img, &filled).unwrap().to_rgba8();
+        assert_eq!(*result.get_pixel(0, 0), BLACK.to_rgba());
+        assert_eq!(*result.get_pixel(20, 20), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_drop_shadow_and_validation() {
+        let img = grey(20, 20);
+        let shadow = Some(FrameShadow { offset_x: 4.0, offset_y: 4.0, blur: 0.0, color: BLACK });
+        let result = frame(&img, &FrameParams { shadow, ..params() }).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (24, 24));
+        assert_eq!(*result.get_pixel(22, 22), BLACK.to_rgba());
+        assert_eq!(result.get_pixel(22, 1)[3], 0);
+        assert_eq!(*result.get_pixel(10, 10), Rgba([128, 128, 128, 255]));
+
+        let blurred = Some(FrameShadow { offset_x: 0.0, offset_y: 0.0, blur: 2.0, color: BLACK });
+        let result = frame(&img, &FrameParams { shadow: blurred, ..params() }).unwrap();
+        assert_eq!(result.dimensions(), (32, 32));
+
+        assert!(frame(&img, &params()).is_err());
+        assert!(frame(&img, &FrameParams { corner_radius: -1.0, ..params() }).is_err());
+        let wide = BorderWidth::Uniform { width: 20000 };
+        assert!(frame(&img, &FrameParams { border: border(wide), ..params() }).is_err());
+        let polaroid = BorderWidth::Polaroid { width: u32::MAX / 2 };
+        assert!(frame(&img, &FrameParams { border: border(polaroid), ..params() }).is_err());
+    }
+}
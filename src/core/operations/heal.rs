@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use image::{DynamicImage, GrayImage, RgbaImage};
+use crate::core::operations::region::rasterize;
+use crate::types::errors::AppError;
+use crate::types::operations::HealParams;
+
+/// Pixel state during fast marching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Known,
+    Band,
+    Inside,
+}
+
+/// Band pixel ordered by arrival time (smallest first)
+#[derive(Debug, PartialEq)]
+struct Queued {
+    time: f32,
+    index: usize,
+}
+
+impl Eq for Queued {}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.total_cmp(&self.time).then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Fast marching state over the image
+struct Marcher<'a> {
+    width: usize,
+    height: usize,
+    radius: i64,
+    state: Vec<State>,
+    time: Vec<f32>,
+    image: &'a mut RgbaImage,
+}
+
+impl Marcher<'_> {
+    fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + use<> {
+        let (x, y, w, h) = (index % self.width, index / self.width, self.width, self.height);
+        [
+            (x > 0).then(|| index - 1),
+            (x + 1 < w).then(|| index + 1),
+            (y > 0).then(|| index - w),
+            (y + 1 < h).then(|| index + w),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn known_time(&self, x: i64, y: i64) -> Option<f32> {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        let index = y as usize * self.width + x as usize;
+        (self.state[index] == State::Known).then(|| self.time[index])
+    }
+
+    /// Solve the eikonal equation from one horizontal and one vertical neighbour
+    fn solve(&self, a: Option<f32>, b: Option<f32>) -> f32 {
+        match (a, b) {
+            (Some(t1), Some(t2)) => {
+                let r = (2.0 - (t1 - t2).powi(2)).max(0.0).sqrt();
+                let s = (t1 + t2 - r) / 2.0;
+                if s >= t1 && s >= t2 { s } else { s + r }
+            }
+            (Some(t), None) | (None, Some(t)) => 1.0 + t,
+            (None, None) => f32::MAX,
+        }
+    }
+
+    fn arrival_time(&self, index: usize) -> f32 {
+        let (x, y) = ((index % self.width) as i64, (index / self.width) as i64);
+        let left = self.known_time(x - 1, y);
+        let right = self.known_time(x + 1, y);
+        let up = self.known_time(x, y - 1);
+        let down = self.known_time(x, y + 1);
+
+        [(left, up), (left, down), (right, up), (right, down)]
+            .into_iter()
+            .map(|(a, b)| self.solve(a, b))
+            .fold(f32::MAX, f32::min)
+    }
+
+    /// Gradient of the arrival time, from neighbours outside the hole
+    fn time_gradient(&self, index: usize) -> (f32, f32) {
+        let (x, y) = ((index % self.width) as i64, (index / self.width) as i64);
+        let t = self.time[index];
+        let at = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+                return None;
+            }
+            let i = y as usize * self.width + x as usize;
+            (self.state[i] != State::Inside).then(|| self.time[i])
+        };
+        let derivative = |before: Option<f32>, after: Option<f32>| match (before, after) {
+            (Some(b), Some(a)) => (a - b) / 2.0,
+            (None, Some(a)) => a - t,
+            (Some(b), None) => t - b,
+            (None, None) => 0.0,
+        };
+
+        (derivative(at(x - 1, y), at(x + 1, y)), derivative(at(x, y - 1), at(x, y + 1)))
+    }
+
+    /// Fill a pixel from the weighted known pixels around it (Telea 2004)
+    fn fill(&mut self, index: usize) {
+        let (x, y) = ((index % self.width) as i64, (index / self.width) as i64);
+        let (gx, gy) = self.time_gradient(index);
+        let t = self.time[index];
+
+        let mut sum = [0.0f32; 4];
+        let mut total = 0.0f32;
+        for ny in (y - self.radius).max(0)..=(y + self.radius).min(self.height as i64 - 1) {
+            for nx in (x - self.radius).max(0)..=(x + self.radius).min(self.width as i64 - 1) {
+                let (rx, ry) = ((x - nx) as f32, (y - ny) as f32);
+                let length_sq = rx * rx + ry * ry;
+                let neighbour = ny as usize * self.width + nx as usize;
+                if length_sq == 0.0
+                    || length_sq > (self.radius * self.radius) as f32
+                    || self.state[neighbour] != State::Known
+                {
+                    continue;
+                }
+
+                let length = length_sq.sqrt();
+                let direction = ((rx * gx + ry * gy) / length).abs().max(1e-6);
+                let distance = 1.0 / (length_sq * length);
+                let level = 1.0 / (1.0 + (self.time[neighbour] - t).abs());
+                let weight = direction * distance * level;
+
+                let pixel = self.image.get_pixel(nx as u32, ny as u32);
+                for (channel, value) in sum.iter_mut().zip(pixel.0) {
+                    *channel += weight * value as f32;
+                }
+                total += weight;
+            }
+        }
+
+        if total > 0.0 {
+            let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+            for (value, channel) in pixel.0.iter_mut().zip(sum) {
+                *value = (channel / total).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Inpaint the masked pixels of an image with fast marching
+///
+/// # Parameters
+/// - `image`: Image to fill in place
+/// - `mask`: Non-zero where pixels should be replaced
+/// - `radius`: Neighbourhood radius used for each filled pixel
+pub fn inpaint(image: &mut RgbaImage, mask: &GrayImage, radius: u32) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let state: Vec<State> = mask
+        .pixels()
+        .map(|p| if p[0] > 0 { State::Inside } else { State::Known })
+        .collect();
+    let time = state
+        .iter()
+        .map(|s| if *s == State::Inside { f32::MAX } else { 0.0 })
+        .collect();
+
+    let mut marcher = Marcher {
+        width,
+        height,
+        radius: radius as i64,
+        state,
+        time,
+        image,
+    };
+
+    // The initial band is the known boundary around the hole
+    let mut queue = BinaryHeap::new();
+    for index in 0..width * height {
+        if marcher.state[index] == State::Inside {
+            for neighbour in marcher.neighbours(index) {
+                if marcher.state[neighbour] == State::Known {
+                    marcher.state[neighbour] = State::Band;
+                    queue.push(Queued { time: 0.0, index: neighbour });
+                }
+            }
+        }
+    }
+
+    while let Some(Queued { index, .. }) = queue.pop() {
+        if marcher.state[index] == State::Known {
+            continue;
+        }
+        marcher.state[index] = State::Known;
+
+        for neighbour in marcher.neighbours(index) {
+            if marcher.state[neighbour] == State::Known {
+                continue;
+            }
+
+            let time = marcher.arrival_time(neighbour).min(marcher.time[neighbour]);
+            marcher.time[neighbour] = time;
+
+            if marcher.state[neighbour] == State::Inside {
+                marcher.state[neighbour] = State::Band;
+                marcher.fill(neighbour);
+            }
+            queue.push(Queued { time, index: neighbour });
+        }
+    }
+}
+
+/// Heal masked regions of an image
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Regions to fill and neighbourhood radius
+///
+/// # Returns
+/// RGBA image with the regions filled from their surroundings
+pub fn heal(img: &DynamicImage, params: &HealParams) -> Result<DynamicImage, AppError> {
+    if !(1..=20).contains(&params.radius) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Heal radius must be between 1 and 20, got {}", params.radius),
+        });
+    }
+    if params.regions.is_empty() {
+        return Err(AppError::InvalidOperation {
+            details: "Heal needs at least one region".to_string(),
+        });
+    }
+
+    let mut output = img.to_rgba8();
+    let mask = rasterize(&params.regions, output.width(), output.height())?;
+    inpaint(&mut output, &mask, params.radius);
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{Point, Region};
+    use image::Rgba;
+
+    fn square(x: f32, y: f32, size: f32) -> Region {
+        Region::Polygon {
+            points: vec![
+                Point { x, y },
+                Point { x: x + size, y },
+                Point { x: x + size, y: y + size },
+                Point { x, y: y + size },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_heal_removes_spot() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(30, 30, |x, y| {
+            if (12..18).contains(&x) && (12..18).contains(&y) {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([200, 150, 100, 255])
+            }
+        }));
+        let params = HealParams { regions: vec![square(11.0, 11.0, 8.0)], radius: 5 };
+
+        let result = heal(&img, &params).unwrap().to_rgba8();
+        assert!(result.pixels().all(|p| *p == Rgba([200, 150, 100, 255])));
+    }
+
+    #[test]
+    fn test_heal_follows_gradient() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 20, |x, y| {
+            if (18..22).contains(&x) && (8..12).contains(&y) {
+                Rgba([255, 0, 255, 255])
+            } else {
+                Rgba([(x * 6) as u8, 100, 100, 255])
+            }
+        }));
+        let params = HealParams {
+            regions: vec![Region::Stroke {
+                points: vec![Point { x: 19.5, y: 9.5 }],
+                radius: 3.0,
+            }],
+            radius: 4,
+        };
+
+        let result = heal(&img, &params).unwrap().to_rgba8();
+        for x in 18..22 {
+            let pixel = result.get_pixel(x, 10);
+            assert!((pixel[0] as i32 - (x * 6) as i32).abs() <= 20, "pixel {} was {:?}", x, pixel);
+            assert_eq!(pixel[1], 100);
+        }
+    }
+
+    #[test]
+    fn test_heal_validation() {
+        let img = DynamicImage::new_rgb8(10, 10);
+
+        assert!(heal(&img, &HealParams { regions: vec![], radius: 5 }).is_err());
+        let regions = vec![square(1.0, 1.0, 2.0)];
+        assert!(heal(&img, &HealParams { regions, radius: 0 }).is_err());
+    }
+}
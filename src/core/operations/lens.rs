@@ -0,0 +1,216 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::geometric_transformations;
+use rayon::prelude::*;
+use crate::core::operations::perspective::interpolation;
+use crate::types::errors::AppError;
+use crate::types::operations::LensParams;
+
+/// Radial coordinate system centred on the image, normalised to the half diagonal
+#[derive(Debug, Clone, Copy)]
+struct RadialFrame {
+    cx: f32,
+    cy: f32,
+    norm_sq: f32,
+}
+
+impl RadialFrame {
+    fn new(width: u32, height: u32) -> Self {
+        let cx = (width as f32 - 1.0) / 2.0;
+        let cy = (height as f32 - 1.0) / 2.0;
+        Self {
+            cx,
+            cy,
+            norm_sq: (cx * cx + cy * cy).max(1.0),
+        }
+    }
+
+    /// Squared normalised radius of a point (1.0 at the corners)
+    fn radius_sq(&self, x: f32, y: f32) -> f32 {
+        ((x - self.cx).powi(2) + (y - self.cy).powi(2)) / self.norm_sq
+    }
+}
+
+fn validate(params: &LensParams) -> Result<(), AppError> {
+    for (name, k) in [("k1", params.k1), ("k2", params.k2), ("k3", params.k3)] {
+        if !(-1.0..=1.0).contains(&k) {
+            return Err(AppError::InvalidOperation {
+                details: format!("Distortion coefficient {} must be between -1.0 and 1.0, got {}", name, k),
+            });
+        }
+    }
+
+    for (name, scale) in [("Red", params.red_scale), ("Blue", params.blue_scale)] {
+        if !(0.9..=1.1).contains(&scale) {
+            return Err(AppError::InvalidOperation {
+                details: format!("{} channel scale must be between 0.9 and 1.1, got {}", name, scale),
+            });
+        }
+    }
+
+    if !(0.0..=2.0).contains(&params.vignetting) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Vignetting must be between 0.0 and 2.0, got {}", params.vignetting),
+        });
+    }
+
+    Ok(())
+}
+
+/// Resample the image through the distortion model with an extra channel scale
+fn remap(source: &RgbaImage, params: &LensParams, scale: f32) -> RgbaImage {
+    if params.k1 == 0.0 && params.k2 == 0.0 && params.k3 == 0.0 && scale == 1.0 {
+        return source.clone();
+    }
+
+    let frame = RadialFrame::new(source.width(), source.height());
+    let (k1, k2, k3) = (params.k1, params.k2, params.k3);
+
+    // Each output pixel samples the source at r * (1 + k1 r^2 + k2 r^4 + k3 r^6)
+    let mapping = move |x: f32, y: f32| {
+        let r2 = frame.radius_sq(x, y);
+        let factor = (1.0 + k1 * r2 + k2 * r2 * r2 + k3 * r2 * r2 * r2) * scale;
+        (frame.cx + (x - frame.cx) * factor, frame.cy + (y - frame.cy) * factor)
+    };
+
+    geometric_transformations::warp_with(
+        source,
+        mapping,
+        interpolation(params.interpolation),
+        Rgba([0, 0, 0, 0]),
+    )
+}
+
+/// Correct lens distortion, lateral chromatic aberration and vignetting
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Distortion coefficients, channel scales and vignetting amount
+///
+/// # Returns
+/// Corrected RGBA image with the same dimensions
+pub fn lens_correction(img: &DynamicImage, params: &LensParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+
+    let source = img.to_rgba8();
+    let (width, height) = source.dimensions();
+
+    // Green (and alpha) define the geometry; red and blue are scaled around it
+    let mut output = remap(&source, params, 1.0);
+
+    if params.red_scale != 1.0 || params.blue_scale != 1.0 {
+        let red = remap(&source, params, params.red_scale);
+        let blue = remap(&source, params, params.blue_scale);
+
+        output
+            .par_chunks_mut(4)
+            .zip(red.par_chunks(4))
+            .zip(blue.par_chunks(4))
+            .for_each(|((pixel, r), b)| {
+                pixel[0] = r[0];
+                pixel[2] = b[2];
+            });
+    }
+
+    if params.vignetting > 0.0 {
+        let frame = RadialFrame::new(width, height);
+        let amount = params.vignetting;
+
+        output
+            .par_chunks_mut(width as usize * 4)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.chunks_mut(4).enumerate() {
+                    let gain = 1.0 + amount * frame.radius_sq(x as f32, y as f32);
+                    for channel in &mut pixel[..3] {
+                        *channel = (*channel as f32 * gain).clamp(0.0, 255.0) as u8;
+                    }
+                }
+            });
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::Interpolation;
+
+    fn params() -> LensParams {
+        LensParams {
+            k1: 0.0,
+            k2: 0.0,
+            k3: 0.0,
+            red_scale: 1.0,
+            blue_scale: 1.0,
+            vignetting: 0.0,
+            interpolation: Interpolation::Bilinear,
+        }
+    }
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 10) as u8, (y * 10) as u8, ((x + y) * 5) as u8, 255])
+        }))
+    }
+
+    #[test]
+    fn test_lens_identity() {
+        let img = gradient(21, 21);
+        let result = lens_correction(&img, &params()).unwrap();
+        assert_eq!(result.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn test_lens_distortion_keeps_center() {
+        let img = gradient(21, 21);
+        let mut p = params();
+        p.k1 = -0.3;
+
+        let result = lens_correction(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (21, 21));
+        assert_eq!(result.get_pixel(10, 10), img.to_rgba8().get_pixel(10, 10));
+        assert_ne!(result.get_pixel(0, 10), img.to_rgba8().get_pixel(0, 10));
+    }
+
+    #[test]
+    fn test_lens_chromatic_aberration_only_moves_red_and_blue() {
+        let img = gradient(21, 21);
+        let mut p = params();
+        p.red_scale = 0.95;
+        p.blue_scale = 1.05;
+
+        let result = lens_correction(&img, &p).unwrap().to_rgba8();
+        let original = img.to_rgba8();
+        assert!(result.pixels().zip(original.pixels()).all(|(a, b)| a[1] == b[1]));
+        assert_ne!(result.get_pixel(20, 10)[0], original.get_pixel(20, 10)[0]);
+    }
+
+    #[test]
+    fn test_lens_vignetting_brightens_corners() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(21, 21, Rgba([100, 100, 100, 255])));
+        let mut p = params();
+        p.vignetting = 1.0;
+
+        let result = lens_correction(&img, &p).unwrap().to_rgba8();
+        assert_eq!(result.get_pixel(10, 10)[0], 100);
+        assert_eq!(result.get_pixel(0, 0)[0], 200);
+    }
+
+    #[test]
+    fn test_lens_validation() {
+        let img = gradient(5, 5);
+
+        let mut p = params();
+        p.k2 = 2.0;
+        assert!(lens_correction(&img, &p).is_err());
+
+        let mut p = params();
+        p.blue_scale = 1.5;
+        assert!(lens_correction(&img, &p).is_err());
+
+        let mut p = params();
+        p.vignetting = -0.5;
+        assert!(lens_correction(&img, &p).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use crate::core::operations::region::{Coverage, brush_falloff, paint_stroke};
+use crate::core::operations::selection::{rasterize_selection, validate_selection};
+use crate::types::errors::AppError;
+use crate::types::operations::{Mask, MaskMode, MaskShape, Point};
+
+fn invalid(details: String) -> AppError {
+    AppError::InvalidOperation { details }
+}
+
+fn is_finite(point: &Point) -> bool {
+    point.x.is_finite() && point.y.is_finite()
+}
+
+fn validate_shape(shape: &MaskShape) -> Result<(), AppError> {
+    match shape {
+        MaskShape::Linear { start, end } => {
+            if !is_finite(start) || !is_finite(end) || start == end {
+                return Err(invalid("Linear mask needs two distinct finite points".to_string()));
+            }
+        }
+        MaskShape::Radial { center, radius_x, radius_y, angle, feather } => {
+            if !is_finite(center) || !angle.is_finite() {
+                return Err(invalid("Radial mask centre and angle must be finite".to_string()));
+            }
+            if !(*radius_x > 0.0 && *radius_y > 0.0 && radius_x.is_finite() && radius_y.is_finite()) {
+                return Err(invalid(format!(
+                    "Radial mask radii must be positive, got {} and {}",
+                    radius_x, radius_y
+                )));
+            }
+            if !(0.0..=1.0).contains(feather) {
+                return Err(invalid(format!("Radial mask feather must be between 0 and 1, got {}", feather)));
+            }
+        }
+        MaskShape::Brush { points, radius, hardness } => {
+            if points.is_empty() || !points.iter().all(is_finite) {
+                return Err(invalid("Brush mask needs at least 1 finite point".to_string()));
+            }
+            if !(*radius > 0.0 && *radius <= 1000.0) {
+                return Err(invalid(format!("Brush radius must be between 0 and 1000, got {}", radius)));
+            }
+            if !(0.0..=1.0).contains(hardness) {
+                return Err(invalid(format!("Brush hardness must be between 0 and 1, got {}", hardness)));
+            }
+        }
+        MaskShape::Luminance { min, max, feather } => {
+            if !(0.0..=1.0).contains(min) || !(0.0..=1.0).contains(max) || min > max {
+                return Err(invalid(format!(
+                    "Luminance range must satisfy 0 <= min <= max <= 1, got {} to {}",
+                    min, max
+                )));
+            }
+            if !(0.0..=1.0).contains(feather) {
+                return Err(invalid(format!("Luminance feather must be between 0 and 1, got {}", feather)));
+            }
+        }
+        MaskShape::Color { tolerance, feather, .. } => {
+            if !(0.0..=1.0).contains(tolerance) || !(0.0..=1.0).contains(feather) {
+                return Err(invalid(format!(
+                    "Colour tolerance and feather must be between 0 and 1, got {} and {}",
+                    tolerance, feather
+                )));
+            }
+        }
+        MaskShape::Selection { selection } => validate_selection(selection)?,
+    }
+
+    Ok(())
+}
+
+/// Weight of `value` against a closed range, fading linearly over `feather` outside it
+fn range_weight(value: f32, min: f32, max: f32, feather: f32) -> f32 {
+    let outside = (min - value).max(value - max).max(0.0);
+    if outside == 0.0 {
+        1.0
+    } else if feather > 0.0 {
+        (1.0 - outside / feather).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+fn luminance(pixel: &Rgba<u8>) -> f32 {
+    (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.0
+}
+
+/// Rasterise one shape; image-dependent shapes sample `source`
+fn shape_coverage(shape: &MaskShape, source: &RgbaImage) -> Result<Coverage, AppError> {
+    let (width, height) = source.dimensions();
+
+    let coverage = match shape {
+        MaskShape::Linear { start, end } => {
+            let (dx, dy) = (end.x - start.x, end.y - start.y);
+            let length_sq = dx * dx + dy * dy;
+            Coverage::from_fn(width, height, |x, y| {
+                let t = ((x as f32 - start.x) * dx + (y as f32 - start.y) * dy) / length_sq;
+                [1.0 - t.clamp(0.0, 1.0)].into()
+            })
+        }
+        MaskShape::Radial { center, radius_x, radius_y, angle, feather } => {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            Coverage::from_fn(width, height, |x, y| {
+                let (px, py) = (x as f32 - center.x, y as f32 - center.y);
+                let u = (px * cos + py * sin) / radius_x;
+                let v = (py * cos - px * sin) / radius_y;
+                [brush_falloff((u * u + v * v).sqrt(), 1.0, 1.0 - feather)].into()
+            })
+        }
+        MaskShape::Brush { points, radius, hardness } => {
+            let mut coverage = Coverage::new(width, height);
+            paint_stroke(&mut coverage, points, *radius, *hardness);
+            coverage
+        }
+        MaskShape::Luminance { min, max, feather } => Coverage::from_fn(width, height, |x, y| {
+            [range_weight(luminance(source.get_pixel(x, y)), *min, *max, *feather)].into()
+        }),
+        MaskShape::Color { color, tolerance, feather } => {
+            let target = color.to_rgba();
+            let scale = 255.0 * 3f32.sqrt();
+            Coverage::from_fn(width, height, |x, y| {
+                let pixel = source.get_pixel(x, y);
+                let distance = (0..3)
+                    .map(|c| (pixel[c] as f32 - target[c] as f32).powi(2))
+                    .sum::<f32>()
+                    .sqrt()
+                    / scale;
+                [range_weight(distance, 0.0, *tolerance, *feather)].into()
+            })
+        }
+        MaskShape::Selection { selection } => {
+            let selected = rasterize_selection(source, selection)?;
+            Coverage::from_fn(width, height, |x, y| [selected.get_pixel(x, y)[0] as f32 / 255.0].into())
+        }
+    };
+
+    Ok(coverage)
+}
+
+/// Rasterise a mask for an image
+///
+/// # Parameters
+/// - `source`: Image the mask is evaluated against (luminance and colour shapes sample it)
+/// - `mask`: Mask definition
+///
+/// # Returns
+/// Coverage of the image size, or error if a layer is invalid
+pub fn rasterize_mask(source: &RgbaImage, mask: &Mask) -> Result<Coverage, AppError> {
+    if mask.layers.is_empty() {
+        return Err(invalid("Mask needs at least one layer".to_string()));
+    }
+
+    let (width, height) = source.dimensions();
+    let mut combined = Coverage::new(width, height);
+    for layer in &mask.layers {
+        validate_shape(&layer.shape)?;
+        let coverage = shape_coverage(&layer.shape, source)?;
+
+        for (value, layer_value) in combined.iter_mut().zip(coverage.iter()) {
+            let layer_value = if layer.invert { 1.0 - layer_value } else { *layer_value };
+            *value = match layer.mode {
+                MaskMode::Add => value.max(layer_value),
+                MaskMode::Subtract => value.min(1.0 - layer_value),
+                MaskMode::Intersect => value.min(layer_value),
+            };
+        }
+    }
+
+    if mask.invert {
+        combined.iter_mut().for_each(|value| *value = 1.0 - *value);
+    }
+
+    Ok(combined)
+}
+
+/// Blend an operation's result with its input through a mask
+///
+/// # Parameters
+/// - `original`: Image before the operation
+/// - `edited`: Image after the operation, which must be the same size
+/// - `mask`: Where the edit applies
+///
+/// # Returns
+/// RGBA image taking `edited` where the mask is 1.0 and `original` where it is 0.0
+pub fn apply_masked(original: &DynamicImage, edited: &DynamicImage, mask: &Mask) -> Result<DynamicImage, AppError> {
+    if original.dimensions() != edited.dimensions() {
+        return Err(invalid("Masked operations must keep the image size".to_string()));
+    }
+
+    let mut output = original.to_rgba8();
+    let coverage = rasterize_mask(&output, mask)?;
+    let edited = edited.to_rgba8();
+
+    for ((pixel, target), weight) in output.pixels_mut().zip(edited.pixels()).zip(coverage.iter()) {
+        for (value, target) in pixel.0.iter_mut().zip(target.0) {
+            *value = (*value as f32 + (target as f32 - *value as f32) * weight).round() as u8;
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{Color, MaskLayer};
+
+    fn layer(shape: MaskShape, mode: MaskMode) -> MaskLayer {
+        MaskLayer { shape, mode, invert: false }
+    }
+
+    fn mask(layers: Vec<MaskLayer>) -> Mask {
+        Mask { layers, invert: false }
+    }
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_linear_and_radial_masks() {
+        let source = RgbaImage::new(21, 21);
+
+        let linear = MaskShape::Linear { start: point(0.0, 0.0), end: point(20.0, 0.0) };
+        let coverage = rasterize_mask(&source, &mask(vec![layer(linear, MaskMode::Add)])).unwrap();
+        assert_eq!(coverage.get_pixel(0, 5)[0], 1.0);
+        assert_eq!(coverage.get_pixel(10, 5)[0], 0.5);
+        assert_eq!(coverage.get_pixel(20, 5)[0], 0.0);
+
+        let radial = MaskShape::Radial {
+            center: point(10.0, 10.0),
+            radius_x: 10.0,
+            radius_y: 5.0,
+            angle: 0.0,
+            feather: 0.5,
+        };
+        let coverage = rasterize_mask(&source, &mask(vec![layer(radial, MaskMode::Add)])).unwrap();
+        assert_eq!(coverage.get_pixel(10, 10)[0], 1.0);
+        assert_eq!(coverage.get_pixel(18, 10)[0], coverage.get_pixel(10, 14)[0]);
+        assert_eq!(coverage.get_pixel(10, 16)[0], 0.0);
+    }
+
+    #[test]
+    fn test_luminance_and_colour_masks() {
+        let source = RgbaImage::from_fn(3, 1, |x, _| Rgba([[0, 128, 255][x as usize]; 4]));
+
+        let shadows = MaskShape::Luminance { min: 0.0, max: 0.2, feather: 0.0 };
+        let coverage = rasterize_mask(&source, &mask(vec![layer(shadows, MaskMode::Add)])).unwrap();
+        assert_eq!(coverage.iter().copied().collect::<Vec<_>>(), vec![1.0, 0.0, 0.0]);
+
+        let color = Color { r: 255, g: 255, b: 255, a: 255 };
+        let white = MaskShape::Color { color, tolerance: 0.1, feather: 0.5 };
+        let coverage = rasterize_mask(&source, &mask(vec![layer(white, MaskMode::Add)])).unwrap();
+        assert_eq!(coverage.get_pixel(2, 0)[0], 1.0);
+        assert!(coverage.get_pixel(1, 0)[0] > 0.0 && coverage.get_pixel(1, 0)[0] < 1.0);
+        assert_eq!(coverage.get_pixel(0, 0)[0], 0.0);
+    }
+
+    #[test]
+    fn test_mask_combining_and_invert() {
+        let source = RgbaImage::new(20, 1);
+        let left = MaskShape::Linear { start: point(9.0, 0.0), end: point(10.0, 0.0) };
+        let brush = MaskShape::Brush { points: vec![point(5.0, 0.0)], radius: 2.0, hardness: 1.0 };
+
+        let subtract = mask(vec![layer(left.clone(), MaskMode::Add), layer(brush.clone(), MaskMode::Subtract)]);
+        let coverage = rasterize_mask(&source, &subtract).unwrap();
+        assert_eq!((coverage.get_pixel(2, 0)[0], coverage.get_pixel(5, 0)[0]), (1.0, 0.0));
+
+        let intersect = mask(vec![layer(left.clone(), MaskMode::Add), layer(brush, MaskMode::Intersect)]);
+        let coverage = rasterize_mask(&source, &intersect).unwrap();
+        assert_eq!((coverage.get_pixel(2, 0)[0], coverage.get_pixel(5, 0)[0]), (0.0, 1.0));
+
+        let inverted = Mask { layers: vec![layer(left, MaskMode::Add)], invert: true };
+        let coverage = rasterize_mask(&source, &inverted).unwrap();
+        assert_eq!((coverage.get_pixel(2, 0)[0], coverage.get_pixel(15, 0)[0]), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_apply_masked() {
+        let original = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 1, Rgba([0, 0, 0, 255])));
+        let edited = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 1, Rgba([200, 100, 50, 255])));
+        let gradient = MaskShape::Linear { start: point(0.0, 0.0), end: point(8.0, 0.0) };
+
+        let result = apply_masked(&original, &edited, &mask(vec![layer(gradient.clone(), MaskMode::Add)]))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(*result.get_pixel(0, 0), Rgba([200, 100, 50, 255]));
+        assert_eq!(*result.get_pixel(4, 0), Rgba([100, 50, 25, 255]));
+        assert_eq!(*result.get_pixel(9, 0), Rgba([0, 0, 0, 255]));
+
+        let smaller = DynamicImage::new_rgba8(5, 1);
+        assert!(apply_masked(&original, &smaller, &mask(vec![layer(gradient, MaskMode::Add)])).is_err());
+        assert!(apply_masked(&original, &edited, &mask(vec![])).is_err());
+    }
+}
@@ -2,3 +2,26 @@ pub mod filters;
 pub mod adjustments;
 pub mod transform;
 pub mod crop;
+pub mod resize;
+pub mod canvas;
+pub mod perspective;
+pub mod lens;
+pub mod auto_crop;
+pub mod seam_carving;
+pub mod smart_crop;
+pub mod region;
+pub mod heal;
+pub mod clone_stamp;
+pub mod red_eye;
+pub mod mask;
+pub mod selection;
+pub mod text;
+pub mod watermark;
+pub mod draw;
+pub mod redact;
+pub mod frame;
+pub mod chroma_key;
+pub mod replace_color;
+pub mod alpha;
+pub mod quantize;
+pub mod pixel_art;
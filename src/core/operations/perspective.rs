@@ -0,0 +1,191 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use imageproc::geometric_transformations::{self, Projection};
+use crate::types::errors::AppError;
+use crate::types::operations::{Interpolation, PerspectiveMode, PerspectiveParams, Point};
+use crate::utils::preview::validate_dimensions;
+
+/// Maximum inset of a keystone edge, as a fraction of the image side
+const KEYSTONE_STRENGTH: f32 = 0.25;
+
+/// Map an interpolation to the `imageproc` interpolation type
+pub fn interpolation(interpolation: Interpolation) -> geometric_transformations::Interpolation {
+    match interpolation {
+        Interpolation::Nearest => geometric_transformations::Interpolation::Nearest,
+        Interpolation::Bilinear => geometric_transformations::Interpolation::Bilinear,
+        Interpolation::Bicubic => geometric_transformations::Interpolation::Bicubic,
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+/// Calculate the source quad for keystone sliders
+///
+/// # Returns
+/// Corners in top-left, top-right, bottom-right, bottom-left order
+fn keystone_corners(width: u32, height: u32, vertical: f32, horizontal: f32) -> [Point; 4] {
+    let (w, h) = (width as f32, height as f32);
+
+    // Shrinking an edge of the source quad stretches that edge in the output
+    let top = vertical.max(0.0) * w * KEYSTONE_STRENGTH;
+    let bottom = (-vertical).max(0.0) * w * KEYSTONE_STRENGTH;
+    let left = horizontal.max(0.0) * h * KEYSTONE_STRENGTH;
+    let right = (-horizontal).max(0.0) * h * KEYSTONE_STRENGTH;
+
+    [
+        Point { x: top, y: left },
+        Point { x: w - top, y: right },
+        Point { x: w - bottom, y: h - right },
+        Point { x: bottom, y: h - left },
+    ]
+}
+
+/// Warp a quad of the image onto a rectangle
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `corners`: Source corners (top-left, top-right, bottom-right, bottom-left)
+/// - `width`, `height`: Output dimensions
+/// - `interp`: Interpolation used for sampling
+///
+/// # Returns
+/// RGBA image; output pixels mapped from outside the source are transparent
+pub fn warp_quad(
+    img: &DynamicImage,
+    corners: &[Point; 4],
+    width: u32,
+    height: u32,
+    interp: Interpolation,
+) -> Result<DynamicImage, AppError> {
+    validate_dimensions(width, height).map_err(|details| AppError::InvalidOperation { details })?;
+
+    let (w, h) = (width as f32, height as f32);
+    let from = corners.map(|p| (p.x, p.y));
+    let to = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+
+    let projection = Projection::from_control_points(from, to).ok_or_else(|| AppError::InvalidOperation {
+        details: "Perspective corners must form a non-degenerate quadrilateral".to_string(),
+    })?;
+
+    let mut output = RgbaImage::new(width, height);
+    geometric_transformations::warp_into(
+        &img.to_rgba8(),
+        &projection,
+        interpolation(interp),
+        Rgba([0, 0, 0, 0]),
+        &mut output,
+    );
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+/// Apply a perspective correction to an image
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Corner mapping or keystone sliders, and interpolation
+///
+/// # Returns
+/// Corrected image or error if the parameters are invalid
+pub fn perspective(img: &DynamicImage, params: &PerspectiveParams) -> Result<DynamicImage, AppError> {
+    let (img_width, img_height) = img.dimensions();
+
+    match params.mode {
+        PerspectiveMode::Corners { corners, width, height } => {
+            let [tl, tr, br, bl] = corners;
+            let width = width.unwrap_or_else(|| ((distance(tl, tr) + distance(bl, br)) / 2.0).round() as u32);
+            let height = height.unwrap_or_else(|| ((distance(tl, bl) + distance(tr, br)) / 2.0).round() as u32);
+
+            warp_quad(img, &corners, width, height, params.interpolation)
+        }
+        PerspectiveMode::Keystone { vertical, horizontal } => {
+            if !(-1.0..=1.0).contains(&vertical) || !(-1.0..=1.0).contains(&horizontal) {
+                return Err(AppError::InvalidOperation {
+                    details: format!(
+                        "Keystone values must be between -1.0 and 1.0, got vertical {} horizontal {}",
+                        vertical, horizontal
+                    ),
+                });
+            }
+
+            let corners = keystone_corners(img_width, img_height, vertical, horizontal);
+            warp_quad(img, &corners, img_width, img_height, params.interpolation)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_perspective_extracts_quad() {
+        // Red square in the middle of a black image
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 40, |x, y| {
+            if (10..30).contains(&x) && (10..30).contains(&y) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 255])
+            }
+        }));
+        let params = PerspectiveParams {
+            mode: PerspectiveMode::Corners {
+                corners: [point(10.0, 10.0), point(30.0, 10.0), point(30.0, 30.0), point(10.0, 30.0)],
+                width: None,
+                height: None,
+            },
+            interpolation: Interpolation::Nearest,
+        };
+
+        let result = perspective(&img, &params).unwrap().to_rgba8();
+        assert_eq!(result.dimensions(), (20, 20));
+        assert!(result.pixels().all(|p| *p == Rgba([255, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_keystone_keeps_dimensions() {
+        let img = DynamicImage::new_rgb8(40, 30);
+        let params = PerspectiveParams {
+            mode: PerspectiveMode::Keystone { vertical: 0.5, horizontal: -0.2 },
+            interpolation: Interpolation::Bicubic,
+        };
+
+        let result = perspective(&img, &params).unwrap();
+        assert_eq!(result.dimensions(), (40, 30));
+    }
+
+    #[test]
+    fn test_keystone_corners() {
+        let corners = keystone_corners(100, 100, 1.0, 0.0);
+        assert_eq!(corners[0], point(25.0, 0.0));
+        assert_eq!(corners[1], point(75.0, 0.0));
+        assert_eq!(corners[2], point(100.0, 100.0));
+        assert_eq!(corners[3], point(0.0, 100.0));
+    }
+
+    #[test]
+    fn test_perspective_validation() {
+        let img = DynamicImage::new_rgb8(10, 10);
+
+        let keystone = PerspectiveParams {
+            mode: PerspectiveMode::Keystone { vertical: 1.5, horizontal: 0.0 },
+            interpolation: Interpolation::Bilinear,
+        };
+        assert!(perspective(&img, &keystone).is_err());
+
+        let degenerate = PerspectiveParams {
+            mode: PerspectiveMode::Corners {
+                corners: [point(0.0, 0.0); 4],
+                width: Some(10),
+                height: Some(10),
+            },
+            interpolation: Interpolation::Bilinear,
+        };
+        assert!(perspective(&img, &degenerate).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use crate::types::errors::AppError;
+use crate::types::operations::PixelArtScaler;
+use crate::utils::preview::validate_dimensions;
+
+/// Pixel at an offset from `(x, y)`, repeating the edge outside the image
+fn at(source: &RgbaImage, x: u32, y: u32, dx: i64, dy: i64) -> Rgba<u8> {
+    let x = (x as i64 + dx).clamp(0, source.width() as i64 - 1) as u32;
+    let y = (y as i64 + dy).clamp(0, source.height() as i64 - 1) as u32;
+    *source.get_pixel(x, y)
+}
+
+/// Write a `factor` x `factor` block of output pixels for source pixel `(x, y)`
+fn put_block(output: &mut RgbaImage, x: u32, y: u32, factor: u32, block: &[Rgba<u8>]) {
+    for (i, pixel) in block.iter().enumerate() {
+        let (bx, by) = (i as u32 % factor, i as u32 / factor);
+        output.put_pixel(x * factor + bx, y * factor + by, *pixel);
+    }
+}
+
+fn nearest(source: &RgbaImage, factor: u32) -> RgbaImage {
+    RgbaImage::from_fn(source.width() * factor, source.height() * factor, |x, y| {
+        *source.get_pixel(x / factor, y / factor)
+    })
+}
+
+/// EPX / AdvMAME2x
+///
+/// Each output corner takes the colour of its two neighbours when they agree
+/// and the opposite neighbours do not.
+fn scale2x(source: &RgbaImage) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    let mut output = RgbaImage::new(width * 2, height * 2);
+
+    for y in 0..height {
+        for x in 0..width {
+            let n = |dx, dy| at(source, x, y, dx, dy);
+            let (p, a, b, c, d) = (n(0, 0), n(0, -1), n(1, 0), n(-1, 0), n(0, 1));
+
+            let block = [
+                if c == a && c != d && a != b { a } else { p },
+                if a == b && a != c && b != d { b } else { p },
+                if d == c && d != b && c != a { c } else { p },
+                if b == d && b != a && d != c { d } else { p },
+            ];
+            put_block(&mut output, x, y, 2, &block);
+        }
+    }
+
+    output
+}
+
+/// AdvMAME3x
+fn scale3x(source: &RgbaImage) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    let mut output = RgbaImage::new(width * 3, height * 3);
+
+    for y in 0..height {
+        for x in 0..width {
+            let n = |dx, dy| at(source, x, y, dx, dy);
+            let (a, b, c) = (n(-1, -1), n(0, -1), n(1, -1));
+            let (d, e, f) = (n(-1, 0), n(0, 0), n(1, 0));
+            let (g, h, i) = (n(-1, 1), n(0, 1), n(1, 1));
+
+            let block = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) { b } else { e },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) { d } else { e },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) { f } else { e },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) { h } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 9]
+            };
+            put_block(&mut output, x, y, 3, &block);
+        }
+    }
+
+    output
+}
+
+/// Perceptual distance between two pixels, weighted towards luma as in xBR
+fn distance(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let yuv = |p: Rgba<u8>| {
+        let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+        [
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.169 * r - 0.331 * g + 0.5 * b,
+            0.5 * r - 0.419 * g - 0.081 * b,
+        ]
+    };
+    let (ya, yb) = (yuv(a), yuv(b));
+    48.0 * (ya[0] - yb[0]).abs()
+        + 7.0 * (ya[1] - yb[1]).abs()
+        + 6.0 * (ya[2] - yb[2]).abs()
+        + 48.0 * (a[3] as f32 - b[3] as f32).abs()
+}
+
+/// Average of two pixels, weighting colour by alpha so transparent pixels add no colour
+fn mix(a: Rgba<u8>, b: Rgba<u8>) -> Rgba<u8> {
+    let (wa, wb) = (a[3] as f32, b[3] as f32);
+    let alpha = ((wa + wb) / 2.0).round() as u8;
+    if wa + wb == 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |c: usize| ((a[c] as f32 * wa + b[c] as f32 * wb) / (wa + wb)).round() as u8;
+    Rgba([channel(0), channel(1), channel(2), alpha])
+}
+
+/// 2xBR
+///
+/// Each output corner compares the colour variation along both diagonals of
+/// the 5x5 neighbourhood. When an edge runs across the corner, the corner is
+/// blended with the closer of the two neighbours on the far side of it.
+fn xbr(source: &RgbaImage) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    let mut output = RgbaImage::new(width * 2, height * 2);
+
+    for y in 0..height {
+        for x in 0..width {
+            let e = *source.get_pixel(x, y);
+            let mut block = [e; 4];
+
+            // Every corner is the bottom-right one of a mirrored neighbourhood
+            for (corner, (sx, sy)) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].into_iter().enumerate() {
+                let n = |dx: i64, dy: i64| at(source, x, y, dx * sx, dy * sy);
+                let (f, h, i) = (n(1, 0), n(0, 1), n(1, 1));
+                if e == f || e == h {
+                    continue;
+                }
+
+                let (b, c, d, g) = (n(0, -1), n(1, -1), n(-1, 0), n(-1, 1));
+                let (f4, h5, i4, i5) = (n(2, 0), n(0, 2), n(2, 1), n(1, 2));
+                let across = distance(e, c) + distance(e, g) + distance(i, f4) + distance(i, h5)
+                    + 4.0 * distance(h, f);
+                let along = distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b)
+                    + 4.0 * distance(e, i);
+
+                if across < along {
+                    let neighbour = if distance(e, f) <= distance(e, h) { f } else { h };
+                    block[corner] = mix(e, neighbour);
+                }
+            }
+
+            put_block(&mut output, x, y, 2, &block);
+        }
+    }
+
+    output
+}
+
+/// Upscale pixel art by an integer factor without blurring it
+pub fn upscale(img: &DynamicImage, scaler: PixelArtScaler) -> Result<DynamicImage, AppError> {
+    let factor = match scaler {
+        PixelArtScaler::Nearest { factor } => factor,
+        PixelArtScaler::Scale2x | PixelArtScaler::Xbr => 2,
+        PixelArtScaler::Scale3x => 3,
+    };
+    if !(1..=16).contains(&factor) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Upscale factor must be between 1 and 16, got {}", factor),
+        });
+    }
+
+    let source = img.to_rgba8();
+    let (width, height) = source.dimensions();
+    validate_dimensions(width.saturating_mul(factor), height.saturating_mul(factor))
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    let output = match scaler {
+        PixelArtScaler::Nearest { factor } => nearest(&source, factor),
+        PixelArtScaler::Scale2x => scale2x(&source),
+        PixelArtScaler::Scale3x => scale3x(&source),
+        PixelArtScaler::Xbr => xbr(&source),
+    };
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INK: Rgba<u8> = Rgba([20, 20, 40, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// Build a sprite from rows of `#` (ink) and `.` (transparent)
+    fn sprite(rows: &[&str]) -> DynamicImage {
+        let (width, height) = (rows[0].len() as u32, rows.len() as u32);
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            if rows[y as usize].as_bytes()[x as usize] == b'#' { INK } else { CLEAR }
+        }))
+    }
+
+    /// Render back to rows, marking colours that are neither ink nor clear with `+`
+    fn rows(img: &DynamicImage) -> Vec<String> {
+        let rgba = img.to_rgba8();
+        rgba.rows()
+            .map(|row| {
+                row.map(|&pixel| match pixel {
+                    INK => '#',
+                    CLEAR => '.',
+                    _ => '+',
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nearest_repeats_pixels() {
+        let checker = sprite(&["#.", ".#"]);
+        let result = upscale(&checker, PixelArtScaler::Nearest { factor: 3 }).unwrap();
+        assert_eq!(rows(&result), vec!["###...", "###...", "###...", "...###", "...###", "...###"]);
+    }
+
+    #[test]
+    fn test_scale2x_smooths_diagonals() {
+        // The gap between diagonal neighbours is filled in, turning the line into a slope
+        let diagonal = sprite(&["....", ".#..", "..#.", "...."]);
+        let result = upscale(&diagonal, PixelArtScaler::Scale2x).unwrap();
+        assert_eq!(
+            rows(&result),
+            vec![
+                "........",
+                "........",
+                "..##....",
+                "..###...",
+                "...###..",
+                "....##..",
+                "........",
+                "........",
+            ]
+        );
+
+        // A lone pixel has no neighbours to agree with and stays square
+        let dot = upscale(&sprite(&["...", ".#.", "..."]), PixelArtScaler::Scale2x).unwrap();
+        assert_eq!(rows(&dot), vec!["......", "......", "..##..", "..##..", "......", "......"]);
+    }
+
+    #[test]
+    fn test_scale3x_smooths_diagonals() {
+        let diagonal = sprite(&["....", ".#..", "..#.", "...."]);
+        let result = upscale(&diagonal, PixelArtScaler::Scale3x).unwrap();
+        assert_eq!(
+            rows(&result),
+            vec![
+                "............",
+                "............",
+                "............",
+                "...###......",
+                "...###......",
+                "...####.....",
+                ".....####...",
+                "......###...",
+                "......###...",
+                "............",
+                "............",
+                "............",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xbr_blends_only_across_diagonal_edges() {
+        // Straight edges stay hard
+        let bar = sprite(&["####", "####", "....", "...."]);
+        let result = upscale(&bar, PixelArtScaler::Xbr).unwrap();
+        assert!(rows(&result).iter().all(|row| !row.contains('+')));
+        assert_eq!(result.to_rgba8().dimensions(), (8, 8));
+
+        // A staircase gets blended corners along its slope
+        let stairs = sprite(&["#...", "##..", "###.", "####"]);
+        let result = upscale(&stairs, PixelArtScaler::Xbr).unwrap().to_rgba8();
+        let blended = result.pixels().filter(|&&pixel| pixel != INK && pixel != CLEAR).count();
+        assert!(blended > 0);
+        // Blends keep the ink colour and only soften coverage
+        let edge = result.get_pixel(3, 2);
+        assert_eq!((edge[0], edge[1], edge[2], edge[3]), (20, 20, 40, 128));
+    }
+
+    #[test]
+    fn test_rejects_bad_factors() {
+        let img = sprite(&["#"]);
+        assert!(upscale(&img, PixelArtScaler::Nearest { factor: 0 }).is_err());
+        assert!(upscale(&img, PixelArtScaler::Nearest { factor: 17 }).is_err());
+
+        let wide = DynamicImage::ImageRgba8(RgbaImage::new(10000, 1));
+        assert!(upscale(&wide, PixelArtScaler::Scale2x).is_err());
+    }
+}
@@ -0,0 +1,487 @@
+use std::path::Path;
+use image::{DynamicImage, Rgba, RgbaImage};
+use rayon::prelude::*;
+use crate::types::errors::AppError;
+use crate::types::operations::{DitherMode, PaletteSource, QuantizeParams};
+use crate::utils::preview::validate_file_size;
+
+/// Pixels with less alpha than this become the transparent index of indexed output
+const ALPHA_CUTOFF: u8 = 128;
+
+/// Most pixels sampled when building a palette
+const MAX_SAMPLES: usize = 1 << 18;
+
+const KMEANS_ITERATIONS: usize = 8;
+
+/// Image reduced to indices into a palette of at most 256 colours
+#[derive(Debug, Clone)]
+pub struct IndexedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Palette entries, including the transparent entry when there is one
+    pub palette: Vec<[u8; 3]>,
+    /// One palette index per pixel, row by row
+    pub indices: Vec<u8>,
+    /// Index that marks transparent pixels
+    pub transparent: Option<u8>,
+}
+
+/// Parse a GIMP palette
+fn parse_gpl(text: &str) -> Result<Vec<[u8; 3]>, AppError> {
+    let invalid = |line: usize, reason: &str| AppError::InvalidOperation {
+        details: format!("Invalid palette file at line {}: {}", line + 1, reason),
+    };
+
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim_start_matches('\u{feff}').trim() == "GIMP Palette" => {}
+        _ => return Err(invalid(0, "expected a \"GIMP Palette\" header")),
+    }
+
+    let mut colors = Vec::new();
+    for (number, line) in lines {
+        let line = line.trim();
+        let skipped = ["#", "Name:", "Columns:"].iter().any(|prefix| line.starts_with(prefix));
+        if line.is_empty() || skipped {
+            continue;
+        }
+
+        // Channels may be followed by a colour name
+        let mut channels = line.split_whitespace().map(|value| value.parse::<u8>());
+        match (channels.next(), channels.next(), channels.next()) {
+            (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => colors.push([r, g, b]),
+            _ => return Err(invalid(number, "expected red, green and blue values from 0 to 255")),
+        }
+    }
+
+    if colors.is_empty() || colors.len() > 256 {
+        return Err(AppError::InvalidOperation {
+            details: format!("Palette must hold between 1 and 256 colours, got {}", colors.len()),
+        });
+    }
+
+    Ok(colors)
+}
+
+/// Load a GIMP `.gpl` palette file
+pub fn load_gpl(path: &str) -> Result<Vec<[u8; 3]>, AppError> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+    if !extension.is_some_and(|ext| ext.eq_ignore_ascii_case("gpl")) {
+        return Err(AppError::UnsupportedFormat {
+            format: format!("{}. Palettes must be GIMP .gpl files", path),
+        });
+    }
+
+    validate_file_size(path)?;
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| AppError::ImageLoadError(format!("{}: {}", path, e)))?;
+    parse_gpl(&text)
+}
+
+/// Replace a palette file with the colours it holds
+///
+/// History records quantisation this way, so that replaying it neither reads
+/// the file again nor depends on it being unchanged.
+pub fn resolve(params: &QuantizeParams) -> Result<QuantizeParams, AppError> {
+    match &params.palette {
+        PaletteSource::File { path } => {
+            let palette = PaletteSource::Colors { colors: load_gpl(path)? };
+            Ok(QuantizeParams { palette, dither: params.dither })
+        }
+        _ => Ok(params.clone()),
+    }
+}
+
+/// Opaque colours of the image, thinned out evenly on large images
+fn samples(rgba: &RgbaImage) -> Vec<[u8; 3]> {
+    let opaque = rgba.pixels().filter(|pixel| pixel[3] >= ALPHA_CUTOFF).count();
+    let stride = opaque.div_ceil(MAX_SAMPLES).max(1);
+    rgba.pixels()
+        .filter(|pixel| pixel[3] >= ALPHA_CUTOFF)
+        .step_by(stride)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect()
+}
+
+fn mean(colors: &[[u8; 3]]) -> [u8; 3] {
+    let count = colors.len() as u64;
+    let mut sum = [0u64; 3];
+    for color in colors {
+        for (total, value) in sum.iter_mut().zip(color) {
+            *total += *value as u64;
+        }
+    }
+    sum.map(|total| ((total + count / 2) / count) as u8)
+}
+
+/// Channel with the widest spread of values, and that spread
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = colors.iter().map(|color| color[c]).min().unwrap_or(0);
+            let max = colors.iter().map(|color| color[c]).max().unwrap_or(0);
+            (c, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+/// Median cut palette of at most `colors` entries
+///
+/// Boxes are only split between differing values, so an image with fewer
+/// distinct colours than requested gets exactly those colours back.
+fn median_cut(samples: Vec<[u8; 3]>, colors: usize) -> Vec<[u8; 3]> {
+    let mut boxes = vec![samples];
+    while boxes.len() < colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, colors)| (index, widest_channel(colors)))
+            .filter(|&(_, (_, range))| range > 0)
+            .max_by_key(|&(_, (_, range))| range);
+        let Some((index, (channel, _))) = widest else {
+            break;
+        };
+
+        let mut lower = boxes.swap_remove(index);
+        lower.sort_unstable_by_key(|color| color[channel]);
+        let median = lower[lower.len() / 2][channel];
+        let mut split = lower.partition_point(|color| color[channel] < median);
+        if split == 0 {
+            split = lower.partition_point(|color| color[channel] <= median);
+        }
+        let upper = lower.split_off(split);
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().filter(|colors| !colors.is_empty()).map(|colors| mean(colors)).collect()
+}
+
+fn nearest(palette: &[[u8; 3]], color: [f32; 3]) -> usize {
+    let distance = |entry: &[u8; 3]| {
+        (0..3).map(|c| (entry[c] as f32 - color[c]).powi(2)).sum::<f32>()
+    };
+    (0..palette.len())
+        .min_by(|&a, &b| distance(&palette[a]).total_cmp(&distance(&palette[b])))
+        .unwrap_or(0)
+}
+
+/// Move palette entries to the mean of the samples nearest to them
+fn kmeans(samples: &[[u8; 3]], mut palette: Vec<[u8; 3]>) -> Vec<[u8; 3]> {
+    for _ in 0..KMEANS_ITERATIONS {
+        let sums = samples
+            .par_iter()
+            .fold(
+                || vec![[0u64; 4]; palette.len()],
+                |mut sums, sample| {
+                    let index = nearest(&palette, sample.map(|v| v as f32));
+                    for c in 0..3 {
+                        sums[index][c] += sample[c] as u64;
+                    }
+                    sums[index][3] += 1;
+                    sums
+                },
+            )
+            .reduce(
+                || vec![[0u64; 4]; palette.len()],
+                |mut a, b| {
+                    for (total, part) in a.iter_mut().zip(b) {
+                        for c in 0..4 {
+                            total[c] += part[c];
+                        }
+                    }
+                    a
+                },
+            );
+
+        let mut moved = false;
+        for (entry, sum) in palette.iter_mut().zip(sums) {
+            let count = sum[3];
+            if count == 0 {
+                continue;
+            }
+            let centre = [0, 1, 2].map(|c| ((sum[c] + count / 2) / count) as u8);
+            moved |= centre != *entry;
+            *entry = centre;
+        }
+        if !moved {
+            break;
+        }
+    }
+
+    palette
+}
+
+/// Check that a fixed palette fits in `limit` colours
+fn fixed_palette(palette: Vec<[u8; 3]>, limit: usize) -> Result<Vec<[u8; 3]>, AppError> {
+    if palette.is_empty() || palette.len() > 256 {
+        return Err(AppError::InvalidOperation {
+            details: format!("Palette must hold between 1 and 256 colours, got {}", palette.len()),
+        });
+    }
+    if palette.len() > limit {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "Palette has {} colours but only {} fit alongside transparency",
+                palette.len(),
+                limit
+            ),
+        });
+    }
+
+    Ok(palette)
+}
+
+/// Build a palette of at most `limit` colours for an image
+fn build_palette(
+    rgba: &RgbaImage,
+    source: &PaletteSource,
+    limit: usize,
+) -> Result<Vec<[u8; 3]>, AppError> {
+    let palette = match source {
+        PaletteSource::MedianCut { colors } | PaletteSource::KMeans { colors } => {
+            if !(2..=256).contains(colors) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Palette size must be between 2 and 256, got {}", colors),
+                });
+            }
+
+            let samples = samples(rgba);
+            let palette = median_cut(samples.clone(), (*colors as usize).min(limit));
+            match source {
+                PaletteSource::KMeans { .. } if !samples.is_empty() => kmeans(&samples, palette),
+                _ => palette,
+            }
+        }
+        PaletteSource::File { path } => fixed_palette(load_gpl(path)?, limit)?,
+        PaletteSource::Colors { colors } => fixed_palette(colors.clone(), limit)?,
+    };
+
+    // A fully transparent image still needs one colour
+    Ok(if palette.is_empty() { vec![[0, 0, 0]] } else { palette })
+}
+
+/// Rank of a cell in a `size` x `size` Bayer matrix
+fn bayer_rank(x: u32, y: u32, size: u32) -> u32 {
+    if size == 1 {
+        return 0;
+    }
+    let half = size / 2;
+    let offset = [[0, 2], [3, 1]][(y / half) as usize][(x / half) as usize];
+    4 * bayer_rank(x % half, y % half, half) + offset
+}
+
+/// Map every pixel to a palette index, dithering as requested
+fn map_indices(rgba: &RgbaImage, palette: &[[u8; 3]], dither: DitherMode) -> Vec<u8> {
+    let (width, height) = rgba.dimensions();
+    let color = |pixel: &Rgba<u8>| [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+
+    match dither {
+        DitherMode::None => rgba
+            .par_pixels()
+            .map(|pixel| nearest(palette, color(pixel)) as u8)
+            .collect(),
+        DitherMode::Bayer4 | DitherMode::Bayer8 => {
+            let size = if dither == DitherMode::Bayer4 { 4 } else { 8 };
+            // Offsets span roughly one palette step per channel
+            let spread = 255.0 / (palette.len() as f32).cbrt();
+            rgba.par_enumerate_pixels()
+                .map(|(x, y, pixel)| {
+                    let rank = bayer_rank(x % size, y % size, size) as f32;
+                    let offset = ((rank + 0.5) / (size * size) as f32 - 0.5) * spread;
+                    nearest(palette, color(pixel).map(|value| value + offset)) as u8
+                })
+                .collect()
+        }
+        DitherMode::FloydSteinberg => {
+            let mut indices = Vec::with_capacity((width * height) as usize);
+            // Error carried into the current and next rows, padded by a pixel each side
+            let mut current = vec![[0.0f32; 3]; width as usize + 2];
+            let mut next = vec![[0.0f32; 3]; width as usize + 2];
+
+            for y in 0..height {
+                for x in 0..width {
+                    let carried = current[x as usize + 1];
+                    let wanted: [f32; 3] = std::array::from_fn(|c| {
+                        (color(rgba.get_pixel(x, y))[c] + carried[c]).clamp(0.0, 255.0)
+                    });
+                    let index = nearest(palette, wanted);
+                    indices.push(index as u8);
+
+                    let error: [f32; 3] =
+                        std::array::from_fn(|c| wanted[c] - palette[index][c] as f32);
+                    let x = x as usize + 1;
+                    for c in 0..3 {
+                        current[x + 1][c] += error[c] * 7.0 / 16.0;
+                        next[x - 1][c] += error[c] * 3.0 / 16.0;
+                        next[x][c] += error[c] * 5.0 / 16.0;
+                        next[x + 1][c] += error[c] / 16.0;
+                    }
+                }
+                std::mem::swap(&mut current, &mut next);
+                next.fill([0.0; 3]);
+            }
+
+            indices
+        }
+    }
+}
+
+/// Reduce an image to palette indices for indexed formats
+///
+/// Pixels below half opacity share one extra transparent index, so the
+/// palette itself is limited to 255 colours when there are any.
+pub fn index(img: &DynamicImage, params: &QuantizeParams) -> Result<IndexedImage, AppError> {
+    let rgba = img.to_rgba8();
+    let transparent = rgba.pixels().any(|pixel| pixel[3] < ALPHA_CUTOFF);
+    let limit = if transparent { 255 } else { 256 };
+
+    let mut palette = build_palette(&rgba, &params.palette, limit)?;
+    let mut indices = map_indices(&rgba, &palette, params.dither);
+
+    let transparent = transparent.then(|| {
+        let index = palette.len() as u8;
+        palette.push([0, 0, 0]);
+        for (slot, pixel) in indices.iter_mut().zip(rgba.pixels()) {
+            if pixel[3] < ALPHA_CUTOFF {
+                *slot = index;
+            }
+        }
+        index
+    });
+
+    Ok(IndexedImage { width: rgba.width(), height: rgba.height(), palette, indices, transparent })
+}
+
+/// Reduce an image to a palette, keeping its alpha channel
+pub fn quantize(img: &DynamicImage, params: &QuantizeParams) -> Result<DynamicImage, AppError> {
+    let mut output = img.to_rgba8();
+    let palette = build_palette(&output, &params.palette, 256)?;
+    let indices = map_indices(&output, &palette, params.dither);
+
+    for (pixel, index) in output.pixels_mut().zip(indices) {
+        let [r, g, b] = palette[index as usize];
+        *pixel = Rgba([r, g, b, pixel[3]]);
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 16, |x, y| {
+            Rgba([(x * 4) as u8, (y * 16) as u8, 128, 255])
+        }))
+    }
+
+    fn distinct(img: &DynamicImage) -> usize {
+        img.to_rgba8().pixels().map(|pixel| pixel.0).collect::<HashSet<_>>().len()
+    }
+
+    fn params(palette: PaletteSource, dither: DitherMode) -> QuantizeParams {
+        QuantizeParams { palette, dither }
+    }
+
+    #[test]
+    fn test_median_cut_and_kmeans_limit_colours() {
+        for palette in [PaletteSource::MedianCut { colors: 8 }, PaletteSource::KMeans { colors: 8 }]
+        {
+            let result = quantize(&gradient(), &params(palette, DitherMode::None)).unwrap();
+            assert_eq!(distinct(&result), 8);
+        }
+
+        // Fewer colours than requested come back unchanged
+        let two = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, _| {
+            if x < 2 { Rgba([10, 20, 30, 255]) } else { Rgba([200, 100, 0, 255]) }
+        }));
+        let median_cut = PaletteSource::MedianCut { colors: 16 };
+        assert_eq!(quantize(&two, &params(median_cut, DitherMode::None)).unwrap(), two);
+    }
+
+    #[test]
+    fn test_dithering_mixes_palette_colours() {
+        // Mid grey between black and white dithers into a mix of both
+        let grey = RgbaImage::from_pixel(16, 16, Rgba([128, 128, 128, 255]));
+        let grey = DynamicImage::ImageRgba8(grey);
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mono.gpl");
+        let gpl = "GIMP Palette\nName: Mono\n#\n  0   0   0 Black\n255 255 255 White\n";
+        std::fs::write(&path, gpl).unwrap();
+        let file = || PaletteSource::File { path: path.to_string_lossy().into_owned() };
+
+        let flat = quantize(&grey, &params(file(), DitherMode::None)).unwrap();
+        assert_eq!(distinct(&flat), 1);
+        for dither in [DitherMode::FloydSteinberg, DitherMode::Bayer4, DitherMode::Bayer8] {
+            let result = quantize(&grey, &params(file(), dither)).unwrap().to_rgba8();
+            let white = result.pixels().filter(|pixel| pixel[0] == 255).count();
+            assert!((96..=160).contains(&white), "{:?} gave {} white pixels", dither, white);
+        }
+    }
+
+    #[test]
+    fn test_resolve_records_file_colours() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mono.gpl");
+        std::fs::write(&path, "GIMP Palette\n0 0 0\n255 255 255\n").unwrap();
+        let file = PaletteSource::File { path: path.to_string_lossy().into_owned() };
+
+        let resolved = resolve(&params(file, DitherMode::Bayer4)).unwrap();
+        let colors = vec![[0, 0, 0], [255, 255, 255]];
+        assert_eq!(resolved, params(PaletteSource::Colors { colors }, DitherMode::Bayer4));
+
+        // The recorded palette no longer depends on the file
+        drop(dir);
+        assert_eq!(distinct(&quantize(&gradient(), &resolved).unwrap()), 2);
+        let empty = PaletteSource::Colors { colors: vec![] };
+        assert!(quantize(&gradient(), &params(empty, DitherMode::None)).is_err());
+    }
+
+    #[test]
+    fn test_bayer_matrix_ranks() {
+        let row: Vec<u32> = (0..4).map(|x| bayer_rank(x, 0, 4)).collect();
+        assert_eq!(row, vec![0, 8, 2, 10]);
+        let ranks: HashSet<u32> = (0..64).map(|i| bayer_rank(i % 8, i / 8, 8)).collect();
+        assert_eq!(ranks.len(), 64);
+    }
+
+    #[test]
+    fn test_parse_gpl() {
+        let palette = parse_gpl("GIMP Palette\nColumns: 2\n255 0 0\t Red\n0 0 255\n").unwrap();
+        assert_eq!(palette, vec![[255, 0, 0], [0, 0, 255]]);
+
+        assert!(parse_gpl("255 0 0\n").is_err());
+        assert!(parse_gpl("GIMP Palette\n255 0\n").is_err());
+        assert!(parse_gpl("GIMP Palette\n300 0 0\n").is_err());
+        assert!(parse_gpl("GIMP Palette\n").is_err());
+    }
+
+    #[test]
+    fn test_index_reserves_transparent_entry() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, y| {
+            if x < 4 { Rgba([0, 0, 0, 0]) } else { Rgba([(x * 16) as u8, (y * 16) as u8, 0, 255]) }
+        }));
+        let indexed = index(&img, &QuantizeParams::default()).unwrap();
+
+        assert!(indexed.palette.len() <= 256);
+        let transparent = indexed.transparent.unwrap();
+        assert_eq!(transparent as usize, indexed.palette.len() - 1);
+        assert_eq!(indexed.indices[0], transparent);
+        assert!(indexed.indices[4..16].iter().all(|&index| index != transparent));
+    }
+
+    #[test]
+    fn test_rejects_invalid_palettes() {
+        let sizes = [PaletteSource::MedianCut { colors: 1 }, PaletteSource::KMeans { colors: 300 }];
+        for palette in sizes {
+            assert!(quantize(&gradient(), &params(palette, DitherMode::None)).is_err());
+        }
+        let not_gpl = PaletteSource::File { path: "palette.txt".to_string() };
+        assert!(quantize(&gradient(), &params(not_gpl, DitherMode::None)).is_err());
+    }
+}
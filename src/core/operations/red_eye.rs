@@ -0,0 +1,230 @@
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use imageproc::region_labelling::{Connectivity, connected_components};
+use crate::core::operations::region::brush_falloff;
+use crate::types::errors::AppError;
+use crate::types::operations::{Circle, Point, RedEyeMode, RedEyeParams};
+
+/// Fraction of the circle radius corrected at full strength
+const CORE: f32 = 0.7;
+
+/// Margin added around detected blobs so the soft edge covers the whole pupil
+const DETECTION_MARGIN: f32 = 1.3;
+
+/// Largest correction radius accepted
+const MAX_RADIUS: f32 = 1000.0;
+
+/// How strongly red dominates a pixel, from 0.0 (neutral) to 1.0 (pure red)
+fn redness(pixel: &Rgba<u8>) -> f32 {
+    let [r, g, b, _] = pixel.0.map(|c| c as f32);
+    if r <= g.max(b) {
+        return 0.0;
+    }
+    (r - (g + b) / 2.0) / r
+}
+
+fn is_red_eye(pixel: &Rgba<u8>) -> bool {
+    pixel[0] >= 80 && redness(pixel) >= 0.4
+}
+
+/// Find round, strongly red blobs that look like red pupils
+///
+/// Candidates are connected components of red pixels whose bounding box is
+/// roughly square and about as full as a disc. Blobs wider than a quarter
+/// of the short image edge, or too wide for a circle of `MAX_RADIUS`, are
+/// ignored.
+///
+/// # Returns
+/// Circles covering each detected pupil, in reading order
+pub fn detect_red_eyes(img: &DynamicImage) -> Vec<Circle> {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mask = GrayImage::from_fn(width, height, |x, y| {
+        Luma([if is_red_eye(rgba.get_pixel(x, y)) { 255 } else { 0 }])
+    });
+    let labels = connected_components(&mask, Connectivity::Eight, Luma([0u8]));
+
+    // Per label: min x, min y, max x, max y, pixel count
+    let count = labels.pixels().map(|p| p[0]).max().unwrap_or(0) as usize;
+    let mut blobs = vec![(u32::MAX, u32::MAX, 0u32, 0u32, 0u32); count + 1];
+    for (x, y, label) in labels.enumerate_pixels() {
+        let blob = &mut blobs[label[0] as usize];
+        *blob = (blob.0.min(x), blob.1.min(y), blob.2.max(x), blob.3.max(y), blob.4 + 1);
+    }
+
+    let max_diameter = max_blob_diameter(width, height);
+    blobs
+        .into_iter()
+        .skip(1)
+        .filter(|&(_, _, _, _, pixels)| pixels > 0)
+        .filter_map(|(x0, y0, x1, y1, pixels)| {
+            let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+            let fill = pixels as f32 / (w * h) as f32;
+            let round = w.max(h) <= 2 * w.min(h) && (0.5..=0.9).contains(&fill);
+            let sized = w.min(h) >= 3 && w.max(h) <= max_diameter;
+
+            (round && sized).then(|| Circle {
+                center: Point { x: (x0 + x1) as f32 / 2.0, y: (y0 + y1) as f32 / 2.0 },
+                radius: w.max(h) as f32 / 2.0 * DETECTION_MARGIN,
+            })
+        })
+        .collect()
+}
+
+/// Widest blob detected, keeping its margin-grown circle within `MAX_RADIUS`
+fn max_blob_diameter(width: u32, height: u32) -> u32 {
+    let largest = (MAX_RADIUS * 2.0 / DETECTION_MARGIN) as u32;
+    (width.min(height) / 4).clamp(1, largest)
+}
+
+fn validate_circle(circle: &Circle) -> Result<(), AppError> {
+    if !circle.center.x.is_finite() || !circle.center.y.is_finite() {
+        return Err(AppError::InvalidOperation {
+            details: "Red-eye centre must be finite".to_string(),
+        });
+    }
+    if !(circle.radius > 0.0 && circle.radius <= MAX_RADIUS) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "Red-eye radius must be between 0 and {}, got {}",
+                MAX_RADIUS, circle.radius
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Neutralise red pixels inside a circle, fading out towards its edge
+fn correct_circle(output: &mut RgbaImage, circle: &Circle) {
+    let (width, height) = (output.width() as i64, output.height() as i64);
+    let Circle { center, radius } = *circle;
+    let x0 = ((center.x - radius).floor() as i64).max(0);
+    let y0 = ((center.y - radius).floor() as i64).max(0);
+    let x1 = ((center.x + radius).ceil() as i64 + 1).min(width);
+    let y1 = ((center.y + radius).ceil() as i64 + 1).min(height);
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let distance = ((x as f32 - center.x).powi(2) + (y as f32 - center.y).powi(2)).sqrt();
+            let edge = brush_falloff(distance, radius, CORE);
+            let pixel = output.get_pixel_mut(x as u32, y as u32);
+            // Ramp in between mildly and strongly red so skin at the rim is spared
+            let amount = edge * ((redness(pixel) - 0.2) / 0.3).clamp(0.0, 1.0);
+            if amount <= 0.0 {
+                continue;
+            }
+
+            let [r, g, b, _] = pixel.0.map(|c| c as f32);
+            let target = (g + b) / 2.0;
+            pixel[0] = (r + (target - r) * amount).round() as u8;
+        }
+    }
+}
+
+/// Circles to correct, detecting them in auto mode
+fn eyes(img: &DynamicImage, params: &RedEyeParams) -> Vec<Circle> {
+    match &params.mode {
+        RedEyeMode::Manual { eyes } => eyes.clone(),
+        RedEyeMode::Auto => detect_red_eyes(img),
+    }
+}
+
+/// Replace auto mode with the circles it detects
+pub fn resolve(img: &DynamicImage, params: &RedEyeParams) -> RedEyeParams {
+    RedEyeParams {
+        mode: RedEyeMode::Manual { eyes: eyes(img, params) },
+    }
+}
+
+/// Remove red-eye from circular regions
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Circles to correct, or auto mode to detect them
+///
+/// # Returns
+/// RGBA image with red pupils desaturated, or error if a circle is invalid
+pub fn red_eye(img: &DynamicImage, params: &RedEyeParams) -> Result<DynamicImage, AppError> {
+    let eyes = eyes(img, params);
+    for eye in &eyes {
+        validate_circle(eye)?;
+    }
+
+    let mut output = img.to_rgba8();
+    for eye in &eyes {
+        correct_circle(&mut output, eye);
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut};
+    use imageproc::rect::Rect;
+
+    const SKIN: Rgba<u8> = Rgba([224, 172, 140, 255]);
+    const PUPIL: Rgba<u8> = Rgba([210, 30, 40, 255]);
+
+    /// Face with two red pupils at (40, 30) and (80, 30), and a red shirt below
+    fn portrait_fixture() -> DynamicImage {
+        let mut img = RgbaImage::from_pixel(120, 100, SKIN);
+        for x in [40, 80] {
+            draw_filled_circle_mut(&mut img, (x, 30), 8, Rgba([250, 250, 245, 255]));
+            draw_filled_circle_mut(&mut img, (x, 30), 5, PUPIL);
+        }
+        draw_filled_rect_mut(&mut img, Rect::at(0, 75).of_size(120, 25), Rgba([200, 20, 20, 255]));
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_detect_red_eyes_fixture() {
+        let eyes = detect_red_eyes(&portrait_fixture());
+
+        assert_eq!(eyes.len(), 2, "eyes were {:?}", eyes);
+        for (eye, x) in eyes.iter().zip([40.0, 80.0]) {
+            assert!((eye.center.x - x).abs() <= 1.0 && (eye.center.y - 30.0).abs() <= 1.0);
+            assert!(eye.radius >= 5.0 && eye.radius <= 9.0, "radius was {}", eye.radius);
+        }
+    }
+
+    #[test]
+    fn test_red_eye_auto_fixture() {
+        let img = portrait_fixture();
+        let result = red_eye(&img, &RedEyeParams { mode: RedEyeMode::Auto }).unwrap().to_rgba8();
+
+        let pupil = result.get_pixel(40, 30);
+        assert!(redness(pupil) < 0.1, "pupil was {:?}", pupil);
+        assert_eq!(*result.get_pixel(60, 30), SKIN);
+        assert_eq!(*result.get_pixel(60, 90), Rgba([200, 20, 20, 255]));
+    }
+
+    #[test]
+    fn test_red_eye_manual_soft_edge() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(30, 30, PUPIL));
+        let eyes = vec![Circle { center: Point { x: 15.0, y: 15.0 }, radius: 10.0 }];
+        let result = red_eye(&img, &RedEyeParams { mode: RedEyeMode::Manual { eyes } }).unwrap().to_rgba8();
+
+        assert_eq!(*result.get_pixel(15, 15), Rgba([35, 30, 40, 255]));
+        let rim = result.get_pixel(24, 15)[0];
+        assert!(rim > 35 && rim < 210, "rim was {}", rim);
+        assert_eq!(*result.get_pixel(0, 0), PUPIL);
+    }
+
+    #[test]
+    fn test_red_eye_resolve_and_validation() {
+        let resolved = resolve(&portrait_fixture(), &RedEyeParams { mode: RedEyeMode::Auto });
+        assert!(matches!(resolved.mode, RedEyeMode::Manual { ref eyes } if eyes.len() == 2));
+
+        let eyes = vec![Circle { center: Point { x: 1.0, y: 1.0 }, radius: 0.0 }];
+        let params = RedEyeParams { mode: RedEyeMode::Manual { eyes } };
+        assert!(red_eye(&portrait_fixture(), &params).is_err());
+
+        // Blobs found on very large images still resolve to valid circles
+        let widest = max_blob_diameter(20_000, 20_000);
+        let center = Point { x: 10_000.0, y: 10_000.0 };
+        let circle = Circle { center, radius: widest as f32 / 2.0 * DETECTION_MARGIN };
+        assert!(validate_circle(&circle).is_ok(), "{}", circle.radius);
+        assert_eq!(max_blob_diameter(40, 40), 10);
+    }
+}
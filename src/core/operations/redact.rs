@@ -0,0 +1,224 @@
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage, imageops};
+use imageproc::filter::gaussian_blur_f32;
+use crate::core::operations::region::rasterize;
+use crate::core::operations::selection::selection_bounds;
+use crate::types::errors::AppError;
+use crate::types::operations::{RedactMode, RedactParams};
+
+fn validate(params: &RedactParams) -> Result<(), AppError> {
+    if params.regions.is_empty() {
+        return Err(AppError::InvalidOperation {
+            details: "Redact needs at least one region".to_string(),
+        });
+    }
+
+    match params.mode {
+        RedactMode::Fill { .. } => {}
+        RedactMode::Pixelate { block_size } => {
+            if !(2..=1000).contains(&block_size) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Block size must be between 2 and 1000, got {}", block_size),
+                });
+            }
+        }
+        RedactMode::Blur { radius } => {
+            if !(1.0..=200.0).contains(&radius) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Blur radius must be between 1 and 200, got {}", radius),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace every `block` pixel square with its average
+///
+/// Squares are aligned to the image origin so that neighbouring redactions
+/// share a grid.
+fn pixelate(source: &RgbaImage, block: u32) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    let mut output = RgbaImage::new(width, height);
+
+    for by in (0..height).step_by(block as usize) {
+        for bx in (0..width).step_by(block as usize) {
+            let (bw, bh) = (block.min(width - bx), block.min(height - by));
+            let mut sum = [0u64; 4];
+            for y in by..by + bh {
+                for x in bx..bx + bw {
+                    for (total, value) in sum.iter_mut().zip(source.get_pixel(x, y).0) {
+                        *total += value as u64;
+                    }
+                }
+            }
+
+            let count = (bw * bh) as u64;
+            let average = Rgba(sum.map(|total| ((total + count / 2) / count) as u8));
+            for y in by..by + bh {
+                for x in bx..bx + bw {
+                    output.put_pixel(x, y, average);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Blur a pixelated copy of the area around the mask
+fn blurred(source: &RgbaImage, mask: &GrayImage, radius: f32) -> Option<(u32, u32, RgbaImage)> {
+    let bounds = selection_bounds(mask)?;
+    let margin = (radius * 3.0).ceil() as u32;
+    let x = bounds.x.saturating_sub(margin);
+    let y = bounds.y.saturating_sub(margin);
+    let width = (bounds.x + bounds.width + margin).min(source.width()) - x;
+    let height = (bounds.y + bounds.height + margin).min(source.height()) - y;
+
+    let area = imageops::crop_imm(source, x, y, width, height).to_image();
+    let block = (radius.round() as u32).max(2);
+    Some((x, y, gaussian_blur_f32(&pixelate(&area, block), radius)))
+}
+
+/// Hide regions of an image by filling, pixelating or blurring them
+///
+/// Covered pixels are replaced rather than blended, so only the fill colour
+/// or block averages survive in the result.
+pub fn redact(img: &DynamicImage, params: &RedactParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+
+    let mut output = img.to_rgba8();
+    let mask = rasterize(&params.regions, output.width(), output.height())?;
+
+    let replacement = match params.mode {
+        RedactMode::Fill { color } => {
+            let (width, height) = output.dimensions();
+            (0, 0, RgbaImage::from_pixel(width, height, color.to_rgba()))
+        }
+        RedactMode::Pixelate { block_size } => (0, 0, pixelate(&output, block_size)),
+        RedactMode::Blur { radius } => match blurred(&output, &mask, radius) {
+            Some(area) => area,
+            None => return Ok(DynamicImage::ImageRgba8(output)),
+        },
+    };
+
+    let (ox, oy, replacement) = replacement;
+    for (x, y, value) in mask.enumerate_pixels() {
+        if value[0] > 0 {
+            output.put_pixel(x, y, *replacement.get_pixel(x - ox, y - oy));
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{Color, Point, Region};
+
+    /// Image with a distinct value in every pixel
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 40, |x, y| {
+            Rgba([(x * 6) as u8, (y * 6) as u8, ((x + y) * 3) as u8, 255])
+        }))
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Region {
+        Region::Rect { x, y, width, height }
+    }
+
+    #[test]
+    fn test_fill_replaces_including_alpha() {
+        let img = gradient();
+        let color = Color { r: 0, g: 0, b: 0, a: 0 };
+        let regions = vec![rect(10.0, 10.0, 5.0, 5.0)];
+        let params = RedactParams { regions, mode: RedactMode::Fill { color } };
+        let result = redact(&img, &params).unwrap().to_rgba8();
+
+        assert_eq!(*result.get_pixel(12, 12), Rgba([0, 0, 0, 0]));
+        assert_eq!(result.get_pixel(9, 12), img.to_rgba8().get_pixel(9, 12));
+    }
+
+    #[test]
+    fn test_pixelate_uses_whole_blocks() {
+        let img = gradient();
+        // A one-pixel region still gets the average of its whole 8x8 block
+        let params = RedactParams {
+            regions: vec![rect(3.0, 3.0, 1.0, 1.0), rect(16.0, 16.0, 16.0, 8.0)],
+            mode: RedactMode::Pixelate { block_size: 8 },
+        };
+        let result = redact(&img, &params).unwrap().to_rgba8();
+
+        assert_eq!(*result.get_pixel(3, 3), Rgba([21, 21, 21, 255]));
+        assert_eq!(result.get_pixel(2, 3), img.to_rgba8().get_pixel(2, 3));
+        assert_eq!(result.get_pixel(16, 16), result.get_pixel(23, 23));
+        assert_ne!(result.get_pixel(23, 16), result.get_pixel(24, 16));
+    }
+
+    #[test]
+    fn test_blur_stays_inside_regions() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(40, 40, |x, y| {
+            if (x + y) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+        }));
+        let triangle = Region::Polygon {
+            points: vec![
+                Point { x: 5.0, y: 5.0 },
+                Point { x: 35.0, y: 5.0 },
+                Point { x: 5.0, y: 35.0 },
+            ],
+        };
+        let mode = RedactMode::Blur { radius: 4.0 };
+        let params = RedactParams { regions: vec![triangle], mode };
+        let result = redact(&img, &params).unwrap().to_rgba8();
+
+        // The fine checkerboard turns uniform grey inside the region only
+        for (x, y) in [(8, 8), (9, 8), (20, 10), (10, 20)] {
+            let value = result.get_pixel(x, y)[0];
+            assert!((110..=145).contains(&value), "({}, {}) = {}", x, y, value);
+        }
+        assert_eq!(result.get_pixel(30, 30), img.to_rgba8().get_pixel(30, 30));
+    }
+
+    #[test]
+    fn test_degenerate_polygon_does_not_panic() {
+        let img = gradient();
+        let speck = Region::Polygon {
+            points: vec![
+                Point { x: 1.1, y: 1.1 },
+                Point { x: 1.2, y: 1.2 },
+                Point { x: 1.3, y: 1.3 },
+            ],
+        };
+        let params = RedactParams { regions: vec![speck], mode: RedactMode::Pixelate { block_size: 2 } };
+        let result = redact(&img, &params).unwrap();
+        assert_eq!(result.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn test_far_off_rect_does_not_panic() {
+        let img = gradient();
+        // At 1e20 adding the width changes nothing, leaving a zero-width rectangle
+        let far = Region::Rect { x: 1e20, y: 0.0, width: 1.0, height: 5.0 };
+        let fill = RedactMode::Fill { color: Color { r: 0, g: 0, b: 0, a: 255 } };
+        let params = RedactParams { regions: vec![far], mode: fill.clone() };
+        let result = redact(&img, &params).unwrap();
+        assert_eq!(result.to_rgba8(), img.to_rgba8());
+
+        // A huge rectangle covers the image rather than overflowing
+        let huge = Region::Rect { x: -1e20, y: -1e20, width: 1e30, height: 1e30 };
+        let result = redact(&img, &RedactParams { regions: vec![huge], mode: fill }).unwrap();
+        assert!(result.to_rgba8().pixels().all(|pixel| *pixel == Rgba([0, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_rejects_invalid_redactions() {
+        let img = gradient();
+        let regions = vec![rect(0.0, 0.0, 4.0, 4.0)];
+        for mode in [RedactMode::Pixelate { block_size: 1 }, RedactMode::Blur { radius: 0.5 }] {
+            assert!(redact(&img, &RedactParams { regions: regions.clone(), mode }).is_err());
+        }
+        let fill = RedactMode::Fill { color: Color { r: 0, g: 0, b: 0, a: 255 } };
+        assert!(redact(&img, &RedactParams { regions: vec![], mode: fill }).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use image::{GrayImage, ImageBuffer, Luma};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut, draw_polygon_mut};
+use imageproc::point::Point as PixelPoint;
+use imageproc::rect::Rect;
+use crate::types::errors::AppError;
+use crate::types::operations::{Point, Region};
+
+/// Check that a region is drawable
+pub fn validate_region(region: &Region) -> Result<(), AppError> {
+    match region {
+        Region::Rect { x, y, width, height } => {
+            if ![x, y, width, height].iter().all(|value| value.is_finite()) {
+                return Err(AppError::InvalidOperation {
+                    details: "Rectangle coordinates must be finite".to_string(),
+                });
+            }
+            if *width < 1.0 || *height < 1.0 {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Rectangle must be at least 1x1, got {}x{}", width, height),
+                });
+            }
+        }
+        Region::Polygon { points } => {
+            if points.len() < 3 {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Polygon needs at least 3 points, got {}", points.len()),
+                });
+            }
+        }
+        Region::Stroke { points, radius } => {
+            if points.is_empty() {
+                return Err(AppError::InvalidOperation {
+                    details: "Stroke needs at least 1 point".to_string(),
+                });
+            }
+            if !(*radius > 0.0 && *radius <= 1000.0) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Stroke radius must be between 0 and 1000, got {}", radius),
+                });
+            }
+        }
+    }
+
+    if region_points(region).iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        return Err(AppError::InvalidOperation {
+            details: "Region points must be finite".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn region_points(region: &Region) -> &[Point] {
+    match region {
+        Region::Rect { .. } => &[],
+        Region::Polygon { points } | Region::Stroke { points, .. } => points,
+    }
+}
+
+/// Draw a region onto a mask with the given value
+pub fn draw_region(mask: &mut GrayImage, region: &Region, value: u8) {
+    match region {
+        Region::Rect { x, y, width, height } => {
+            // Clamp in i64 so far-off or huge rectangles cannot overflow or collapse to zero size
+            let (width_px, height_px) = (mask.width() as i64, mask.height() as i64);
+            let x0 = (x.round() as i64).clamp(0, width_px);
+            let y0 = (y.round() as i64).clamp(0, height_px);
+            let x1 = ((x + width).round() as i64).clamp(0, width_px);
+            let y1 = ((y + height).round() as i64).clamp(0, height_px);
+            if x0 < x1 && y0 < y1 {
+                let rect = Rect::at(x0 as i32, y0 as i32);
+                let rect = rect.of_size((x1 - x0) as u32, (y1 - y0) as u32);
+                draw_filled_rect_mut(mask, rect, Luma([value]));
+            }
+        }
+        Region::Polygon { points } => {
+            let points: Vec<(f64, f64)> = points.iter().map(|p| (p.x as f64, p.y as f64)).collect();
+            let poly = polygon_pixels(&points, mask.width(), mask.height());
+            if !poly.is_empty() {
+                draw_polygon_mut(mask, &poly, Luma([value]));
+            }
+        }
+        Region::Stroke { points, radius } => {
+            // Stamp the brush along each segment, half a radius apart
+            for p in stroke_centres(points, radius / 2.0, mask.width(), mask.height(), *radius) {
+                let center = (p.x.round() as i32, p.y.round() as i32);
+                draw_filled_circle_mut(mask, center, radius.round() as i32, Luma([value]));
+            }
+        }
+    }
+}
+
+/// Clip a closed polygon to `bounds` (x0, y0, x1, y1) with Sutherland-Hodgman
+///
+/// Filling the result covers the same area inside `bounds` as the original
+/// polygon; edges outside are replaced by runs along the border.
+fn clip_polygon(points: &[(f64, f64)], bounds: [f64; 4]) -> Vec<(f64, f64)> {
+    // Signed distance inside the left, top, right or bottom edge
+    let inside = |p: (f64, f64), edge: usize| match edge {
+        0 => p.0 - bounds[0],
+        1 => p.1 - bounds[1],
+        2 => bounds[2] - p.0,
+        _ => bounds[3] - p.1,
+    };
+
+    let mut clipped = points.to_vec();
+    for edge in 0..4 {
+        let input = std::mem::take(&mut clipped);
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            let (d0, d1) = (inside(previous, edge), inside(current, edge));
+            if (d0 >= 0.0) != (d1 >= 0.0) {
+                let t = d0 / (d0 - d1);
+                let x = previous.0 + (current.0 - previous.0) * t;
+                let y = previous.1 + (current.1 - previous.1) * t;
+                clipped.push((x, y));
+            }
+            if d1 >= 0.0 {
+                clipped.push(current);
+            }
+        }
+    }
+
+    clipped
+}
+
+/// Pixel vertices of a polygon ready for imageproc's `draw_polygon_mut`
+///
+/// The polygon is clipped to a one-pixel margin around a `width` x `height`
+/// mask first, so far-off vertices do not make imageproc walk every pixel of
+/// their edges. Returns an empty list when fewer than three distinct pixels
+/// remain, since such a polygon covers no area and imageproc panics on one
+/// that collapsed to a single point.
+pub fn polygon_pixels(points: &[(f64, f64)], width: u32, height: u32) -> Vec<PixelPoint<i32>> {
+    let bounds = [-1.0, -1.0, width as f64 + 1.0, height as f64 + 1.0];
+    let mut poly: Vec<PixelPoint<i32>> = clip_polygon(points, bounds)
+        .iter()
+        .map(|&(x, y)| PixelPoint::new(x.round() as i32, y.round() as i32))
+        .collect();
+    poly.dedup();
+    // imageproc rejects explicitly closed polygons
+    while poly.len() > 1 && poly.first() == poly.last() {
+        poly.pop();
+    }
+
+    if poly.len() < 3 {
+        poly.clear();
+    }
+    poly
+}
+
+/// Portion of the segment `a`-`b` inside `bounds` (x0, y0, x1, y1), as a parameter range
+fn clip_segment(a: Point, b: Point, bounds: [f64; 4]) -> Option<(f64, f64)> {
+    let (ax, ay) = (a.x as f64, a.y as f64);
+    let (dx, dy) = (b.x as f64 - ax, b.y as f64 - ay);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    let edges = [
+        (-dx, ax - bounds[0]),
+        (dx, bounds[2] - ax),
+        (-dy, ay - bounds[1]),
+        (dy, bounds[3] - ay),
+    ];
+
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Brush dab positions along a path, at most `spacing` pixels apart
+///
+/// Segments are clipped to a `width` x `height` image grown by `margin`, so
+/// distant points cost nothing. Inside it, the first point is always included
+/// and every segment ends on its endpoint.
+pub fn stroke_centres(
+    points: &[Point],
+    spacing: f32,
+    width: u32,
+    height: u32,
+    margin: f32,
+) -> Vec<Point> {
+    let spacing = spacing.max(1.0);
+    let margin = margin as f64;
+    let bounds = [-margin, -margin, width as f64 + margin, height as f64 + margin];
+    let inside = |p: &Point| {
+        let (x, y) = (p.x as f64, p.y as f64);
+        x >= bounds[0] && y >= bounds[1] && x <= bounds[2] && y <= bounds[3]
+    };
+    let mut centres: Vec<Point> = points.first().copied().filter(inside).into_iter().collect();
+
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let Some((t0, t1)) = clip_segment(a, b, bounds) else {
+            continue;
+        };
+        // Interpolate in f64 so points far outside the image keep their precision
+        let at = |t: f64| {
+            let x = a.x as f64 + (b.x as f64 - a.x as f64) * t;
+            let y = a.y as f64 + (b.y as f64 - a.y as f64) * t;
+            (x, y)
+        };
+        let (start, end) = (at(t0), at(t1));
+        if t0 > 0.0 {
+            centres.push(Point { x: start.0 as f32, y: start.1 as f32 });
+        }
+
+        let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+        let steps = (length / spacing as f64).ceil().max(1.0) as u32;
+        for i in 1..=steps {
+            let t = i as f64 / steps as f64;
+            let (x, y) = (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+            centres.push(Point { x: x as f32, y: y as f32 });
+        }
+    }
+
+    centres
+}
+
+/// Brush strength at `distance` from the dab centre
+pub fn brush_falloff(distance: f32, radius: f32, hardness: f32) -> f32 {
+    let d = distance / radius;
+    if d >= 1.0 {
+        0.0
+    } else if d <= hardness {
+        1.0
+    } else {
+        let t = (1.0 - d) / (1.0 - hardness);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Per-pixel brush strength from 0.0 to 1.0
+pub type Coverage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// Paint a soft round brush along a path
+///
+/// Dabs are spaced a quarter radius apart and overlapping dabs keep the
+/// strongest value, so a stroke never builds up past its own falloff.
+pub fn paint_stroke(coverage: &mut Coverage, points: &[Point], radius: f32, hardness: f32) {
+    let (width, height) = (coverage.width() as i64, coverage.height() as i64);
+
+    let centres = stroke_centres(points, radius / 4.0, coverage.width(), coverage.height(), radius);
+    for centre in centres {
+        let x0 = ((centre.x - radius).floor() as i64).max(0);
+        let y0 = ((centre.y - radius).floor() as i64).max(0);
+        let x1 = ((centre.x + radius).ceil() as i64 + 1).min(width);
+        let y1 = ((centre.y + radius).ceil() as i64 + 1).min(height);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let distance = ((x as f32 - centre.x).powi(2) + (y as f32 - centre.y).powi(2)).sqrt();
+                let cell = &mut coverage.get_pixel_mut(x as u32, y as u32)[0];
+                *cell = cell.max(brush_falloff(distance, radius, hardness));
+            }
+        }
+    }
+}
+
+/// Rasterise regions into a binary mask (255 inside, 0 outside)
+///
+/// # Returns
+/// Mask of the given size, or error if a region is invalid
+pub fn rasterize(regions: &[Region], width: u32, height: u32) -> Result<GrayImage, AppError> {
+    let mut mask = GrayImage::new(width, height);
+    for region in regions {
+        validate_region(region)?;
+        draw_region(&mut mask, region, 255);
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_rasterize_polygon_and_stroke() {
+        let regions = vec![
+            Region::Polygon {
+                points: vec![point(1.0, 1.0), point(5.0, 1.0), point(5.0, 5.0), point(1.0, 5.0), point(1.0, 1.0)],
+            },
+            Region::Stroke { points: vec![point(10.0, 10.0), point(18.0, 10.0)], radius: 1.0 },
+            Region::Rect { x: 2.0, y: 14.0, width: 4.0, height: 3.0 },
+        ];
+
+        let mask = rasterize(&regions, 20, 20).unwrap();
+        assert_eq!(mask.get_pixel(3, 3)[0], 255);
+        assert_eq!(mask.get_pixel(14, 10)[0], 255);
+        assert_eq!(mask.get_pixel(14, 14)[0], 0);
+        assert_eq!(mask.get_pixel(8, 8)[0], 0);
+        assert_eq!(mask.get_pixel(5, 16)[0], 255);
+        assert_eq!(mask.get_pixel(6, 16)[0], 0);
+        assert_eq!(mask.get_pixel(5, 17)[0], 0);
+    }
+
+    #[test]
+    fn test_rasterize_validation() {
+        let polygon = Region::Polygon { points: vec![point(0.0, 0.0), point(1.0, 1.0)] };
+        assert!(rasterize(&[polygon], 4, 4).is_err());
+
+        let stroke = Region::Stroke { points: vec![point(0.0, 0.0)], radius: 0.0 };
+        assert!(rasterize(&[stroke], 4, 4).is_err());
+
+        let rect = Region::Rect { x: 0.0, y: 0.0, width: 0.5, height: 2.0 };
+        assert!(rasterize(&[rect], 4, 4).is_err());
+    }
+
+    #[test]
+    fn test_stroke_centres_clip_to_image() {
+        // A stroke reaching far off the image only places dabs near it
+        let far = [point(0.0, 5.0), point(1e9, 5.0)];
+        let centres = stroke_centres(&far, 1.0, 20, 10, 2.0);
+        assert!(centres.len() <= 24, "{}", centres.len());
+        assert!(centres.iter().all(|p| (-2.0..=22.0).contains(&p.x)));
+
+        let outside = [point(-1e9, -50.0), point(1e9, -50.0)];
+        assert!(stroke_centres(&outside, 1.0, 20, 10, 2.0).is_empty());
+
+        let points = vec![point(-1e9, 3.0), point(1e9, 3.0)];
+        let stroke = Region::Stroke { points, radius: 1.0 };
+        let mask = rasterize(&[stroke], 20, 10).unwrap();
+        assert_eq!(mask.get_pixel(0, 3)[0], 255);
+        assert_eq!(mask.get_pixel(19, 3)[0], 255);
+    }
+
+    #[test]
+    fn test_degenerate_polygon_draws_nothing() {
+        // Every point rounds to the same pixel
+        let speck = Region::Polygon { points: vec![point(1.1, 1.1), point(1.2, 1.2), point(1.3, 1.3)] };
+        // Two distinct pixels make a line with no inside
+        let sliver = Region::Polygon { points: vec![point(1.0, 1.0), point(3.0, 3.0), point(1.2, 0.9)] };
+
+        let mask = rasterize(&[speck, sliver], 4, 4).unwrap();
+        assert!(mask.pixels().all(|pixel| pixel[0] == 0));
+    }
+
+    #[test]
+    fn test_polygon_clips_to_image() {
+        // A triangle with far-off corners is clipped, keeping its edge through the image
+        let points = vec![point(-1e9, -1e9), point(1e9, 1e9), point(-1e9, 1e9)];
+        let mask = rasterize(&[Region::Polygon { points }], 20, 10).unwrap();
+        assert_eq!(mask.get_pixel(0, 9)[0], 255);
+        assert_eq!(mask.get_pixel(2, 8)[0], 255);
+        assert_eq!(mask.get_pixel(8, 2)[0], 0);
+        assert_eq!(mask.get_pixel(19, 0)[0], 0);
+
+        let outside = vec![point(-1e9, -50.0), point(1e9, -50.0), point(0.0, -1e9)];
+        let mask = rasterize(&[Region::Polygon { points: outside }], 20, 10).unwrap();
+        assert!(mask.pixels().all(|pixel| pixel[0] == 0));
+    }
+}
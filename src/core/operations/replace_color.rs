@@ -0,0 +1,191 @@
+use image::{DynamicImage, GenericImageView};
+use rayon::prelude::*;
+use crate::core::operations::adjustments::{hsl_to_rgb, rgb_to_hsl};
+use crate::types::errors::AppError;
+use crate::types::operations::{ColorSource, ReplaceColorParams};
+
+/// Sources below this saturation have no meaningful hue to match
+const NEUTRAL_SATURATION: f32 = 0.02;
+
+fn validate(params: &ReplaceColorParams) -> Result<(), AppError> {
+    if !(0.0..=180.0).contains(&params.hue_tolerance) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "Hue tolerance must be between 0 and 180, got {}",
+                params.hue_tolerance
+            ),
+        });
+    }
+
+    let tones = [params.saturation_tolerance, params.lightness_tolerance, params.fuzziness];
+    if !tones.iter().all(|value| (0.0..=1.0).contains(value)) {
+        return Err(AppError::InvalidOperation {
+            details: format!(
+                "Saturation and lightness tolerances and fuzziness must be between 0 and 1, \
+                 got {:?}",
+                tones
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Resolve the source to an RGB colour
+fn source_rgb(img: &DynamicImage, source: &ColorSource) -> Result<[u8; 3], AppError> {
+    match source {
+        ColorSource::Color { color } => Ok([color.r, color.g, color.b]),
+        ColorSource::Sample { point } => {
+            let (width, height) = img.dimensions();
+            if !(point.x >= 0.0 && point.y >= 0.0)
+                || point.x >= width as f32
+                || point.y >= height as f32
+            {
+                return Err(AppError::InvalidOperation {
+                    details: format!(
+                        "Sample point ({}, {}) lies outside the image",
+                        point.x, point.y
+                    ),
+                });
+            }
+            let pixel = img.get_pixel(point.x as u32, point.y as u32);
+            Ok([pixel[0], pixel[1], pixel[2]])
+        }
+    }
+}
+
+/// Full match within `tolerance`, fading to none over `fuzziness` of it beyond
+fn axis_weight(difference: f32, tolerance: f32, fuzziness: f32) -> f32 {
+    let falloff = tolerance * fuzziness;
+    if difference <= tolerance {
+        1.0
+    } else if falloff > 0.0 {
+        (1.0 - (difference - tolerance) / falloff).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// Shift pixels near a source colour towards a target colour
+///
+/// Matching pixels move by the HSL difference between source and target
+/// rather than being set to the target, so their relative lightness and
+/// saturation are preserved. Alpha is left untouched.
+pub fn replace_color(
+    img: &DynamicImage,
+    params: &ReplaceColorParams,
+) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+
+    let [r, g, b] = source_rgb(img, &params.source)?;
+    let (source_h, source_s, source_l) = rgb_to_hsl(r, g, b);
+    let (target_h, target_s, target_l) =
+        rgb_to_hsl(params.target.r, params.target.g, params.target.b);
+    let match_hue = source_s >= NEUTRAL_SATURATION;
+
+    let mut output = img.to_rgba8();
+    output.par_chunks_mut(4).for_each(|pixel| {
+        let (h, s, l) = rgb_to_hsl(pixel[0], pixel[1], pixel[2]);
+
+        let hue_difference = (h - source_h).rem_euclid(360.0);
+        let hue_difference = hue_difference.min(360.0 - hue_difference);
+        let fuzziness = params.fuzziness;
+        let mut weight = axis_weight((s - source_s).abs(), params.saturation_tolerance, fuzziness)
+            * axis_weight((l - source_l).abs(), params.lightness_tolerance, fuzziness);
+        if match_hue {
+            weight *= axis_weight(hue_difference, params.hue_tolerance, fuzziness);
+        }
+        if weight <= 0.0 {
+            return;
+        }
+
+        let shifted = hsl_to_rgb(
+            (h + target_h - source_h).rem_euclid(360.0),
+            (s + target_s - source_s).clamp(0.0, 1.0),
+            (l + target_l - source_l).clamp(0.0, 1.0),
+        );
+        for (channel, value) in pixel.iter_mut().zip([shifted.0, shifted.1, shifted.2]) {
+            let blended = *channel as f32 + (value as f32 - *channel as f32) * weight;
+            *channel = blended.round() as u8;
+        }
+    });
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{Color, Point};
+    use image::{Rgba, RgbaImage};
+
+    fn params(source: ColorSource, target: Color) -> ReplaceColorParams {
+        ReplaceColorParams {
+            source,
+            target,
+            hue_tolerance: 20.0,
+            saturation_tolerance: 0.3,
+            lightness_tolerance: 0.3,
+            fuzziness: 0.0,
+        }
+    }
+
+    /// Dark red, red, light red, then blue and alpha-faded red
+    fn swatches() -> DynamicImage {
+        let colours = [[128, 0, 0, 255], [255, 0, 0, 255], [255, 128, 128, 255], [0, 0, 255, 255]];
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(5, 1, |x, _| match colours.get(x as usize) {
+            Some(colour) => Rgba(*colour),
+            None => Rgba([255, 0, 0, 100]),
+        }))
+    }
+
+    #[test]
+    fn test_replacement_keeps_relative_lightness() {
+        let red = Color { r: 255, g: 0, b: 0, a: 255 };
+        let green = Color { r: 0, g: 255, b: 0, a: 255 };
+        let result = replace_color(&swatches(), &params(ColorSource::Color { color: red }, green))
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(*result.get_pixel(1, 0), Rgba([0, 255, 0, 255]));
+        // Darker and lighter reds become darker and lighter greens
+        let dark = result.get_pixel(0, 0);
+        assert!(dark[0] < 2 && (126..=128).contains(&dark[1]) && dark[2] < 2, "{:?}", dark);
+        let light = result.get_pixel(2, 0);
+        assert!(light[0] < 130 && light[1] == 255 && light[2] < 130, "{:?}", light);
+        assert_eq!(*result.get_pixel(3, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*result.get_pixel(4, 0), Rgba([0, 255, 0, 100]));
+    }
+
+    #[test]
+    fn test_sampled_source_and_fuzziness() {
+        let blue = Color { r: 0, g: 0, b: 255, a: 255 };
+        let mut replace = params(ColorSource::Sample { point: Point { x: 1.5, y: 0.5 } }, blue);
+        replace.lightness_tolerance = 0.1;
+        let strict = replace_color(&swatches(), &replace).unwrap().to_rgba8();
+        assert_eq!(*strict.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(*strict.get_pixel(0, 0), Rgba([128, 0, 0, 255]));
+
+        // Dark red is 0.25 from the sample's lightness, two thirds through a 0.15 falloff
+        replace.lightness_tolerance = 0.15;
+        replace.fuzziness = 1.0;
+        let fuzzy = replace_color(&swatches(), &replace).unwrap().to_rgba8();
+        let dark = fuzzy.get_pixel(0, 0);
+        assert!((82..=87).contains(&dark[0]) && (40..=46).contains(&dark[2]), "{:?}", dark);
+    }
+
+    #[test]
+    fn test_rejects_invalid_parameters() {
+        let img = swatches();
+        let white = Color { r: 255, g: 255, b: 255, a: 255 };
+        let outside = ColorSource::Sample { point: Point { x: 5.0, y: 0.0 } };
+        assert!(replace_color(&img, &params(outside, white)).is_err());
+
+        let mut wide = params(ColorSource::Color { color: white }, white);
+        wide.hue_tolerance = 200.0;
+        assert!(replace_color(&img, &wide).is_err());
+        wide.hue_tolerance = 20.0;
+        wide.fuzziness = 2.0;
+        assert!(replace_color(&img, &wide).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use image::{DynamicImage, GenericImageView, imageops};
+use crate::types::errors::AppError;
+use crate::types::operations::{ResampleFilter, ResizeMode, ResizeParams};
+use crate::utils::preview::validate_dimensions;
+
+/// Map a resample filter to the `image` crate filter type
+pub fn filter_type(filter: ResampleFilter) -> imageops::FilterType {
+    match filter {
+        ResampleFilter::Nearest => imageops::FilterType::Nearest,
+        ResampleFilter::Triangle => imageops::FilterType::Triangle,
+        ResampleFilter::CatmullRom => imageops::FilterType::CatmullRom,
+        ResampleFilter::Gaussian => imageops::FilterType::Gaussian,
+        ResampleFilter::Lanczos3 => imageops::FilterType::Lanczos3,
+    }
+}
+
+/// Scale a dimension by a ratio, never going below one pixel
+fn scale(value: u32, ratio: f64) -> u32 {
+    ((value as f64 * ratio).round() as u32).max(1)
+}
+
+/// Calculate the output dimensions of a resize
+///
+/// # Parameters
+/// - `width`, `height`: Current image dimensions
+/// - `mode`: Resize mode
+/// - `keep_aspect`: For `Pixels` mode, derive the missing side from the other.
+///   Width takes precedence; pass a width of 0 to drive the resize by height.
+///
+/// # Returns
+/// Target (width, height), validated against the maximum image dimensions
+pub fn target_dimensions(
+    width: u32,
+    height: u32,
+    mode: &ResizeMode,
+    keep_aspect: bool,
+) -> Result<(u32, u32), AppError> {
+    let (new_width, new_height) = match *mode {
+        ResizeMode::Pixels { width: w, height: h } => {
+            if keep_aspect && w > 0 {
+                (w, scale(height, w as f64 / width as f64))
+            } else if keep_aspect && h > 0 {
+                (scale(width, h as f64 / height as f64), h)
+            } else {
+                (w, h)
+            }
+        }
+        ResizeMode::Percentage { percent } => {
+            if !(percent > 0.0 && percent <= 1000.0) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Resize percentage must be between 0 and 1000, got {}", percent),
+                });
+            }
+            let ratio = percent as f64 / 100.0;
+            (scale(width, ratio), scale(height, ratio))
+        }
+        ResizeMode::Fit { width: w, height: h } => {
+            if w == 0 || h == 0 {
+                (w, h)
+            } else {
+                let ratio = (w as f64 / width as f64).min(h as f64 / height as f64);
+                (scale(width, ratio).min(w), scale(height, ratio).min(h))
+            }
+        }
+        ResizeMode::Fill { width: w, height: h } => (w, h),
+        ResizeMode::LongEdge { length } => {
+            if length == 0 {
+                (0, 0)
+            } else {
+                let ratio = length as f64 / width.max(height) as f64;
+                (scale(width, ratio), scale(height, ratio))
+            }
+        }
+    };
+
+    validate_dimensions(new_width, new_height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    Ok((new_width, new_height))
+}
+
+/// Resize an image
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Resize mode, resampling filter, aspect lock and optional sharpening
+///
+/// # Returns
+/// Resized image or error if the target dimensions are invalid
+pub fn resize(img: &DynamicImage, params: &ResizeParams) -> Result<DynamicImage, AppError> {
+    let (width, height) = img.dimensions();
+    let (new_width, new_height) = target_dimensions(width, height, &params.mode, params.keep_aspect)?;
+
+    if let Some(sigma) = params.sharpen
+        && (sigma <= 0.0 || sigma > 10.0)
+    {
+        return Err(AppError::InvalidOperation {
+            details: format!("Sharpen sigma must be between 0 and 10, got {}", sigma),
+        });
+    }
+
+    let filter = filter_type(params.filter);
+    let resized = match params.mode {
+        // Scale to cover the box, then crop the overflow from the centre
+        ResizeMode::Fill { .. } => img.resize_to_fill(new_width, new_height, filter),
+        _ => img.resize_exact(new_width, new_height, filter),
+    };
+
+    Ok(match params.sharpen {
+        Some(sigma) => resized.unsharpen(sigma, 1),
+        None => resized,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(mode: ResizeMode) -> ResizeParams {
+        ResizeParams {
+            mode,
+            filter: ResampleFilter::Triangle,
+            keep_aspect: true,
+            sharpen: None,
+        }
+    }
+
+    #[test]
+    fn test_resize_pixels_exact() {
+        let img = DynamicImage::new_rgb8(100, 50);
+        let mut p = params(ResizeMode::Pixels { width: 30, height: 40 });
+        p.keep_aspect = false;
+
+        let result = resize(&img, &p).unwrap();
+        assert_eq!(result.dimensions(), (30, 40));
+    }
+
+    #[test]
+    fn test_resize_pixels_keep_aspect() {
+        let img = DynamicImage::new_rgb8(100, 50);
+
+        let result = resize(&img, &params(ResizeMode::Pixels { width: 40, height: 0 })).unwrap();
+        assert_eq!(result.dimensions(), (40, 20));
+
+        let result = resize(&img, &params(ResizeMode::Pixels { width: 0, height: 10 })).unwrap();
+        assert_eq!(result.dimensions(), (20, 10));
+    }
+
+    #[test]
+    fn test_resize_percentage_and_long_edge() {
+        let img = DynamicImage::new_rgb8(100, 50);
+
+        let result = resize(&img, &params(ResizeMode::Percentage { percent: 50.0 })).unwrap();
+        assert_eq!(result.dimensions(), (50, 25));
+
+        let result = resize(&img, &params(ResizeMode::LongEdge { length: 200 })).unwrap();
+        assert_eq!(result.dimensions(), (200, 100));
+    }
+
+    #[test]
+    fn test_resize_fit_and_fill() {
+        let img = DynamicImage::new_rgb8(100, 50);
+
+        let fit = resize(&img, &params(ResizeMode::Fit { width: 40, height: 40 })).unwrap();
+        assert_eq!(fit.dimensions(), (40, 20));
+
+        let fill = resize(&img, &params(ResizeMode::Fill { width: 40, height: 40 })).unwrap();
+        assert_eq!(fill.dimensions(), (40, 40));
+    }
+
+    #[test]
+    fn test_resize_validation() {
+        let img = DynamicImage::new_rgb8(10, 10);
+
+        assert!(resize(&img, &params(ResizeMode::Percentage { percent: 0.0 })).is_err());
+        assert!(resize(&img, &params(ResizeMode::Fill { width: 0, height: 10 })).is_err());
+        assert!(resize(&img, &params(ResizeMode::Pixels { width: 20000, height: 0 })).is_err());
+
+        let mut p = params(ResizeMode::Percentage { percent: 200.0 });
+        p.sharpen = Some(-1.0);
+        assert!(resize(&img, &p).is_err());
+
+        p.sharpen = Some(1.0);
+        assert_eq!(resize(&img, &p).unwrap().dimensions(), (20, 20));
+    }
+}
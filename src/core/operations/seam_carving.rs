@@ -0,0 +1,342 @@
+use image::{DynamicImage, RgbaImage};
+use crate::types::errors::AppError;
+use crate::types::operations::{ContentAwareResizeParams, CropRect};
+use crate::utils::preview::validate_dimensions;
+
+/// Energy added to protected pixels so seams route around them
+const PROTECT_ENERGY: f64 = 1.0e6;
+
+/// Pixel grid being carved, in row-major order
+///
+/// Luma and energy are cached per pixel and updated only around each removed
+/// seam, since a seam changes the neighbours of few pixels.
+#[derive(Debug, Clone)]
+struct Grid {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+    protect: Vec<bool>,
+    luma: Vec<f32>,
+    energy: Vec<f64>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize, pixels: Vec<[u8; 4]>, protect: Vec<bool>) -> Self {
+        let luma = pixels
+            .iter()
+            .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) * p[3] as f32 / 255.0)
+            .collect();
+        let mut grid = Self {
+            width,
+            height,
+            pixels,
+            protect,
+            luma,
+            energy: vec![0.0; width * height],
+        };
+        for i in 0..width * height {
+            grid.energy[i] = grid.pixel_energy(i % width, i / width);
+        }
+
+        grid
+    }
+
+    fn from_image(img: &RgbaImage, protect_mask: &[CropRect]) -> Self {
+        let (width, height) = img.dimensions();
+        let pixels = img.pixels().map(|p| p.0).collect();
+        let protect = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                protect_mask.iter().any(|r| {
+                    x >= r.x && y >= r.y && x - r.x < r.width && y - r.y < r.height
+                })
+            })
+            .collect();
+
+        Self::new(width as usize, height as usize, pixels, protect)
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let raw = self.pixels.into_iter().flatten().collect();
+        RgbaImage::from_raw(self.width as u32, self.height as u32, raw)
+            .expect("Failed to create image buffer")
+    }
+
+    fn transpose(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        let mut protect = Vec::with_capacity(self.protect.len());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                pixels.push(self.pixels[y * self.width + x]);
+                protect.push(self.protect[y * self.width + x]);
+            }
+        }
+
+        Self::new(self.height, self.width, pixels, protect)
+    }
+
+    /// Gradient magnitude energy of one pixel
+    fn pixel_energy(&self, x: usize, y: usize) -> f64 {
+        let (w, h) = (self.width, self.height);
+        let luma = &self.luma;
+        let left = luma[y * w + x.saturating_sub(1)];
+        let right = luma[y * w + (x + 1).min(w - 1)];
+        let up = luma[y.saturating_sub(1) * w + x];
+        let down = luma[(y + 1).min(h - 1) * w + x];
+
+        let protect = if self.protect[y * w + x] { PROTECT_ENERGY } else { 0.0 };
+        ((right - left).abs() + (down - up).abs()) as f64 + protect
+    }
+
+    /// Find the lowest-energy top-to-bottom seam with dynamic programming
+    ///
+    /// Costs are summed in f64 so small differences still count on paths
+    /// that cross protected pixels.
+    ///
+    /// # Returns
+    /// Column of the seam in each row
+    fn find_vertical_seam(&self) -> Vec<usize> {
+        let (w, h) = (self.width, self.height);
+        let mut cost = self.energy.clone();
+
+        for y in 1..h {
+            for x in 0..w {
+                let above = &cost[(y - 1) * w + x.saturating_sub(1)..=(y - 1) * w + (x + 1).min(w - 1)];
+                let min = above.iter().copied().fold(f64::INFINITY, f64::min);
+                cost[y * w + x] += min;
+            }
+        }
+
+        let argmin = |row: usize, from: usize, to: usize| {
+            (from..=to)
+                .min_by(|a, b| cost[row * w + a].total_cmp(&cost[row * w + b]))
+                .unwrap_or(from)
+        };
+
+        let mut seam = vec![0; h];
+        seam[h - 1] = argmin(h - 1, 0, w - 1);
+        for y in (0..h - 1).rev() {
+            let x = seam[y + 1];
+            seam[y] = argmin(y, x.saturating_sub(1), (x + 1).min(w - 1));
+        }
+
+        seam
+    }
+
+    fn remove_vertical_seam(&mut self, seam: &[usize]) {
+        self.pixels = remove_seam(&self.pixels, self.width, seam);
+        self.protect = remove_seam(&self.protect, self.width, seam);
+        self.luma = remove_seam(&self.luma, self.width, seam);
+        self.energy = remove_seam(&self.energy, self.width, seam);
+        self.width -= 1;
+        if self.width == 0 {
+            return;
+        }
+
+        // Only pixels beside the seam here or in the rows above and below
+        // have new neighbours; pixels further right all moved together
+        let h = self.height;
+        for y in 0..h {
+            let near = [seam[y.saturating_sub(1)], seam[y], seam[(y + 1).min(h - 1)]];
+            let from = near.iter().min().copied().unwrap_or(0).saturating_sub(1);
+            let to = near.iter().max().copied().unwrap_or(0).min(self.width - 1);
+            for x in from..=to {
+                self.energy[y * self.width + x] = self.pixel_energy(x, y);
+            }
+        }
+    }
+
+    fn shrink_width(&mut self, count: usize) {
+        for _ in 0..count {
+            let seam = self.find_vertical_seam();
+            self.remove_vertical_seam(&seam);
+        }
+    }
+
+    /// Widen by duplicating the `count` lowest-energy seams (at most the current width)
+    fn enlarge_width(&mut self, count: usize) {
+        let count = count.min(self.width);
+
+        // Carve a copy to find distinct seams, tracking their original columns
+        let mut work = self.clone();
+        let mut columns: Vec<usize> = (0..self.width * self.height).map(|i| i % self.width).collect();
+        let mut inserts: Vec<Vec<usize>> = vec![Vec::with_capacity(count); self.height];
+
+        for _ in 0..count {
+            let seam = work.find_vertical_seam();
+            for (y, &x) in seam.iter().enumerate() {
+                inserts[y].push(columns[y * work.width + x]);
+            }
+            columns = remove_seam(&columns, work.width, &seam);
+            work.remove_vertical_seam(&seam);
+        }
+
+        let new_width = self.width + count;
+        let mut pixels = Vec::with_capacity(new_width * self.height);
+        let mut protect = Vec::with_capacity(new_width * self.height);
+
+        for (y, row_inserts) in inserts.iter_mut().enumerate() {
+            row_inserts.sort_unstable();
+            let row = y * self.width;
+            let mut next = row_inserts.iter().peekable();
+
+            for x in 0..self.width {
+                pixels.push(self.pixels[row + x]);
+                protect.push(self.protect[row + x]);
+
+                while next.next_if(|&&seam_x| seam_x == x).is_some() {
+                    let right = self.pixels[row + (x + 1).min(self.width - 1)];
+                    pixels.push(average(self.pixels[row + x], right));
+                    protect.push(self.protect[row + x]);
+                }
+            }
+        }
+
+        *self = Self::new(new_width, self.height, pixels, protect);
+    }
+
+    fn resize_width(&mut self, target: usize) {
+        while self.width < target {
+            self.enlarge_width(target - self.width);
+        }
+        if self.width > target {
+            self.shrink_width(self.width - target);
+        }
+    }
+}
+
+/// Drop one element per row at the seam column
+fn remove_seam<T: Copy>(data: &[T], width: usize, seam: &[usize]) -> Vec<T> {
+    data.chunks(width)
+        .zip(seam)
+        .flat_map(|(row, &x)| row[..x].iter().chain(&row[x + 1..]).copied())
+        .collect()
+}
+
+fn average(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    std::array::from_fn(|i| ((a[i] as u16 + b[i] as u16) / 2) as u8)
+}
+
+/// Resize an image by removing or duplicating low-energy seams
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Target dimensions and regions to protect
+///
+/// # Returns
+/// RGBA image of the target size
+pub fn content_aware_resize(img: &DynamicImage, params: &ContentAwareResizeParams) -> Result<DynamicImage, AppError> {
+    validate_dimensions(params.width, params.height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    let mut grid = Grid::from_image(&img.to_rgba8(), &params.protect_mask);
+    grid.resize_width(params.width as usize);
+
+    let mut grid = grid.transpose();
+    grid.resize_width(params.height as usize);
+
+    Ok(DynamicImage::ImageRgba8(grid.transpose().into_image()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, Rgba};
+
+    const GREY: Rgba<u8> = Rgba([128, 128, 128, 255]);
+
+    fn params(width: u32, height: u32, protect_mask: Vec<CropRect>) -> ContentAwareResizeParams {
+        ContentAwareResizeParams { width, height, protect_mask }
+    }
+
+    /// Flat grey left half, checkerboard right half
+    fn half_checkerboard() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(10, 6, |x, y| {
+            if x < 5 {
+                GREY
+            } else if (x + y) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        }))
+    }
+
+    fn grey_columns(img: &RgbaImage) -> usize {
+        (0..img.width())
+            .filter(|&x| (0..img.height()).all(|y| *img.get_pixel(x, y) == GREY))
+            .count()
+    }
+
+    #[test]
+    fn test_seam_carving_removes_flat_areas_first() {
+        let result = content_aware_resize(&half_checkerboard(), &params(7, 6, vec![])).unwrap().to_rgba8();
+
+        assert_eq!(result.dimensions(), (7, 6));
+        assert_eq!(grey_columns(&result), 2);
+    }
+
+    #[test]
+    fn test_seam_carving_protect_mask() {
+        let protect = vec![CropRect { x: 0, y: 0, width: 5, height: 6 }];
+        let result = content_aware_resize(&half_checkerboard(), &params(7, 6, protect)).unwrap().to_rgba8();
+
+        assert_eq!(result.dimensions(), (7, 6));
+        assert_eq!(grey_columns(&result), 5);
+    }
+
+    #[test]
+    fn test_seam_carving_enlarge_and_height() {
+        let img = half_checkerboard();
+
+        let wider = content_aware_resize(&img, &params(25, 6, vec![])).unwrap();
+        assert_eq!(wider.dimensions(), (25, 6));
+
+        let shorter = content_aware_resize(&img, &params(10, 3, vec![])).unwrap();
+        assert_eq!(shorter.dimensions(), (10, 3));
+    }
+
+    #[test]
+    fn test_find_vertical_seam_follows_valley() {
+        // Zero-energy diagonal through an otherwise noisy image
+        let img = RgbaImage::from_fn(6, 6, |x, y| {
+            if x == y {
+                GREY
+            } else if (x * 7 + y * 3) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let mut grid = Grid::from_image(&img, &[]);
+        let seam = grid.find_vertical_seam();
+
+        assert!(seam.windows(2).all(|w| w[0].abs_diff(w[1]) <= 1));
+        grid.remove_vertical_seam(&seam);
+        assert_eq!(grid.width, 5);
+        assert_eq!(grid.pixels.len(), 30);
+    }
+
+    #[test]
+    fn test_seam_removal_keeps_energy_current() {
+        let img = RgbaImage::from_fn(12, 8, |x, y| {
+            let value = ((x * 37 + y * 91) % 251) as u8;
+            Rgba([value, value / 2, 255 - value, 255])
+        });
+        let protect = [CropRect { x: 3, y: 2, width: 2, height: 3 }];
+        let mut grid = Grid::from_image(&img, &protect);
+
+        for _ in 0..6 {
+            let seam = grid.find_vertical_seam();
+            grid.remove_vertical_seam(&seam);
+            let fresh = Grid::new(grid.width, grid.height, grid.pixels.clone(), grid.protect.clone());
+            assert_eq!(grid.energy, fresh.energy);
+        }
+    }
+
+    #[test]
+    fn test_seam_carving_validation() {
+        let img = half_checkerboard();
+        assert!(content_aware_resize(&img, &params(0, 6, vec![])).is_err());
+    }
+}
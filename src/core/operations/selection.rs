@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use crate::core::operations::region::{draw_region, validate_region};
+use crate::types::errors::AppError;
+use crate::types::operations::{CropRect, EraseParams, Point, Region, Selection, SelectionShape};
+
+/// Check that a selection can be rasterised
+pub fn validate_selection(selection: &Selection) -> Result<(), AppError> {
+    match &selection.shape {
+        SelectionShape::Rect { x, y, width, height } | SelectionShape::Ellipse { x, y, width, height } => {
+            if ![x, y, width, height].iter().all(|v| v.is_finite()) || *width <= 0.0 || *height <= 0.0 {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Selection size must be positive, got {}x{}", width, height),
+                });
+            }
+        }
+        SelectionShape::Lasso { points } => validate_region(&Region::Polygon { points: points.clone() })?,
+        SelectionShape::MagicWand { seed, .. } => {
+            if !(0.0..=1.0).contains(&seed.x) || !(0.0..=1.0).contains(&seed.y) {
+                return Err(AppError::InvalidOperation {
+                    details: format!("Magic wand seed must lie inside the image, got ({}, {})", seed.x, seed.y),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Select pixels similar to the seed, optionally only those connected to it
+fn magic_wand(source: &RgbaImage, seed: Point, tolerance: u8, contiguous: bool) -> GrayImage {
+    let (width, height) = source.dimensions();
+    let seed_x = ((seed.x * width as f32) as u32).min(width - 1);
+    let seed_y = ((seed.y * height as f32) as u32).min(height - 1);
+    let reference = *source.get_pixel(seed_x, seed_y);
+    let similar = |pixel: &Rgba<u8>| {
+        pixel.0.iter().zip(reference.0).all(|(a, b)| a.abs_diff(b) <= tolerance)
+    };
+
+    if !contiguous {
+        return GrayImage::from_fn(width, height, |x, y| {
+            Luma([if similar(source.get_pixel(x, y)) { 255 } else { 0 }])
+        });
+    }
+
+    let mut mask = GrayImage::new(width, height);
+    let mut queue = VecDeque::from([(seed_x, seed_y)]);
+    mask.put_pixel(seed_x, seed_y, Luma([255]));
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx < width && ny < height && mask.get_pixel(nx, ny)[0] == 0 && similar(source.get_pixel(nx, ny)) {
+                mask.put_pixel(nx, ny, Luma([255]));
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    mask
+}
+
+/// Rasterise a selection against an image
+///
+/// # Returns
+/// Mask of the image size with 255 for selected pixels and 0 elsewhere
+pub fn rasterize_selection(source: &RgbaImage, selection: &Selection) -> Result<GrayImage, AppError> {
+    validate_selection(selection)?;
+
+    let (width, height) = source.dimensions();
+    let (w, h) = (width as f32, height as f32);
+    // Pixels are selected when their centre lies inside the shape
+    let inside = |test: &dyn Fn(f32, f32) -> bool| {
+        GrayImage::from_fn(width, height, |x, y| {
+            Luma([if test((x as f32 + 0.5) / w, (y as f32 + 0.5) / h) { 255 } else { 0 }])
+        })
+    };
+
+    let mut mask = match &selection.shape {
+        SelectionShape::Rect { x, y, width, height } => {
+            inside(&|px, py| px >= *x && py >= *y && px < x + width && py < y + height)
+        }
+        SelectionShape::Ellipse { x, y, width, height } => {
+            let (cx, cy) = (x + width / 2.0, y + height / 2.0);
+            inside(&|px, py| ((px - cx) / (width / 2.0)).powi(2) + ((py - cy) / (height / 2.0)).powi(2) <= 1.0)
+        }
+        SelectionShape::Lasso { points } => {
+            let points = points.iter().map(|p| Point { x: p.x * w, y: p.y * h }).collect();
+            let mut mask = GrayImage::new(width, height);
+            draw_region(&mut mask, &Region::Polygon { points }, 255);
+            mask
+        }
+        SelectionShape::MagicWand { seed, tolerance, contiguous } => {
+            magic_wand(source, *seed, *tolerance, *contiguous)
+        }
+    };
+
+    if selection.invert {
+        mask.iter_mut().for_each(|value| *value = 255 - *value);
+    }
+
+    Ok(mask)
+}
+
+/// Bounding rectangle of the selected pixels, or None if nothing is selected
+pub fn selection_bounds(mask: &GrayImage) -> Option<CropRect> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, value) in mask.enumerate_pixels() {
+        if value[0] > 0 {
+            let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x, y));
+            bounds = Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+        }
+    }
+
+    bounds.map(|(x0, y0, x1, y1)| CropRect {
+        x: x0,
+        y: y0,
+        width: x1 - x0 + 1,
+        height: y1 - y0 + 1,
+    })
+}
+
+/// Copy the selected pixels, cropped to the selection bounds
+///
+/// # Returns
+/// RGBA image with unselected pixels made transparent, or error if nothing is selected
+pub fn copy_selection(img: &DynamicImage, mask: &GrayImage) -> Result<DynamicImage, AppError> {
+    let bounds = selection_bounds(mask).ok_or_else(|| AppError::InvalidOperation {
+        details: "Selection is empty".to_string(),
+    })?;
+
+    let rgba = img.to_rgba8();
+    let copied = RgbaImage::from_fn(bounds.width, bounds.height, |x, y| {
+        let (sx, sy) = (bounds.x + x, bounds.y + y);
+        let mut pixel = *rgba.get_pixel(sx, sy);
+        if mask.get_pixel(sx, sy)[0] == 0 {
+            pixel = Rgba([0, 0, 0, 0]);
+        }
+        pixel
+    });
+
+    Ok(DynamicImage::ImageRgba8(copied))
+}
+
+/// Make the selected pixels fully transparent
+pub fn erase(img: &DynamicImage, params: &EraseParams) -> Result<DynamicImage, AppError> {
+    let mut output = img.to_rgba8();
+    let mask = rasterize_selection(&output, &params.selection)?;
+
+    for (pixel, selected) in output.pixels_mut().zip(mask.pixels()) {
+        if selected[0] > 0 {
+            *pixel = Rgba([0, 0, 0, 0]);
+        }
+    }
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(shape: SelectionShape) -> Selection {
+        Selection { shape, invert: false }
+    }
+
+    fn count(mask: &GrayImage) -> usize {
+        mask.pixels().filter(|p| p[0] > 0).count()
+    }
+
+    #[test]
+    fn test_rect_and_ellipse_scale_with_image() {
+        let rect = select(SelectionShape::Rect { x: 0.25, y: 0.5, width: 0.5, height: 0.5 });
+        let small = rasterize_selection(&RgbaImage::new(8, 8), &rect).unwrap();
+        let large = rasterize_selection(&RgbaImage::new(80, 40), &rect).unwrap();
+
+        assert_eq!(selection_bounds(&small), Some(CropRect { x: 2, y: 4, width: 4, height: 4 }));
+        assert_eq!(selection_bounds(&large), Some(CropRect { x: 20, y: 20, width: 40, height: 20 }));
+
+        let ellipse = select(SelectionShape::Ellipse { x: 0.0, y: 0.0, width: 1.0, height: 1.0 });
+        let mask = rasterize_selection(&RgbaImage::new(20, 20), &ellipse).unwrap();
+        assert_eq!(mask.get_pixel(10, 10)[0], 255);
+        assert_eq!(mask.get_pixel(0, 0)[0], 0);
+        assert_eq!(selection_bounds(&mask), Some(CropRect { x: 0, y: 0, width: 20, height: 20 }));
+    }
+
+    #[test]
+    fn test_lasso_and_invert() {
+        let lasso = Selection {
+            shape: SelectionShape::Lasso {
+                points: vec![Point { x: 0.0, y: 0.0 }, Point { x: 0.5, y: 0.0 }, Point { x: 0.0, y: 0.5 }],
+            },
+            invert: true,
+        };
+        let mask = rasterize_selection(&RgbaImage::new(10, 10), &lasso).unwrap();
+
+        assert_eq!(mask.get_pixel(1, 1)[0], 0);
+        assert_eq!(mask.get_pixel(8, 8)[0], 255);
+    }
+
+    #[test]
+    fn test_degenerate_lasso_selects_nothing() {
+        // A click without a drag lands every point in the same pixel
+        let points = vec![Point { x: 0.51, y: 0.51 }, Point { x: 0.52, y: 0.52 }, Point { x: 0.53, y: 0.51 }];
+        let click = select(SelectionShape::Lasso { points });
+        let mask = rasterize_selection(&RgbaImage::new(10, 10), &click).unwrap();
+        assert_eq!(count(&mask), 0);
+        assert_eq!(selection_bounds(&mask), None);
+    }
+
+    #[test]
+    fn test_magic_wand() {
+        // Two white squares separated by a black column, with a near-white pixel
+        let source = RgbaImage::from_fn(9, 3, |x, y| match (x, y) {
+            (4, _) => Rgba([0, 0, 0, 255]),
+            (1, 1) => Rgba([240, 240, 240, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let wand = |tolerance, contiguous| {
+            let seed = Point { x: 0.0, y: 0.0 };
+            rasterize_selection(&source, &select(SelectionShape::MagicWand { seed, tolerance, contiguous })).unwrap()
+        };
+
+        assert_eq!(count(&wand(0, true)), 11);
+        assert_eq!(count(&wand(20, true)), 12);
+        assert_eq!(count(&wand(20, false)), 24);
+    }
+
+    #[test]
+    fn test_copy_and_erase_selection() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([10, 20, 30, 255])));
+        let selection = select(SelectionShape::Ellipse { x: 0.2, y: 0.2, width: 0.4, height: 0.4 });
+        let mask = rasterize_selection(&img.to_rgba8(), &selection).unwrap();
+
+        let copied = copy_selection(&img, &mask).unwrap().to_rgba8();
+        assert_eq!(copied.dimensions(), (4, 4));
+        assert_eq!(*copied.get_pixel(2, 2), Rgba([10, 20, 30, 255]));
+
+        let erased = erase(&img, &EraseParams { selection }).unwrap().to_rgba8();
+        assert_eq!(erased.get_pixel(4, 4)[3], 0);
+        assert_eq!(erased.get_pixel(9, 9)[3], 255);
+
+        assert!(copy_selection(&img, &GrayImage::new(10, 10)).is_err());
+    }
+}
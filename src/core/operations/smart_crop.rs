@@ -0,0 +1,263 @@
+use image::{DynamicImage, GenericImageView, GrayImage, imageops::FilterType};
+use imageproc::gradients::sobel_gradients;
+use crate::core::operations::crop::{crop_image, crop_with_aspect_ratio};
+use crate::types::errors::AppError;
+use crate::types::operations::{Anchor, CropRect, SmartCropParams};
+use crate::utils::preview::{resize_to_fit, validate_dimensions};
+
+/// Long edge of the working copy used for scoring
+const ANALYSIS_SIZE: u32 = 256;
+
+/// Maximum number of window positions tried along each axis
+const MAX_CANDIDATES: u32 = 32;
+
+const EDGE_WEIGHT: f64 = 1.0;
+const ENTROPY_WEIGHT: f64 = 0.5;
+const SKIN_WEIGHT: f64 = 1.5;
+
+/// Summed-area table for constant-time window sums
+struct SummedArea {
+    stride: usize,
+    sums: Vec<f64>,
+}
+
+impl SummedArea {
+    fn new(width: u32, height: u32, value: impl Fn(u32, u32) -> f64) -> Self {
+        let stride = width as usize + 1;
+        let mut sums = vec![0.0; stride * (height as usize + 1)];
+        for y in 0..height {
+            let mut row = 0.0;
+            for x in 0..width {
+                row += value(x, y);
+                let i = (y as usize + 1) * stride + x as usize + 1;
+                sums[i] = sums[i - stride] + row;
+            }
+        }
+        Self { stride, sums }
+    }
+
+    /// Mean value over a window
+    fn mean(&self, x: u32, y: u32, width: u32, height: u32) -> f64 {
+        let at = |x: u32, y: u32| self.sums[y as usize * self.stride + x as usize];
+        let (x1, y1) = (x + width, y + height);
+        (at(x1, y1) - at(x, y1) - at(x1, y) + at(x, y)) / (width as f64 * height as f64)
+    }
+}
+
+/// Simple RGB skin-tone classifier
+fn is_skin(r: u8, g: u8, b: u8) -> bool {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    r > 95 && g > 40 && b > 20 && max - min > 15 && r.abs_diff(g) > 15 && r > g && r > b
+}
+
+/// Luminance entropy of a window, normalised to 0..1
+fn entropy(luma: &GrayImage, x: u32, y: u32, width: u32, height: u32) -> f64 {
+    let mut histogram = [0u32; 32];
+    for py in y..y + height {
+        for px in x..x + width {
+            histogram[(luma.get_pixel(px, py)[0] >> 3) as usize] += 1;
+        }
+    }
+
+    let total = (width * height) as f64;
+    let bits: f64 = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+
+    bits / 5.0
+}
+
+/// Window offsets to try along one axis
+fn positions(slack: u32) -> Vec<u32> {
+    let step = (slack / MAX_CANDIDATES).max(1) as usize;
+    let mut positions: Vec<u32> = (0..=slack).step_by(step).collect();
+    if positions.last() != Some(&slack) {
+        positions.push(slack);
+    }
+    positions
+}
+
+/// Pick the most interesting window of an aspect ratio
+///
+/// Candidate windows are scored on a downscaled copy by edge density,
+/// luminance entropy and skin-tone coverage. Ties keep the centred window.
+///
+/// # Returns
+/// CropRect of the largest window with `desired_aspect` in original pixels
+pub fn smart_crop_rect(img: &DynamicImage, desired_aspect: f32) -> CropRect {
+    let (width, height) = img.dimensions();
+    let size = crop_with_aspect_ratio(width, height, desired_aspect, Anchor::Center);
+
+    let working = resize_to_fit(img, ANALYSIS_SIZE, ANALYSIS_SIZE);
+    let (work_width, work_height) = working.dimensions();
+    let scale_x = work_width as f64 / width as f64;
+    let scale_y = work_height as f64 / height as f64;
+    let window_width = ((size.width as f64 * scale_x).round() as u32).clamp(1, work_width);
+    let window_height = ((size.height as f64 * scale_y).round() as u32).clamp(1, work_height);
+
+    let luma = working.to_luma8();
+    let rgb = working.to_rgb8();
+    let gradients = sobel_gradients(&luma);
+    let max_gradient = gradients.pixels().map(|p| p[0]).max().unwrap_or(0).max(1) as f64;
+
+    let edges = SummedArea::new(work_width, work_height, |x, y| {
+        gradients.get_pixel(x, y)[0] as f64 / max_gradient
+    });
+    let skin = SummedArea::new(work_width, work_height, |x, y| {
+        let [r, g, b] = rgb.get_pixel(x, y).0;
+        if is_skin(r, g, b) { 1.0 } else { 0.0 }
+    });
+
+    let score = |x: u32, y: u32| {
+        EDGE_WEIGHT * edges.mean(x, y, window_width, window_height)
+            + ENTROPY_WEIGHT * entropy(&luma, x, y, window_width, window_height)
+            + SKIN_WEIGHT * skin.mean(x, y, window_width, window_height)
+    };
+
+    let centre = ((work_width - window_width) / 2, (work_height - window_height) / 2);
+    let mut best = (score(centre.0, centre.1), centre);
+    for &y in &positions(work_height - window_height) {
+        for &x in &positions(work_width - window_width) {
+            let candidate = score(x, y);
+            if candidate > best.0 {
+                best = (candidate, (x, y));
+            }
+        }
+    }
+
+    let (best_x, best_y) = best.1;
+    CropRect {
+        x: ((best_x as f64 / scale_x).round() as u32).min(width - size.width),
+        y: ((best_y as f64 / scale_y).round() as u32).min(height - size.height),
+        width: size.width,
+        height: size.height,
+    }
+}
+
+/// Aspect ratio requested by smart crop parameters
+fn target_aspect(params: &SmartCropParams) -> Result<f32, AppError> {
+    let aspect = match (params.aspect, params.width, params.height) {
+        (Some(aspect), _, _) => aspect.ratio(),
+        (None, Some(width), Some(height)) if width > 0 && height > 0 => width as f32 / height as f32,
+        _ => {
+            return Err(AppError::InvalidOperation {
+                details: "Smart crop needs an aspect ratio or a width and height".to_string(),
+            });
+        }
+    };
+
+    if !aspect.is_finite() || aspect <= 0.0 {
+        return Err(AppError::InvalidOperation {
+            details: format!("Aspect ratio must be positive, got {}", aspect),
+        });
+    }
+
+    Ok(aspect)
+}
+
+/// Crop window for smart crop parameters, detecting it unless already resolved
+fn window(img: &DynamicImage, params: &SmartCropParams) -> Result<CropRect, AppError> {
+    match &params.rect {
+        Some(rect) => Ok(rect.clone()),
+        None => Ok(smart_crop_rect(img, target_aspect(params)?)),
+    }
+}
+
+/// Fill in the crop window for smart crop parameters
+pub fn resolve(img: &DynamicImage, params: &SmartCropParams) -> Result<SmartCropParams, AppError> {
+    Ok(SmartCropParams {
+        rect: Some(window(img, params)?),
+        ..params.clone()
+    })
+}
+
+/// Crop to the most interesting window, optionally resizing to a target size
+///
+/// # Parameters
+/// - `img`: Input image
+/// - `params`: Target aspect ratio or size, and a previously resolved window
+///
+/// # Returns
+/// Cropped image or error if the parameters are invalid
+pub fn smart_crop(img: &DynamicImage, params: &SmartCropParams) -> Result<DynamicImage, AppError> {
+    let cropped = crop_image(img, &window(img, params)?)?;
+
+    match (params.width, params.height) {
+        (Some(width), Some(height)) => {
+            validate_dimensions(width, height).map_err(|details| AppError::InvalidOperation { details })?;
+            Ok(cropped.resize_exact(width, height, FilterType::Lanczos3))
+        }
+        _ => Ok(cropped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::AspectRatio;
+    use image::{Rgb, RgbImage};
+
+    fn params(aspect: Option<AspectRatio>, width: Option<u32>, height: Option<u32>) -> SmartCropParams {
+        SmartCropParams { aspect, width, height, rect: None }
+    }
+
+    #[test]
+    fn test_smart_crop_prefers_detail() {
+        // Flat grey with a checkerboard on the right third
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(300, 100, |x, y| {
+            if x >= 200 && (x / 5 + y / 5) % 2 == 0 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([200, 200, 200])
+            }
+        }));
+
+        let rect = smart_crop_rect(&img, 1.0);
+        assert_eq!((rect.width, rect.height), (100, 100));
+        assert!(rect.x >= 190, "x was {}", rect.x);
+    }
+
+    #[test]
+    fn test_smart_crop_prefers_skin() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(100, 300, |_, y| {
+            if y < 80 {
+                Rgb([224, 172, 140])
+            } else {
+                Rgb([40, 90, 160])
+            }
+        }));
+
+        let rect = smart_crop_rect(&img, 1.0);
+        assert_eq!((rect.width, rect.height), (100, 100));
+        assert!(rect.y <= 10, "y was {}", rect.y);
+    }
+
+    #[test]
+    fn test_smart_crop_flat_image_stays_centred() {
+        let img = DynamicImage::new_rgb8(200, 100);
+        let rect = smart_crop_rect(&img, 1.0);
+        assert_eq!(rect, CropRect { x: 50, y: 0, width: 100, height: 100 });
+    }
+
+    #[test]
+    fn test_smart_crop_to_size() {
+        let img = DynamicImage::new_rgb8(200, 100);
+
+        let resolved = resolve(&img, &params(None, Some(40), Some(30))).unwrap();
+        assert!(resolved.rect.is_some());
+
+        let result = smart_crop(&img, &resolved).unwrap();
+        assert_eq!(result.dimensions(), (40, 30));
+
+        let result = smart_crop(&img, &params(Some(AspectRatio::Widescreen), None, None)).unwrap();
+        assert_eq!(result.dimensions(), (177, 100));
+
+        assert!(smart_crop(&img, &params(None, Some(40), None)).is_err());
+    }
+}
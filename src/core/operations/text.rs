@@ -0,0 +1,491 @@
+use ab_glyph::{Font, FontArc, FontVec, PxScale, ScaleFont};
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage, imageops};
+use imageproc::drawing::{draw_text_mut, text_size};
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::morphology::{Mask, grayscale_dilate};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::core::operations::selection::selection_bounds;
+use crate::types::errors::AppError;
+use crate::types::operations::{Color, Point, TextAlign, TextParams};
+use crate::utils::preview::validate_dimensions;
+
+/// Environment variable naming a font directory searched before the system ones
+pub const FONT_DIR_ENV: &str = "IMG_EDITOR_FONT_DIR";
+
+/// Subdirectory levels searched below each font directory
+const MAX_FONT_DEPTH: usize = 4;
+
+/// Longest text accepted, in characters
+const MAX_TEXT_LENGTH: usize = 10_000;
+
+/// Directories searched for font files, in priority order
+///
+/// The directory named by `IMG_EDITOR_FONT_DIR` comes first, then a `fonts`
+/// directory next to the executable, then the platform font directories.
+pub fn font_directories() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os(FONT_DIR_ENV) {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Ok(exe) = std::env::current_exe()
+        && let Some(parent) = exe.parent()
+    {
+        dirs.push(parent.join("fonts"));
+    }
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.extend(home.map(|home| home.join("Library/Fonts")));
+    } else {
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+        if let Some(home) = home {
+            dirs.push(home.join(".local/share/fonts"));
+            dirs.push(home.join(".fonts"));
+        }
+    }
+
+    dirs
+}
+
+/// Find `name` in `dir` or its subdirectories
+fn find_in(dir: &Path, name: &str, depth: usize) -> Option<PathBuf> {
+    let candidate = dir.join(name);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    if depth == 0 {
+        return None;
+    }
+
+    let mut subdirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    subdirs.sort();
+    subdirs.iter().find_map(|subdir| find_in(subdir, name, depth - 1))
+}
+
+/// Point `IMG_EDITOR_FONT_DIR` at the fonts bundled with the tests
+#[cfg(test)]
+pub(crate) fn use_fixture_fonts() {
+    static FIXTURE_FONTS: std::sync::Once = std::sync::Once::new();
+    FIXTURE_FONTS.call_once(|| {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fonts");
+        // SAFETY: set once before any test reads it, and tests only read it through std
+        unsafe { std::env::set_var(FONT_DIR_ENV, dir) };
+    });
+}
+
+/// Load a font by file name, caching parsed fonts for later operations
+///
+/// Only bare `.ttf` or `.otf` file names are accepted so that operations
+/// cannot read arbitrary paths.
+pub fn load_font(name: &str) -> Result<FontArc, AppError> {
+    static FONTS: OnceLock<Mutex<HashMap<String, FontArc>>> = OnceLock::new();
+    let cache = FONTS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(font) = cache.lock().unwrap().get(name) {
+        return Ok(font.clone());
+    }
+
+    let path = Path::new(name);
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+    if path.file_name().and_then(|file| file.to_str()) != Some(name)
+        || !matches!(extension.as_deref(), Some("ttf" | "otf"))
+    {
+        return Err(AppError::InvalidOperation {
+            details: format!("Font must be a .ttf or .otf file name, got {:?}", name),
+        });
+    }
+
+    let path = font_directories()
+        .iter()
+        .find_map(|dir| find_in(dir, name, MAX_FONT_DEPTH))
+        .ok_or_else(|| AppError::InvalidOperation {
+            details: format!("Font not found: {}", name),
+        })?;
+    let data = std::fs::read(&path).map_err(|e| AppError::FileAccessDenied {
+        path: format!("{}: {}", path.display(), e),
+    })?;
+    let font = FontVec::try_from_vec(data).map_err(|_| AppError::InvalidOperation {
+        details: format!("Unreadable font file: {}", name),
+    })?;
+
+    let font = FontArc::new(font);
+    cache.lock().unwrap().insert(name.to_string(), font.clone());
+    Ok(font)
+}
+
+fn validate(params: &TextParams) -> Result<(), AppError> {
+    if params.text.trim().is_empty() {
+        return Err(AppError::InvalidOperation {
+            details: "Text must not be empty".to_string(),
+        });
+    }
+    let length = params.text.chars().count();
+    if length > MAX_TEXT_LENGTH {
+        return Err(AppError::InvalidOperation {
+            details: format!("Text must be at most {} characters, got {}", MAX_TEXT_LENGTH, length),
+        });
+    }
+    if !(params.size >= 1.0 && params.size <= 2000.0) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Font size must be between 1 and 2000, got {}", params.size),
+        });
+    }
+    let position = params.position;
+    if !position.x.is_finite() || !position.y.is_finite() || !params.rotation.is_finite() {
+        return Err(AppError::InvalidOperation {
+            details: "Text position and rotation must be finite".to_string(),
+        });
+    }
+    if let Some(stroke) = &params.stroke
+        && !(stroke.width >= 1.0 && stroke.width <= 50.0)
+    {
+        return Err(AppError::InvalidOperation {
+            details: format!("Stroke width must be between 1 and 50, got {}", stroke.width),
+        });
+    }
+    if let Some(shadow) = &params.shadow {
+        if !shadow.offset_x.is_finite() || !shadow.offset_y.is_finite() {
+            return Err(AppError::InvalidOperation {
+                details: "Shadow offset must be finite".to_string(),
+            });
+        }
+        if !(0.0..=50.0).contains(&shadow.blur) {
+            return Err(AppError::InvalidOperation {
+                details: format!("Shadow blur must be between 0 and 50, got {}", shadow.blur),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Coverage of the laid-out text block, unrotated
+struct TextBlock {
+    fill: GrayImage,
+    stroke: Option<GrayImage>,
+    shadow: Option<GrayImage>,
+    /// Point of the block placed at `TextParams::position`
+    anchor: Point,
+}
+
+/// Lay out and rasterise the text lines with their outline and shadow
+///
+/// # Returns
+/// The block, or error if it would exceed the maximum image dimensions
+fn render_block(font: &FontArc, params: &TextParams) -> Result<TextBlock, AppError> {
+    let scale = PxScale::from(params.size);
+    let scaled = font.as_scaled(scale);
+    let line_height = scaled.height() + scaled.line_gap();
+
+    let lines: Vec<&str> = params.text.lines().collect();
+    let widths: Vec<u32> = lines.iter().map(|line| text_size(scale, font, line).0).collect();
+    let block_width = widths.iter().copied().max().unwrap_or(0);
+    let block_height = (line_height * lines.len() as f32).ceil() as u32;
+
+    // Room for glyphs overhanging their advance, the outline and the blurred shadow
+    let stroke_width = params.stroke.map_or(0.0, |stroke| stroke.width.round());
+    let blur = params.shadow.map_or(0.0, |shadow| shadow.blur);
+    let pad = (params.size / 4.0 + stroke_width + blur * 3.0).ceil() as u32;
+
+    // The outline and shadow copy the block, so bound it before allocating any of them
+    let fill_width = block_width.saturating_add(pad * 2);
+    let fill_height = block_height.saturating_add(pad * 2);
+    validate_dimensions(fill_width, fill_height)
+        .map_err(|details| AppError::InvalidOperation { details })?;
+
+    let mut fill = GrayImage::new(fill_width, fill_height);
+    for (i, (line, width)) in lines.iter().zip(&widths).enumerate() {
+        let indent = match params.align {
+            TextAlign::Left => 0,
+            TextAlign::Center => (block_width - width) / 2,
+            TextAlign::Right => block_width - width,
+        };
+        let y = pad as f32 + line_height * i as f32;
+        let x = (pad + indent) as i32;
+        draw_text_mut(&mut fill, Luma([255]), x, y.round() as i32, scale, font, line);
+    }
+
+    let stroke = params
+        .stroke
+        .map(|stroke| grayscale_dilate(&fill, &Mask::disk(stroke.width.round() as u8)));
+    let shadow = params.shadow.map(|shadow| {
+        let outline = stroke.as_ref().unwrap_or(&fill);
+        if shadow.blur > 0.0 {
+            gaussian_blur_f32(outline, shadow.blur)
+        } else {
+            outline.clone()
+        }
+    });
+
+    let anchor_x = match params.align {
+        TextAlign::Left => 0.0,
+        TextAlign::Center => block_width as f32 / 2.0,
+        TextAlign::Right => block_width as f32,
+    };
+
+    Ok(TextBlock {
+        fill,
+        stroke,
+        shadow,
+        anchor: Point { x: pad as f32 + anchor_x, y: pad as f32 },
+    })
+}
+
+/// Bilinearly sample coverage at a pixel-centre position, zero outside the image
+fn sample(coverage: &GrayImage, x: f32, y: f32) -> f32 {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let value = |px: f32, py: f32| {
+        if px < 0.0 || py < 0.0 || px >= coverage.width() as f32 || py >= coverage.height() as f32 {
+            0.0
+        } else {
+            coverage.get_pixel(px as u32, py as u32)[0] as f32 / 255.0
+        }
+    };
+
+    let top = value(x0, y0) * (1.0 - fx) + value(x0 + 1.0, y0) * fx;
+    let bottom = value(x0, y0 + 1.0) * (1.0 - fx) + value(x0 + 1.0, y0 + 1.0) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Paint `color` over a pixel with source-over alpha
+pub fn blend_over(pixel: &mut Rgba<u8>, color: Color, coverage: f32) {
+    let alpha_s = coverage * color.a as f32 / 255.0;
+    if alpha_s <= 0.0 {
+        return;
+    }
+
+    let alpha_b = pixel[3] as f32 / 255.0;
+    let alpha_o = alpha_s + alpha_b * (1.0 - alpha_s);
+    let source = [color.r, color.g, color.b];
+    for (value, source) in pixel.0.iter_mut().zip(source) {
+        let mixed = (source as f32 * alpha_s + *value as f32 * alpha_b * (1.0 - alpha_s)) / alpha_o;
+        *value = mixed.round().clamp(0.0, 255.0) as u8;
+    }
+    pixel[3] = (alpha_o * 255.0).round() as u8;
+}
+
+/// Paint coverage rotated about `anchor` and placed at `position`
+fn draw_coverage(
+    output: &mut RgbaImage,
+    coverage: &GrayImage,
+    anchor: Point,
+    position: Point,
+    rotation: f32,
+    color: Color,
+) {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let to_image = |x: f32, y: f32| {
+        let (dx, dy) = (x - anchor.x, y - anchor.y);
+        (position.x + dx * cos - dy * sin, position.y + dx * sin + dy * cos)
+    };
+
+    let (w, h) = (coverage.width() as f32, coverage.height() as f32);
+    let corners = [to_image(0.0, 0.0), to_image(w, 0.0), to_image(0.0, h), to_image(w, h)];
+    let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min).floor().max(0.0);
+    let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min).floor().max(0.0);
+    let max_x = corners.iter().map(|c| c.0).fold(f32::NEG_INFINITY, f32::max).ceil();
+    let max_y = corners.iter().map(|c| c.1).fold(f32::NEG_INFINITY, f32::max).ceil();
+    let max_x = max_x.min(output.width() as f32) as u32;
+    let max_y = max_y.min(output.height() as f32) as u32;
+
+    for y in min_y as u32..max_y {
+        for x in min_x as u32..max_x {
+            let (dx, dy) = (x as f32 + 0.5 - position.x, y as f32 + 0.5 - position.y);
+            let bx = anchor.x + dx * cos + dy * sin;
+            let by = anchor.y - dx * sin + dy * cos;
+            let value = sample(coverage, bx, by);
+            if value > 0.0 {
+                blend_over(output.get_pixel_mut(x, y), color, value);
+            }
+        }
+    }
+}
+
+/// Draw the shadow, then the outline, then the fill of a text block
+fn draw_block(
+    output: &mut RgbaImage,
+    block: &TextBlock,
+    params: &TextParams,
+    position: Point,
+    rotation: f32,
+) {
+    let mut draw = |coverage: &GrayImage, position: Point, color: Color| {
+        draw_coverage(output, coverage, block.anchor, position, rotation, color);
+    };
+    if let (Some(shadow), Some(coverage)) = (&params.shadow, &block.shadow) {
+        let offset = Point {
+            x: position.x + shadow.offset_x,
+            y: position.y + shadow.offset_y,
+        };
+        draw(coverage, offset, shadow.color);
+    }
+    if let (Some(stroke), Some(coverage)) = (&params.stroke, &block.stroke) {
+        draw(coverage, position, stroke.color);
+    }
+    draw(&block.fill, position, params.color);
+}
+
+/// Draw text onto an image
+///
+/// Lines are split on newlines and drawn with the shadow first, then the
+/// outline, then the fill, all rotated together around `position`.
+pub fn text(img: &DynamicImage, params: &TextParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+    let font = load_font(&params.font)?;
+    let block = render_block(&font, params)?;
+
+    let mut output = img.to_rgba8();
+    draw_block(&mut output, &block, params, params.position, params.rotation);
+
+    Ok(DynamicImage::ImageRgba8(output))
+}
+
+/// Render text onto a transparent image cropped to the drawn pixels
+///
+/// `position` and `rotation` are ignored.
+pub fn text_image(params: &TextParams) -> Result<RgbaImage, AppError> {
+    validate(params)?;
+    let font = load_font(&params.font)?;
+    let block = render_block(&font, params)?;
+
+    let mut output = RgbaImage::new(block.fill.width(), block.fill.height());
+    draw_block(&mut output, &block, params, block.anchor, 0.0);
+
+    let alpha = GrayImage::from_fn(output.width(), output.height(), |x, y| {
+        Luma([output.get_pixel(x, y)[3]])
+    });
+    let bounds = selection_bounds(&alpha).ok_or_else(|| AppError::InvalidOperation {
+        details: "Text has no visible glyphs".to_string(),
+    })?;
+
+    Ok(imageops::crop_imm(&output, bounds.x, bounds.y, bounds.width, bounds.height).to_image())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::operations::{TextShadow, TextStroke};
+
+    /// Bundled with the tests, see `tests/fixtures/fonts`
+    const TEST_FONT: &str = "FixtureSans-Regular.ttf";
+
+    const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+    const RED: Color = Color { r: 255, g: 0, b: 0, a: 255 };
+
+    fn params(text: &str) -> TextParams {
+        use_fixture_fonts();
+        TextParams {
+            text: text.to_string(),
+            font: TEST_FONT.to_string(),
+            size: 24.0,
+            color: WHITE,
+            position: Point { x: 10.0, y: 10.0 },
+            align: TextAlign::Left,
+            rotation: 0.0,
+            stroke: None,
+            shadow: None,
+        }
+    }
+
+    /// Bounding box of pixels that differ from black
+    fn ink_bounds(img: &DynamicImage) -> Option<(u32, u32, u32, u32)> {
+        let rgba = img.to_rgba8();
+        let inked: Vec<(u32, u32)> = rgba
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[0] > 0 || p[1] > 0 || p[2] > 0)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let min_x = inked.iter().map(|p| p.0).min()?;
+        let min_y = inked.iter().map(|p| p.1).min()?;
+        let max_x = inked.iter().map(|p| p.0).max()?;
+        let max_y = inked.iter().map(|p| p.1).max()?;
+        Some((min_x, min_y, max_x, max_y))
+    }
+
+    fn black(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])))
+    }
+
+    #[test]
+    fn test_rejects_invalid_text_params() {
+        let img = black(10, 10);
+        assert!(text(&img, &params("")).is_err());
+        assert!(text(&img, &TextParams { size: 0.0, ..params("a") }).is_err());
+
+        let stroke = Some(TextStroke { width: 0.0, color: RED });
+        assert!(text(&img, &TextParams { stroke, ..params("a") }).is_err());
+
+        // Overlong text and blocks too big to allocate fail before rendering
+        assert!(text(&img, &params(&"a".repeat(MAX_TEXT_LENGTH + 1))).is_err());
+        let tall = TextParams { size: 2000.0, ..params(&"a\n".repeat(20)) };
+        let error = text(&img, &tall).unwrap_err();
+        assert!(error.to_string().contains("exceed"), "{}", error);
+
+        for font in ["../DejaVuSans.ttf", "/etc/passwd", "fonts/DejaVuSans.ttf", "DejaVuSans"] {
+            assert!(matches!(load_font(font), Err(AppError::InvalidOperation { .. })), "{}", font);
+        }
+        assert!(load_font("NoSuchFont.ttf").is_err());
+    }
+
+    #[test]
+    fn test_text_alignment_and_lines() {
+        let img = black(200, 120);
+
+        let left = text(&img, &params("Hello")).unwrap();
+        let (min_x, min_y, max_x, _) = ink_bounds(&left).unwrap();
+        assert!((10..=13).contains(&min_x) && (10..=20).contains(&min_y));
+
+        let position = Point { x: 150.0, y: 10.0 };
+        let right = TextParams { align: TextAlign::Right, position, ..params("Hello") };
+        let (_, _, right_edge, _) = ink_bounds(&text(&img, &right).unwrap()).unwrap();
+        assert!((146..=150).contains(&right_edge), "right edge {}", right_edge);
+        assert!(max_x > min_x);
+
+        let (_, _, _, one_line) = ink_bounds(&left).unwrap();
+        let two_lines = text(&img, &params("Hello\nworld")).unwrap();
+        let (_, _, _, two_lines) = ink_bounds(&two_lines).unwrap();
+        assert!(two_lines > one_line + 20);
+    }
+
+    #[test]
+    fn test_stroke_shadow_and_rotation() {
+        let img = black(200, 200);
+        let plain = ink_bounds(&text(&img, &params("I")).unwrap()).unwrap();
+
+        let stroke = Some(TextStroke { width: 3.0, color: RED });
+        let stroked = text(&img, &TextParams { stroke, ..params("I") }).unwrap();
+        let outlined = ink_bounds(&stroked).unwrap();
+        assert!(outlined.0 + 3 <= plain.0 && outlined.2 >= plain.2 + 3);
+        let middle = (plain.1 + plain.3) / 2;
+        assert_eq!(*stroked.to_rgba8().get_pixel(outlined.0 + 1, middle), Rgba([255, 0, 0, 255]));
+
+        let shadow = Some(TextShadow { offset_x: 20.0, offset_y: 0.0, blur: 0.0, color: RED });
+        let shadowed = text(&img, &TextParams { shadow, ..params("I") }).unwrap();
+        let shadowed = ink_bounds(&shadowed).unwrap();
+        assert_eq!(shadowed.2, plain.2 + 20);
+
+        // A quarter turn lays the tall letter on its side
+        let position = Point { x: 100.0, y: 100.0 };
+        let rotated = TextParams { rotation: 90.0, position, ..params("I") };
+        let (min_x, min_y, max_x, max_y) = ink_bounds(&text(&img, &rotated).unwrap()).unwrap();
+        assert!(max_x - min_x > max_y - min_y);
+        assert!(max_x < 100);
+    }
+}
@@ -0,0 +1,295 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage, imageops};
+use crate::core::compositor::{CompositeLayer, composite};
+use crate::core::operations::canvas::anchor_offset;
+use crate::core::operations::text::text_image;
+use crate::types::errors::AppError;
+use crate::types::layers::LayerProperties;
+use crate::types::operations::{
+    Point, TextAlign, TextParams, WatermarkMark, WatermarkParams, WatermarkPlacement,
+};
+use crate::utils::base64::{decode_image, encode_image};
+use crate::utils::preview::{validate_dimensions, validate_file_size, validate_format};
+
+fn validate(params: &WatermarkParams) -> Result<(), AppError> {
+    if !(0.0..=1.0).contains(&params.opacity) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Watermark opacity must be between 0 and 1, got {}", params.opacity),
+        });
+    }
+
+    let scale = match &params.mark {
+        WatermarkMark::Image { scale, .. }
+        | WatermarkMark::EmbeddedImage { scale, .. }
+        | WatermarkMark::Text { scale, .. } => *scale,
+    };
+    if !(scale > 0.0 && scale <= 1.0) {
+        return Err(AppError::InvalidOperation {
+            details: format!("Watermark scale must be between 0 and 1, got {}", scale),
+        });
+    }
+
+    match params.placement {
+        WatermarkPlacement::Anchored { margin, .. } if !(0.0..0.5).contains(&margin) => {
+            Err(AppError::InvalidOperation {
+                details: format!("Watermark margin must be between 0 and 0.5, got {}", margin),
+            })
+        }
+        WatermarkPlacement::Tiled { spacing, .. } if !(0.0..=1.0).contains(&spacing) => {
+            Err(AppError::InvalidOperation {
+                details: format!("Watermark spacing must be between 0 and 1, got {}", spacing),
+            })
+        }
+        WatermarkPlacement::Tiled { angle, .. } if !angle.is_finite() => {
+            Err(AppError::InvalidOperation {
+                details: "Watermark angle must be finite".to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn load_logo(path: &str) -> Result<DynamicImage, AppError> {
+    validate_format(path)?;
+    validate_file_size(path)?;
+    image::open(path).map_err(|e| AppError::ImageLoadError(format!("{}: {}", path, e)))
+}
+
+/// Embed a logo file's image data in the parameters
+///
+/// History records watermarks this way, so that replaying them neither
+/// reads the file again nor depends on it being unchanged.
+pub fn resolve(params: &WatermarkParams) -> Result<WatermarkParams, AppError> {
+    let WatermarkMark::Image { path, scale } = &params.mark else {
+        return Ok(params.clone());
+    };
+
+    let data = encode_image(&load_logo(path)?)?;
+    let mark = WatermarkMark::EmbeddedImage { data, scale: *scale };
+    Ok(WatermarkParams { mark, ..params.clone() })
+}
+
+/// Load or render the mark at its final size
+fn render_mark(mark: &WatermarkMark, short_edge: f32) -> Result<RgbaImage, AppError> {
+    let (logo, scale) = match mark {
+        WatermarkMark::Image { path, scale } => (load_logo(path)?, scale),
+        WatermarkMark::EmbeddedImage { data, scale } => (decode_image(data)?, scale),
+        WatermarkMark::Text { text, font, color, scale } => {
+            return text_image(&TextParams {
+                text: text.clone(),
+                font: font.clone(),
+                size: (short_edge * scale).max(1.0),
+                color: *color,
+                position: Point { x: 0.0, y: 0.0 },
+                align: TextAlign::Center,
+                rotation: 0.0,
+                stroke: None,
+                shadow: None,
+            });
+        }
+    };
+
+    let (width, height) = logo.dimensions();
+    validate_dimensions(width, height).map_err(|details| AppError::InvalidOperation { details })?;
+    let ratio = short_edge * scale / width.max(height) as f32;
+    let width = ((width as f32 * ratio).round() as u32).max(1);
+    let height = ((height as f32 * ratio).round() as u32).max(1);
+    Ok(imageops::resize(&logo.to_rgba8(), width, height, imageops::FilterType::Lanczos3))
+}
+
+/// Bilinearly sample a pixel-centre position, transparent outside the image
+///
+/// Colours are weighted by alpha so transparent pixels do not darken edges.
+fn sample(image: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let mut sum = [0.0f32; 4];
+    let taps = [
+        (x0, y0, (1.0 - fx) * (1.0 - fy)),
+        (x0 + 1.0, y0, fx * (1.0 - fy)),
+        (x0, y0 + 1.0, (1.0 - fx) * fy),
+        (x0 + 1.0, y0 + 1.0, fx * fy),
+    ];
+    for (px, py, weight) in taps {
+        if px < 0.0 || py < 0.0 || px >= image.width() as f32 || py >= image.height() as f32 {
+            continue;
+        }
+        let pixel = image.get_pixel(px as u32, py as u32);
+        let alpha = pixel[3] as f32 * weight;
+        for c in 0..3 {
+            sum[c] += pixel[c] as f32 * alpha;
+        }
+        sum[3] += alpha;
+    }
+
+    if sum[3] <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |c: usize| (sum[c] / sum[3]).round().clamp(0.0, 255.0) as u8;
+    Rgba([channel(0), channel(1), channel(2), sum[3].round().clamp(0.0, 255.0) as u8])
+}
+
+/// Repeat the mark over a canvas in rotated rows, offsetting every other row by half a mark
+fn tile(mark: &RgbaImage, width: u32, height: u32, gap: f32, angle: f32) -> RgbaImage {
+    let cell_width = mark.width() as f32 + gap;
+    let cell_height = mark.height() as f32 + gap;
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        // Position in the unrotated tiling, centred on the image so the pattern is symmetric
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        let u = dx * cos + dy * sin + cell_width / 2.0;
+        let v = -dx * sin + dy * cos + cell_height / 2.0;
+
+        let row = (v / cell_height).floor() as i64;
+        let shift = if row.rem_euclid(2) == 1 { cell_width / 2.0 } else { 0.0 };
+        let (u, v) = ((u + shift).rem_euclid(cell_width), v.rem_euclid(cell_height));
+        sample(mark, u, v)
+    })
+}
+
+/// Overlay a logo or text watermark
+pub fn watermark(img: &DynamicImage, params: &WatermarkParams) -> Result<DynamicImage, AppError> {
+    validate(params)?;
+    let (width, height) = img.dimensions();
+    let short_edge = width.min(height) as f32;
+    let mark = render_mark(&params.mark, short_edge)?;
+
+    let mut properties = LayerProperties {
+        opacity: params.opacity,
+        blend_mode: params.blend_mode,
+        ..LayerProperties::named("watermark")
+    };
+    let overlay = match params.placement {
+        WatermarkPlacement::Anchored { anchor, margin } => {
+            let margin = (short_edge * margin).round() as u32;
+            let area = (width.saturating_sub(margin * 2), height.saturating_sub(margin * 2));
+            let (x, y) = anchor_offset(anchor, area, mark.dimensions());
+            properties.x = x + margin as i64;
+            properties.y = y + margin as i64;
+            mark
+        }
+        WatermarkPlacement::Tiled { spacing, angle } => {
+            tile(&mark, width, height, short_edge * spacing, angle)
+        }
+    };
+
+    let overlay = DynamicImage::ImageRgba8(overlay);
+    let base = LayerProperties::named("image");
+    Ok(composite(width, height, &[
+        CompositeLayer { image: img, properties: &base },
+        CompositeLayer { image: &overlay, properties: &properties },
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::layers::BlendMode;
+    use crate::types::operations::Anchor;
+    use tempfile::tempdir;
+
+    fn grey(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([100, 100, 100, 255])))
+    }
+
+    /// Save a solid red 40x20 logo and return its path
+    fn logo(dir: &std::path::Path) -> String {
+        let path = dir.join("logo.png");
+        RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255])).save(&path).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn params(path: String, placement: WatermarkPlacement) -> WatermarkParams {
+        WatermarkParams {
+            mark: WatermarkMark::Image { path, scale: 0.2 },
+            placement,
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+
+    #[test]
+    fn test_anchored_logo_scales_with_short_edge() {
+        let dir = tempdir().unwrap();
+        let img = grey(300, 100);
+        let placement = WatermarkPlacement::Anchored { anchor: Anchor::BottomRight, margin: 0.05 };
+        let result = watermark(&img, &params(logo(dir.path()), placement)).unwrap().to_rgba8();
+
+        // Long side 20% of the 100px short edge gives a 20x10 logo, 5px from the corner
+        let red = Rgba([255, 0, 0, 255]);
+        assert_eq!(*result.get_pixel(275, 85), red);
+        assert_eq!(*result.get_pixel(294, 94), red);
+        assert_eq!(*result.get_pixel(274, 85), Rgba([100, 100, 100, 255]));
+        assert_eq!(*result.get_pixel(275, 84), Rgba([100, 100, 100, 255]));
+        assert_eq!(*result.get_pixel(295, 95), Rgba([100, 100, 100, 255]));
+    }
+
+    #[test]
+    fn test_opacity_and_blend_mode() {
+        let dir = tempdir().unwrap();
+        let img = grey(100, 100);
+        let placement = WatermarkPlacement::Anchored { anchor: Anchor::Center, margin: 0.0 };
+
+        let faded = WatermarkParams { opacity: 0.5, ..params(logo(dir.path()), placement.clone()) };
+        let result = watermark(&img, &faded).unwrap().to_rgba8();
+        assert_eq!(*result.get_pixel(50, 50), Rgba([178, 50, 50, 255]));
+
+        let blend_mode = BlendMode::Multiply;
+        let multiplied = WatermarkParams { blend_mode, ..params(logo(dir.path()), placement) };
+        let result = watermark(&img, &multiplied).unwrap().to_rgba8();
+        assert_eq!(*result.get_pixel(50, 50), Rgba([100, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_tiled_marks_cover_the_image() {
+        let dir = tempdir().unwrap();
+        let img = grey(200, 200);
+        let placement = WatermarkPlacement::Tiled { spacing: 0.1, angle: -30.0 };
+        let result = watermark(&img, &params(logo(dir.path()), placement)).unwrap().to_rgba8();
+
+        // Each quarter of the image holds part of a mark
+        for (qx, qy) in [(0, 0), (100, 0), (0, 100), (100, 100)] {
+            let marked = (qx..qx + 100)
+                .flat_map(|x| (qy..qy + 100).map(move |y| (x, y)))
+                .filter(|&(x, y)| result.get_pixel(x, y)[0] > 200)
+                .count();
+            assert!(marked > 200, "quarter at {},{} has {} marked pixels", qx, qy, marked);
+        }
+        let untouched = result.pixels().filter(|p| p[0] == 100).count();
+        assert!(untouched > 200 * 200 / 4);
+    }
+
+    #[test]
+    fn test_resolve_embeds_logo() {
+        let dir = tempdir().unwrap();
+        let img = grey(300, 100);
+        let placement = WatermarkPlacement::Anchored { anchor: Anchor::BottomRight, margin: 0.05 };
+        let params = params(logo(dir.path()), placement);
+        let expected = watermark(&img, &params).unwrap();
+
+        // The resolved mark no longer needs the file
+        let resolved = resolve(&params).unwrap();
+        assert!(matches!(resolved.mark, WatermarkMark::EmbeddedImage { scale: 0.2, .. }));
+        drop(dir);
+        assert_eq!(watermark(&img, &resolved).unwrap(), expected);
+
+        assert!(resolve(&params).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_watermarks() {
+        let img = grey(50, 50);
+        let placement = WatermarkPlacement::default();
+        assert!(watermark(&img, &params("missing.png".to_string(), placement.clone())).is_err());
+
+        let dir = tempdir().unwrap();
+        let path = logo(dir.path());
+        let opaque = WatermarkParams { opacity: 1.5, ..params(path.clone(), placement) };
+        assert!(watermark(&img, &opaque).is_err());
+        let placement = WatermarkPlacement::Anchored { anchor: Anchor::Top, margin: 0.6 };
+        assert!(watermark(&img, &params(path, placement)).is_err());
+    }
+}
@@ -10,6 +10,8 @@ mod utils;
 use state::image_state::ImageState;
 use commands::export_commands;
 use commands::image_commands;
+use commands::layer_commands;
+use commands::selection_commands;
 
 fn main() {
     tauri::Builder::default()
@@ -17,9 +19,24 @@ fn main() {
         .manage(ImageState::new())
         .invoke_handler(tauri::generate_handler![
             image_commands::open_image,
+            image_commands::open_hdr_bracket,
             image_commands::apply_operation,
             image_commands::undo,
             image_commands::redo,
+            layer_commands::list_layers,
+            layer_commands::add_image_layer,
+            layer_commands::add_fill_layer,
+            layer_commands::add_text_layer,
+            layer_commands::remove_layer,
+            layer_commands::move_layer,
+            layer_commands::set_active_layer,
+            layer_commands::set_layer_properties,
+            selection_commands::set_selection,
+            selection_commands::get_selection,
+            selection_commands::apply_operation_to_selection,
+            selection_commands::crop_to_selection,
+            selection_commands::copy_selection,
+            selection_commands::cut_selection,
             export_commands::export_image_command,
         ])
         .run(tauri::generate_context!())
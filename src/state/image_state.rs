@@ -1,12 +1,22 @@
-use arc_swap::ArcSwap;
-use image::DynamicImage;
-use std::path::Path;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use image::{DynamicImage, GrayImage};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::core::compositor::{CompositeLayer, composite};
+use crate::core::hdr;
 use crate::core::history_manager::HistoryManager;
 use crate::core::image_processor::ImageProcessor;
+use crate::state::layer::Layer;
 use crate::types::errors::AppError;
-use crate::types::operations::{EditOperation, OperationType};
+use crate::types::hdr::HdrParams;
+use crate::core::operations::selection::{copy_selection, rasterize_selection, selection_bounds};
+use crate::core::operations::text::text;
+use crate::types::layers::{LayerContent, LayerInfo, LayerProperties};
+use crate::types::operations::{
+    Color, EditOperation, EraseParams, Mask, MaskLayer, MaskMode, MaskShape, OperationType, Selection,
+    TextParams,
+};
 use crate::utils::preview::resize_to_fit;
 
 /// Preview cache structure
@@ -32,68 +42,90 @@ impl Default for PreviewCache {
 
 /// Image state manager
 pub struct ImageState {
-    /// Original image (immutable, shared via ArcSwap)
-    original_image: ArcSwap<Option<DynamicImage>>,
+    /// Layer stack from bottom to top; the bottom layer sets the canvas size
+    layers: ArcSwap<Vec<Arc<Layer>>>,
 
-    /// Current image for preview
-    current_image: ArcSwap<Option<DynamicImage>>,
+    /// Id of the layer operations apply to
+    active_layer: ArcSwapOption<String>,
 
-    /// History manager
-    history: Arc<HistoryManager>,
+    /// Composited layers for preview and export
+    current_image: ArcSwap<Option<DynamicImage>>,
 
     /// Preview cache
     #[allow(dead_code)]
     preview_cache: ArcSwap<PreviewCache>,
+
+    /// Active selection in normalised coordinates
+    selection: ArcSwap<Option<Selection>>,
+
+    /// Selection rasterised for the current image, dropped whenever it changes
+    selection_mask: ArcSwapOption<GrayImage>,
 }
 
 impl ImageState {
     /// Create new image state
     pub fn new() -> Self {
         Self {
-            original_image: ArcSwap::new(Arc::new(None)),
+            layers: ArcSwap::new(Arc::new(Vec::new())),
+            active_layer: ArcSwapOption::empty(),
             current_image: ArcSwap::new(Arc::new(None)),
-            history: Arc::new(HistoryManager::new()),
             preview_cache: ArcSwap::new(Arc::new(PreviewCache::default())),
+            selection: ArcSwap::new(Arc::new(None)),
+            selection_mask: ArcSwapOption::empty(),
         }
     }
 
-    /// Get reference to original image
+    /// Get reference to the active layer's original image
     #[allow(dead_code)]
     pub fn get_original(&self) -> Option<Arc<Option<DynamicImage>>> {
-        Some(self.original_image.load_full())
+        Some(Arc::new(self.active_layer().ok().map(|layer| layer.original().clone())))
     }
 
-    /// Get reference to current image
+    /// Get reference to current image, with all layers composited
     #[allow(dead_code)]
     pub fn get_current(&self) -> Option<Arc<Option<DynamicImage>>> {
         Some(self.current_image.load_full())
     }
 
-    /// Get reference to history manager
+    /// Get reference to the active layer's history manager
     #[allow(dead_code)]
-    pub fn history(&self) -> &Arc<HistoryManager> {
-        &self.history
+    pub fn history(&self) -> Option<Arc<HistoryManager>> {
+        self.active_layer().ok().map(|layer| layer.history().clone())
     }
 
-    /// Store original image
+    /// Store original image as the only layer
     pub fn set_original(&self, image: DynamicImage) {
-        self.original_image.store(Arc::new(Some(image.clone())));
+        let background = Arc::new(Layer::new(
+            LayerContent::Image,
+            image.clone(),
+            LayerProperties::named("Background"),
+        ));
+        self.active_layer.store(Some(Arc::new(background.id.clone())));
+        self.layers.store(Arc::new(vec![background]));
         self.current_image.store(Arc::new(Some(image)));
+        self.selection.store(Arc::new(None));
+        self.selection_mask.store(None);
     }
 
-    /// Store current image
+    /// Store the active layer's current image
     #[allow(dead_code)]
     pub fn set_current(&self, image: DynamicImage) {
-        self.current_image.store(Arc::new(Some(image)));
+        if let Ok(layer) = self.active_layer() {
+            layer.set_current(image);
+            self.current_image.store(Arc::new(flatten(&self.layers.load())));
+            self.selection_mask.store(None);
+        }
     }
 
     /// Clear all state
     #[allow(dead_code)]
     pub fn clear(&self) {
-        self.original_image.store(Arc::new(None));
+        self.layers.store(Arc::new(Vec::new()));
+        self.active_layer.store(None);
         self.current_image.store(Arc::new(None));
-        self.history.clear();
         self.preview_cache.store(Arc::new(PreviewCache::default()));
+        self.selection.store(Arc::new(None));
+        self.selection_mask.store(None);
     }
 
     /// Load original image from file path
@@ -117,15 +149,39 @@ impl ImageState {
         let width = image.width();
         let height = image.height();
 
-        // Store original and current
+        // Replace the layer stack, which also starts a fresh history
         self.set_original(image);
-        
-        // Clear history on new image
-        self.history.clear();
 
         Ok((width, height, format_str))
     }
 
+    /// Merge bracketed exposures into a new original image
+    pub async fn load_bracket<P: AsRef<Path>>(&self, paths: &[P], params: HdrParams) -> Result<(u32, u32, String), AppError> {
+        hdr::validate(&params, paths.len())?;
+        let paths: Vec<PathBuf> = paths.iter().map(|path| path.as_ref().to_path_buf()).collect();
+
+        // Load, align and merge in blocking thread
+        let image = tokio::task::spawn_blocking(move || {
+            let frames = paths
+                .iter()
+                .map(|path| {
+                    image::open(path).map_err(|e| AppError::ImageLoadError(format!("{}: {}", path.display(), e)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            hdr::merge_bracket(frames, &params)
+        })
+        .await
+        .map_err(|e| AppError::ProcessingError { details: e.to_string() })??;
+
+        let width = image.width();
+        let height = image.height();
+
+        // Edits and history continue from the merged result
+        self.set_original(image);
+
+        Ok((width, height, "HDR".to_string()))
+    }
+
     /// Generate preview with maximum dimensions
     pub async fn generate_preview(&self, max_width: u32, max_height: u32) -> Result<String, AppError> {
         // Load current image
@@ -149,105 +205,355 @@ impl ImageState {
         Ok(base64)
     }
 
-    /// Apply an operation to the current image
-    pub async fn apply_operation(&self, operation: EditOperation) -> Result<(), AppError> {
-        // Load current image
-        let current = self.current_image.load();
-        let image = current
-            .as_ref()
-            .as_ref()
-            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?
-            .clone();
+    /// Apply an operation to the active layer
+    pub async fn apply_operation(&self, mut operation: EditOperation) -> Result<(), AppError> {
+        // Load active layer image
+        let layer = self.active_layer()?;
+        let image = layer.current();
 
-        let operation_clone = operation.clone();
+        let operation_type = operation.operation.clone();
+        let operation_mask = operation.mask.clone();
         
-        // Apply operation in blocking thread
-        let result = tokio::task::spawn_blocking(move || {
-            ImageProcessor::apply_operation(&image, &operation_clone.operation)
+        // Resolve automatic operations and apply in blocking thread
+        let (result, resolved) = tokio::task::spawn_blocking(move || {
+            let resolved = ImageProcessor::resolve_operation(&image, &operation_type)?;
+            let result = ImageProcessor::apply_masked_operation(&image, &resolved, operation_mask.as_ref())?;
+            Ok::<_, AppError>((result, resolved))
         })
         .await
         .map_err(|e| AppError::ProcessingError { details: e.to_string() })??;
 
         // Store result
-        self.current_image.store(Arc::new(Some(result)));
+        layer.set_current(result);
+        self.selection_mask.store(None);
         
-        // Add to history
-        self.history.add_operation(operation);
+        // Add to history with concrete parameters so rebuilds are deterministic
+        operation.operation = resolved;
+        layer.history().add_operation(operation);
 
-        Ok(())
+        self.refresh_composite().await
     }
 
-    /// Undo the last operation
+    /// Undo the last operation on the active layer
     pub async fn undo(&self) -> Result<(), AppError> {
         // Undo in history and get updated operations list
-        let operations = self.history.undo()
+        let layer = self.active_layer()?;
+        let operations = layer.history().undo()
             .ok_or_else(|| AppError::StateError { 
                 message: "Nothing to undo".to_string() 
             })?;
 
-        // Rebuild current image from original + history
-        self.rebuild_from_operations(operations).await?;
+        // Rebuild layer image from original + history
+        self.rebuild_from_operations(&layer, operations).await?;
 
         Ok(())
     }
 
-    /// Redo the last undone operation
+    /// Redo the last undone operation on the active layer
     pub async fn redo(&self) -> Result<(), AppError> {
         // Redo in history and get updated operations list
-        let operations = self.history.redo()
+        let layer = self.active_layer()?;
+        let operations = layer.history().redo()
             .ok_or_else(|| AppError::StateError { 
                 message: "Nothing to redo".to_string() 
             })?;
 
-        // Rebuild current image from original + history
-        self.rebuild_from_operations(operations).await?;
+        // Rebuild layer image from original + history
+        self.rebuild_from_operations(&layer, operations).await?;
 
         Ok(())
     }
 
-    /// Rebuild current image from a list of edit operations
-    async fn rebuild_from_operations(&self, operations: Vec<EditOperation>) -> Result<(), AppError> {
+    /// Rebuild a layer's image from a list of edit operations
+    async fn rebuild_from_operations(&self, layer: &Layer, operations: Vec<EditOperation>) -> Result<(), AppError> {
         // Load original image
-        let original = self.original_image.load();
-        let image = original
-            .as_ref()
-            .as_ref()
-            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?
-            .clone();
-
-        // Extract operation types
-        let op_types: Vec<OperationType> = operations
-            .iter()
-            .map(|op| op.operation.clone())
-            .collect();
+        let image = layer.original().clone();
 
         // Apply all operations in blocking thread
-        let result = if op_types.is_empty() {
+        let result = if operations.is_empty() {
             image
         } else {
             tokio::task::spawn_blocking(move || {
-                ImageProcessor::apply_operations(&image, &op_types)
+                ImageProcessor::apply_edits(&image, &operations)
             })
             .await
             .map_err(|e| AppError::ProcessingError { details: e.to_string() })??
         };
 
         // Store result
-        self.current_image.store(Arc::new(Some(result)));
+        layer.set_current(result);
+        self.selection_mask.store(None);
+
+        self.refresh_composite().await
+    }
+
+    /// Get the layer operations apply to
+    fn active_layer(&self) -> Result<Arc<Layer>, AppError> {
+        let active = self.active_layer.load_full();
+        self.layers
+            .load()
+            .iter()
+            .find(|layer| Some(&layer.id) == active.as_deref())
+            .cloned()
+            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })
+    }
+
+    /// Get a layer by id
+    fn layer(&self, id: &str) -> Result<Arc<Layer>, AppError> {
+        self.layers
+            .load()
+            .iter()
+            .find(|layer| layer.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::StateError { message: format!("Layer not found: {}", id) })
+    }
+
+    /// Recomposite the layer stack into the current image
+    async fn refresh_composite(&self) -> Result<(), AppError> {
+        let layers = self.layers.load_full();
+
+        // Composite in blocking thread
+        let result = tokio::task::spawn_blocking(move || flatten(&layers))
+            .await
+            .map_err(|e| AppError::ProcessingError { details: e.to_string() })?;
+
+        self.current_image.store(Arc::new(result));
+
+        Ok(())
+    }
+
+    /// Describe the layer stack from bottom to top
+    pub fn layers(&self) -> Vec<LayerInfo> {
+        let active = self.active_layer.load_full();
+        self.layers
+            .load()
+            .iter()
+            .map(|layer| layer.info(Some(&layer.id) == active.as_deref()))
+            .collect()
+    }
+
+    /// Add a layer on top of the stack and make it active
+    async fn push_layer(&self, layer: Layer) -> Result<String, AppError> {
+        if self.layers.load().is_empty() {
+            return Err(AppError::StateError { message: "No image loaded".to_string() });
+        }
+
+        let id = layer.id.clone();
+        let mut layers = self.layers.load().as_ref().clone();
+        layers.push(Arc::new(layer));
+        self.layers.store(Arc::new(layers));
+        self.set_active_layer(&id)?;
+
+        self.refresh_composite().await?;
+
+        Ok(id)
+    }
+
+    /// Add an image layer on top of the stack
+    ///
+    /// # Returns
+    /// Id of the new layer, which becomes active
+    pub async fn add_image_layer(&self, name: String, image: DynamicImage) -> Result<String, AppError> {
+        self.push_layer(Layer::new(LayerContent::Image, image, LayerProperties::named(name))).await
+    }
+
+    /// Add a solid colour layer covering the canvas on top of the stack
+    ///
+    /// # Returns
+    /// Id of the new layer, which becomes active
+    pub async fn add_fill_layer(&self, name: String, color: Color) -> Result<String, AppError> {
+        let (width, height) = canvas_size(&self.layers.load())
+            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?;
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(width, height, color.to_rgba()));
+
+        self.push_layer(Layer::new(LayerContent::Fill { color }, image, LayerProperties::named(name))).await
+    }
+
+    /// Add a text layer covering the canvas on top of the stack
+    ///
+    /// # Returns
+    /// Id of the new layer, which becomes active
+    pub async fn add_text_layer(&self, name: String, params: TextParams) -> Result<String, AppError> {
+        let (width, height) = canvas_size(&self.layers.load())
+            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?;
+
+        // Render in blocking thread, since fonts are loaded from disk
+        let text_params = params.clone();
+        let image = tokio::task::spawn_blocking(move || {
+            let canvas = DynamicImage::ImageRgba8(image::RgbaImage::new(width, height));
+            text(&canvas, &text_params)
+        })
+        .await
+        .map_err(|e| AppError::ProcessingError { details: e.to_string() })??;
+
+        self.push_layer(Layer::new(LayerContent::Text { params }, image, LayerProperties::named(name))).await
+    }
+
+    /// Remove a layer; the last remaining layer cannot be removed
+    pub async fn remove_layer(&self, id: &str) -> Result<(), AppError> {
+        let layers = self.layers.load_full();
+        if layers.len() <= 1 {
+            return Err(AppError::StateError { message: "Cannot remove the only layer".to_string() });
+        }
+        let index = layers
+            .iter()
+            .position(|layer| layer.id == id)
+            .ok_or_else(|| AppError::StateError { message: format!("Layer not found: {}", id) })?;
+
+        let mut remaining = layers.as_ref().clone();
+        remaining.remove(index);
+        let next_active = remaining[index.saturating_sub(1)].id.clone();
+        self.layers.store(Arc::new(remaining));
+
+        if self.active_layer.load().as_deref().is_some_and(|active| active == id) {
+            self.set_active_layer(&next_active)?;
+        }
+
+        self.refresh_composite().await
+    }
+
+    /// Move a layer to a new position in the stack (0 is the bottom)
+    pub async fn move_layer(&self, id: &str, index: usize) -> Result<(), AppError> {
+        let mut layers = self.layers.load().as_ref().clone();
+        let from = layers
+            .iter()
+            .position(|layer| layer.id == id)
+            .ok_or_else(|| AppError::StateError { message: format!("Layer not found: {}", id) })?;
+        if index >= layers.len() {
+            return Err(AppError::InvalidOperation {
+                details: format!("Layer index must be below {}, got {}", layers.len(), index),
+            });
+        }
+
+        let layer = layers.remove(from);
+        layers.insert(index, layer);
+        self.layers.store(Arc::new(layers));
+
+        self.refresh_composite().await
+    }
+
+    /// Make a layer the target of operations, undo and redo
+    pub fn set_active_layer(&self, id: &str) -> Result<(), AppError> {
+        let layer = self.layer(id)?;
+        self.active_layer.store(Some(Arc::new(layer.id.clone())));
+        self.selection_mask.store(None);
+
+        Ok(())
+    }
+
+    /// Update a layer's name, opacity, visibility, blend mode or offset
+    pub async fn set_layer_properties(&self, id: &str, properties: LayerProperties) -> Result<(), AppError> {
+        if !(0.0..=1.0).contains(&properties.opacity) {
+            return Err(AppError::InvalidOperation {
+                details: format!("Layer opacity must be between 0.0 and 1.0, got {}", properties.opacity),
+            });
+        }
+
+        self.layer(id)?.set_properties(properties);
+
+        self.refresh_composite().await
+    }
+
+    /// Replace the active selection, or clear it with `None`
+    pub fn set_selection(&self, selection: Option<Selection>) {
+        self.selection.store(Arc::new(selection));
+        self.selection_mask.store(None);
+    }
+
+    /// Get the active selection
+    pub fn get_selection(&self) -> Option<Selection> {
+        self.selection.load().as_ref().clone()
+    }
+
+    /// Get the active selection rasterised for the active layer
+    ///
+    /// The mask is cached until the selection or the current image changes.
+    pub async fn selection_mask(&self) -> Result<Arc<GrayImage>, AppError> {
+        let selection = self
+            .get_selection()
+            .ok_or_else(|| AppError::StateError { message: "No active selection".to_string() })?;
+
+        let image = self.active_layer()?.current();
+
+        if let Some(mask) = self.selection_mask.load_full()
+            && mask.dimensions() == (image.width(), image.height())
+        {
+            return Ok(mask);
+        }
+
+        // Rasterise in blocking thread
+        let mask = tokio::task::spawn_blocking(move || rasterize_selection(&image.to_rgba8(), &selection))
+            .await
+            .map_err(|e| AppError::ProcessingError { details: e.to_string() })??;
+
+        let mask = Arc::new(mask);
+        self.selection_mask.store(Some(mask.clone()));
+
+        Ok(mask)
+    }
+
+    /// Apply an operation only inside the active selection
+    ///
+    /// The selection is recorded in the operation's mask, so history replays it.
+    pub async fn apply_operation_to_selection(&self, mut operation: EditOperation) -> Result<(), AppError> {
+        let selection = self
+            .get_selection()
+            .ok_or_else(|| AppError::StateError { message: "No active selection".to_string() })?;
+
+        let mut mask = operation.mask.take().unwrap_or(Mask { layers: Vec::new(), invert: false });
+        if mask.invert {
+            return Err(AppError::InvalidOperation {
+                details: "Inverted masks cannot be combined with a selection".to_string(),
+            });
+        }
+        let mode = if mask.layers.is_empty() { MaskMode::Add } else { MaskMode::Intersect };
+        mask.layers.push(MaskLayer {
+            shape: MaskShape::Selection { selection },
+            mode,
+            invert: false,
+        });
+        operation.mask = Some(mask);
+
+        self.apply_operation(operation).await
+    }
+
+    /// Crop the active layer to the bounds of the active selection and clear it
+    pub async fn crop_to_selection(&self) -> Result<(), AppError> {
+        let mask = self.selection_mask().await?;
+        let bounds = selection_bounds(&mask)
+            .ok_or_else(|| AppError::StateError { message: "Selection is empty".to_string() })?;
+
+        self.apply_operation(EditOperation::new(OperationType::Crop(bounds.into()))).await?;
+        self.set_selection(None);
 
         Ok(())
     }
 
-    /// Render image with operations applied (for preview)
+    /// Copy the selected pixels of the active layer
+    pub async fn copy_selection(&self) -> Result<DynamicImage, AppError> {
+        let mask = self.selection_mask().await?;
+        let image = self.active_layer()?.current();
+
+        copy_selection(&image, &mask)
+    }
+
+    /// Copy the selected pixels, then erase them from the active layer
+    pub async fn cut_selection(&self) -> Result<DynamicImage, AppError> {
+        let copied = self.copy_selection().await?;
+        let selection = self
+            .get_selection()
+            .ok_or_else(|| AppError::StateError { message: "No active selection".to_string() })?;
+
+        self.apply_operation(EditOperation::new(OperationType::Erase(EraseParams { selection }))).await?;
+
+        Ok(copied)
+    }
+
+    /// Render the active layer with operations applied (for preview)
     #[allow(dead_code)]
     pub async fn render_with_operations(&self, operations: Vec<OperationType>) -> Result<DynamicImage, AppError> {
         // Load original image
-        let original = self.original_image.load();
-        let image = original
-            .as_ref()
-            .as_ref()
-            .ok_or_else(|| AppError::StateError { message: "No image loaded".to_string() })?
-            .clone();
+        let image = self.active_layer()?.original().clone();
 
         // Apply operations in blocking thread
         let result = tokio::task::spawn_blocking(move || {
@@ -260,8 +566,154 @@ impl ImageState {
     }
 }
 
+/// Canvas size, taken from the bottom layer
+fn canvas_size(layers: &[Arc<Layer>]) -> Option<(u32, u32)> {
+    layers.first().map(|layer| {
+        let image = layer.current();
+        (image.width(), image.height())
+    })
+}
+
+/// Composite a layer stack, or None if it is empty
+fn flatten(layers: &[Arc<Layer>]) -> Option<DynamicImage> {
+    let (width, height) = canvas_size(layers)?;
+    let snapshots: Vec<(Arc<DynamicImage>, Arc<LayerProperties>)> = layers
+        .iter()
+        .map(|layer| (layer.current(), layer.properties()))
+        .collect();
+    let composite_layers: Vec<CompositeLayer> = snapshots
+        .iter()
+        .map(|(image, properties)| CompositeLayer { image: image.as_ref(), properties: properties.as_ref() })
+        .collect();
+
+    Some(composite(width, height, &composite_layers))
+}
+
 impl Default for ImageState {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::layers::BlendMode;
+    use crate::types::operations::{FilterType, Point, TextAlign, TransformType};
+    use image::{Rgba, RgbaImage};
+
+    fn pixel(state: &ImageState, x: u32, y: u32) -> Rgba<u8> {
+        let current = state.get_current().unwrap();
+        *current.as_ref().as_ref().unwrap().to_rgba8().get_pixel(x, y)
+    }
+
+    #[tokio::test]
+    async fn test_layers_composite_with_their_own_history() {
+        let state = ImageState::new();
+        let image = RgbaImage::from_pixel(4, 2, Rgba([200, 100, 50, 255]));
+        state.set_original(DynamicImage::ImageRgba8(image));
+        let background = state.layers()[0].id.clone();
+
+        let fill = Color { r: 128, g: 128, b: 128, a: 255 };
+        let overlay = state.add_fill_layer("Shade".to_string(), fill).await.unwrap();
+        let properties = LayerProperties { blend_mode: BlendMode::Multiply, ..LayerProperties::named("Shade") };
+        state.set_layer_properties(&overlay, properties).await.unwrap();
+        assert_eq!(pixel(&state, 0, 0), Rgba([100, 50, 25, 255]));
+
+        // Operations go to the active layer only
+        state.set_active_layer(&background).unwrap();
+        let grayscale = OperationType::Filter(FilterType::Grayscale);
+        let flip = OperationType::Transform(TransformType::FlipHorizontal);
+        state.apply_operation(EditOperation::new(grayscale)).await.unwrap();
+        state.apply_operation(EditOperation::new(flip)).await.unwrap();
+        assert_eq!(state.history().unwrap().history_count(), 2);
+        assert_eq!(state.layer(&overlay).unwrap().history().history_count(), 0);
+
+        state.undo().await.unwrap();
+        state.undo().await.unwrap();
+        assert_eq!(pixel(&state, 0, 0), Rgba([100, 50, 25, 255]));
+
+        state.remove_layer(&overlay).await.unwrap();
+        assert_eq!(pixel(&state, 0, 0), Rgba([200, 100, 50, 255]));
+        assert!(state.remove_layer(&background).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_layer_order_and_properties() {
+        let state = ImageState::new();
+        state.set_original(DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]))));
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255; 4])));
+        let white = state.add_image_layer("White".to_string(), image).await.unwrap();
+        assert_eq!(pixel(&state, 0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(pixel(&state, 1, 1), Rgba([0, 0, 0, 255]));
+
+        // The bottom layer sets the canvas size
+        state.move_layer(&white, 0).await.unwrap();
+        assert_eq!(state.layers()[0].id, white);
+        assert_eq!(state.get_current().unwrap().as_ref().as_ref().unwrap().width(), 1);
+        assert!(state.move_layer(&white, 2).await.is_err());
+
+        let hidden = LayerProperties { visible: false, ..LayerProperties::named("White") };
+        state.set_layer_properties(&white, hidden).await.unwrap();
+        let invalid = LayerProperties { opacity: 1.5, ..LayerProperties::named("White") };
+        assert!(state.set_layer_properties(&white, invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_text_layer_covers_canvas() {
+        crate::core::operations::text::use_fixture_fonts();
+        let state = ImageState::new();
+        state.set_original(DynamicImage::ImageRgba8(RgbaImage::from_pixel(80, 40, Rgba([0, 0, 0, 255]))));
+        let params = TextParams {
+            text: "Hi".to_string(),
+            font: "FixtureSans-Regular.ttf".to_string(),
+            size: 24.0,
+            color: Color { r: 255, g: 255, b: 255, a: 255 },
+            position: Point { x: 10.0, y: 5.0 },
+            align: TextAlign::Left,
+            rotation: 0.0,
+            stroke: None,
+            shadow: None,
+        };
+        let id = state.add_text_layer("Caption".to_string(), params.clone()).await.unwrap();
+
+        let info = state.layers().into_iter().find(|layer| layer.id == id).unwrap();
+        assert_eq!(info.content, LayerContent::Text { params });
+        assert_eq!((info.width, info.height), (80, 40));
+        assert!(info.active);
+
+        // Text is drawn at its position and the background shows elsewhere
+        let current = state.get_current().unwrap();
+        let rgba = current.as_ref().as_ref().unwrap().to_rgba8();
+        assert!(rgba.enumerate_pixels().any(|(x, y, pixel)| x >= 10 && y >= 5 && pixel[0] > 200));
+        assert_eq!(pixel(&state, 79, 39), Rgba([0, 0, 0, 255]));
+    }
+
+    #[tokio::test]
+    async fn test_bracket_becomes_new_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = [40u8, 120, 220]
+            .iter()
+            .enumerate()
+            .map(|(i, level)| {
+                let path = dir.path().join(format!("bracket-{}.png", i));
+                RgbaImage::from_pixel(6, 4, Rgba([*level, *level, *level, 255])).save(&path).unwrap();
+                path
+            })
+            .collect();
+
+        let state = ImageState::new();
+        let (width, height, format) = state.load_bracket(&paths, HdrParams::default()).await.unwrap();
+        assert_eq!((width, height, format.as_str()), (6, 4, "HDR"));
+        assert_eq!(state.layers().len(), 1);
+        assert_eq!(state.history().unwrap().history_count(), 0);
+        // The mid-grey frame is the best exposed and dominates the fusion
+        assert!(pixel(&state, 0, 0)[0].abs_diff(120) < 20, "{:?}", pixel(&state, 0, 0));
+
+        let flip = OperationType::Transform(TransformType::FlipHorizontal);
+        state.apply_operation(EditOperation::new(flip)).await.unwrap();
+        assert_eq!(state.history().unwrap().history_count(), 1);
+
+        assert!(state.load_bracket(&paths[..1], HdrParams::default()).await.is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use arc_swap::ArcSwap;
+use image::{DynamicImage, GenericImageView};
+use std::sync::Arc;
+
+use crate::core::history_manager::HistoryManager;
+use crate::types::layers::{LayerContent, LayerInfo, LayerProperties};
+
+/// Raster layer with its own edit history
+pub struct Layer {
+    /// Unique layer id
+    pub id: String,
+
+    /// What the layer was created from
+    pub content: LayerContent,
+
+    /// Image the layer's history is replayed from
+    original: DynamicImage,
+
+    /// Image after the layer's operations
+    current: ArcSwap<DynamicImage>,
+
+    /// Operations applied to this layer
+    history: Arc<HistoryManager>,
+
+    /// Compositing properties
+    properties: ArcSwap<LayerProperties>,
+}
+
+impl Layer {
+    /// Create a layer with an empty history
+    pub fn new(content: LayerContent, image: DynamicImage, properties: LayerProperties) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            content,
+            original: image.clone(),
+            current: ArcSwap::new(Arc::new(image)),
+            history: Arc::new(HistoryManager::new()),
+            properties: ArcSwap::new(Arc::new(properties)),
+        }
+    }
+
+    /// Get the image the history is replayed from
+    pub fn original(&self) -> &DynamicImage {
+        &self.original
+    }
+
+    /// Get the image after the layer's operations
+    pub fn current(&self) -> Arc<DynamicImage> {
+        self.current.load_full()
+    }
+
+    /// Store the image after the layer's operations
+    pub fn set_current(&self, image: DynamicImage) {
+        self.current.store(Arc::new(image));
+    }
+
+    /// Get the layer's history manager
+    pub fn history(&self) -> &Arc<HistoryManager> {
+        &self.history
+    }
+
+    /// Get the compositing properties
+    pub fn properties(&self) -> Arc<LayerProperties> {
+        self.properties.load_full()
+    }
+
+    /// Replace the compositing properties
+    pub fn set_properties(&self, properties: LayerProperties) {
+        self.properties.store(Arc::new(properties));
+    }
+
+    /// Summarise the layer for the frontend
+    pub fn info(&self, active: bool) -> LayerInfo {
+        let (width, height) = self.current().dimensions();
+        LayerInfo {
+            id: self.id.clone(),
+            content: self.content.clone(),
+            properties: self.properties().as_ref().clone(),
+            width,
+            height,
+            active,
+        }
+    }
+}
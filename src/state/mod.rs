@@ -1 +1,2 @@
 pub mod image_state;
+pub mod layer;
@@ -1,4 +1,6 @@
-use super::operations::EditOperation;
+use super::hdr::HdrParams;
+use super::layers::{LayerInfo, LayerProperties};
+use super::operations::{Color, EditOperation, Selection, TextParams};
 use serde::{Deserialize, Serialize};
 
 /// Open image command input
@@ -9,6 +11,16 @@ pub struct OpenImageInput {
     pub preview_max_height: u32,
 }
 
+/// Open HDR bracket command input
+#[derive(Debug, Deserialize)]
+pub struct OpenHdrBracketInput {
+    pub file_paths: Vec<String>,
+    #[serde(default)]
+    pub params: HdrParams,
+    pub preview_max_width: u32,
+    pub preview_max_height: u32,
+}
+
 /// Open image command output
 #[derive(Debug, Serialize)]
 pub struct OpenImageOutput {
@@ -35,6 +47,87 @@ pub struct ApplyOperationOutput {
     pub new_height: u32,
 }
 
+/// Set selection command input
+#[derive(Debug, Deserialize)]
+pub struct SetSelectionInput {
+    /// New selection, or None to clear it
+    pub selection: Option<Selection>,
+}
+
+/// Selected pixels copied out of the active layer
+#[derive(Debug, Serialize)]
+pub struct SelectionImageOutput {
+    pub image_base64: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Cut selection command output
+#[derive(Debug, Serialize)]
+pub struct CutSelectionOutput {
+    pub copied: SelectionImageOutput,
+    pub result: ApplyOperationOutput,
+}
+
+/// Where the pixels of a new image layer come from
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayerImageSource {
+    /// Image file on disk
+    File { path: String },
+    /// Base64 image, such as pixels copied from a selection
+    Base64 { data: String },
+}
+
+/// Add image layer command input
+#[derive(Debug, Deserialize)]
+pub struct AddImageLayerInput {
+    pub name: String,
+    pub source: LayerImageSource,
+}
+
+/// Add fill layer command input
+#[derive(Debug, Deserialize)]
+pub struct AddFillLayerInput {
+    pub name: String,
+    pub color: Color,
+}
+
+/// Add text layer command input
+#[derive(Debug, Deserialize)]
+pub struct AddTextLayerInput {
+    pub name: String,
+    pub params: TextParams,
+}
+
+/// Input for commands that act on one layer
+#[derive(Debug, Deserialize)]
+pub struct LayerIdInput {
+    pub id: String,
+}
+
+/// Move layer command input
+#[derive(Debug, Deserialize)]
+pub struct MoveLayerInput {
+    pub id: String,
+    /// New position in the stack, 0 being the bottom
+    pub index: usize,
+}
+
+/// Set layer properties command input
+#[derive(Debug, Deserialize)]
+pub struct SetLayerPropertiesInput {
+    pub id: String,
+    pub properties: LayerProperties,
+}
+
+/// Layer stack after a change, with a preview of the composite
+#[derive(Debug, Serialize)]
+pub struct LayerStackOutput {
+    pub layers: Vec<LayerInfo>,
+    pub preview_base64: String,
+}
+
 /// Preview command input
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
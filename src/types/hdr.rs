@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// How a set of bracketed exposures is merged into one image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HdrMerge {
+    /// Mertens exposure fusion: blend the best exposed parts of each frame
+    /// directly, weighted by local contrast, saturation and well-exposedness
+    Fusion {
+        #[serde(default = "default_fusion_weight")]
+        contrast: f32,
+        #[serde(default = "default_fusion_weight")]
+        saturation: f32,
+        #[serde(default = "default_fusion_weight")]
+        exposure: f32,
+    },
+    /// Radiance map tone mapped with the global Reinhard operator
+    ToneMapped {
+        /// Exposure time of each frame in seconds, in file order; estimated
+        /// from the frames themselves when absent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exposure_times: Option<Vec<f32>>,
+        /// Scene key the average luminance is mapped to (0.01-1.0)
+        #[serde(default = "default_key")]
+        key: f32,
+        /// Luminance mapped to white, relative to the key; the brightest pixel when absent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        white: Option<f32>,
+    },
+}
+
+fn default_fusion_weight() -> f32 {
+    1.0
+}
+
+fn default_key() -> f32 {
+    0.18
+}
+
+impl Default for HdrMerge {
+    fn default() -> Self {
+        HdrMerge::Fusion { contrast: 1.0, saturation: 1.0, exposure: 1.0 }
+    }
+}
+
+/// Merging parameters for a bracketed set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HdrParams {
+    #[serde(default)]
+    pub merge: HdrMerge,
+    /// Shift frames onto the middle exposure before merging
+    #[serde(default = "default_align")]
+    pub align: bool,
+}
+
+fn default_align() -> bool {
+    true
+}
+
+impl Default for HdrParams {
+    fn default() -> Self {
+        Self { merge: HdrMerge::default(), align: true }
+    }
+}
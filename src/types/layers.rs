@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use super::operations::{Color, TextParams};
+
+/// Blend modes for compositing a layer onto the layers below it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+}
+
+/// Compositing properties of a layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerProperties {
+    pub name: String,
+    /// Opacity (0.0 to 1.0)
+    #[serde(default = "default_layer_opacity")]
+    pub opacity: f32,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Offset of the layer's top-left corner on the canvas
+    #[serde(default)]
+    pub x: i64,
+    #[serde(default)]
+    pub y: i64,
+}
+
+impl LayerProperties {
+    /// Fully opaque, visible, normal-blended layer at the canvas origin
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            opacity: 1.0,
+            visible: true,
+            blend_mode: BlendMode::Normal,
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+fn default_layer_opacity() -> f32 {
+    1.0
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+/// What a layer was created from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayerContent {
+    /// Raster image, such as the opened file
+    Image,
+    /// Solid colour covering the canvas
+    Fill { color: Color },
+    /// Text drawn onto a transparent layer covering the canvas
+    Text { params: TextParams },
+}
+
+/// Summary of a layer for the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerInfo {
+    pub id: String,
+    pub content: LayerContent,
+    pub properties: LayerProperties,
+    pub width: u32,
+    pub height: u32,
+    pub active: bool,
+}
@@ -1,3 +1,5 @@
 pub mod commands;
 pub mod errors;
+pub mod hdr;
+pub mod layers;
 pub mod operations;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use super::layers::BlendMode;
 
 /// Operation parameters (tagged union)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -7,7 +8,29 @@ pub enum OperationType {
     Filter(FilterType),
     Adjustment(AdjustmentParams),
     Transform(TransformType),
-    Crop(CropRect),
+    Crop(CropParams),
+    Resize(ResizeParams),
+    Canvas(CanvasParams),
+    Perspective(PerspectiveParams),
+    Lens(LensParams),
+    AutoTrim(AutoTrimParams),
+    AutoStraighten(AutoStraightenParams),
+    ContentAwareResize(ContentAwareResizeParams),
+    SmartCrop(SmartCropParams),
+    Heal(HealParams),
+    CloneStamp(CloneStampParams),
+    RedEye(RedEyeParams),
+    Erase(EraseParams),
+    Text(TextParams),
+    Watermark(WatermarkParams),
+    Draw(DrawParams),
+    Redact(RedactParams),
+    Frame(FrameParams),
+    ChromaKey(ChromaKeyParams),
+    ReplaceColor(ReplaceColorParams),
+    Alpha(AlphaOperation),
+    Quantize(QuantizeParams),
+    Upscale(PixelArtScaler),
 }
 
 /// Filter types
@@ -56,10 +79,1004 @@ pub struct CropRect {
     pub height: u32,
 }
 
+/// Crop parameters
+///
+/// A plain `{ x, y, width, height }` in pixels is still accepted as-is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CropParams {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub units: CropUnits,
+    /// Rotation of the crop rectangle in degrees, clockwise around its centre
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub angle: Option<f32>,
+    /// Shrink the rectangle around its centre to this aspect ratio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<AspectRatio>,
+}
+
+impl From<CropRect> for CropParams {
+    fn from(rect: CropRect) -> Self {
+        Self {
+            x: rect.x as f32,
+            y: rect.y as f32,
+            width: rect.width as f32,
+            height: rect.height as f32,
+            units: CropUnits::Pixels,
+            angle: None,
+            aspect: None,
+        }
+    }
+}
+
+/// Coordinate units of a crop rectangle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CropUnits {
+    /// Original image pixels
+    #[default]
+    Pixels,
+    /// Fractions (0.0 to 1.0) of the image width and height
+    Normalized,
+}
+
+/// Aspect ratio presets
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AspectRatio {
+    #[serde(rename = "1:1")]
+    Square,
+    #[serde(rename = "4:3")]
+    Classic,
+    #[serde(rename = "3:2")]
+    Photo,
+    #[serde(rename = "16:9")]
+    Widescreen,
+    #[serde(rename = "4:5")]
+    Portrait,
+    #[serde(rename = "custom")]
+    Custom { width: f32, height: f32 },
+}
+
+impl AspectRatio {
+    /// Width divided by height
+    pub fn ratio(self) -> f32 {
+        match self {
+            AspectRatio::Square => 1.0,
+            AspectRatio::Classic => 4.0 / 3.0,
+            AspectRatio::Photo => 3.0 / 2.0,
+            AspectRatio::Widescreen => 16.0 / 9.0,
+            AspectRatio::Portrait => 4.0 / 5.0,
+            AspectRatio::Custom { width, height } => width / height,
+        }
+    }
+}
+
+/// Resize parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResizeParams {
+    pub mode: ResizeMode,
+    #[serde(default)]
+    pub filter: ResampleFilter,
+    /// Only used by `ResizeMode::Pixels`: derive one side from the other
+    #[serde(default = "default_keep_aspect")]
+    pub keep_aspect: bool,
+    /// Unsharp mask sigma applied after resampling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sharpen: Option<f32>,
+}
+
+fn default_keep_aspect() -> bool {
+    true
+}
+
+/// Resize modes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResizeMode {
+    Pixels { width: u32, height: u32 },
+    Percentage { percent: f32 },
+    Fit { width: u32, height: u32 },
+    Fill { width: u32, height: u32 },
+    LongEdge { length: u32 },
+}
+
+/// Resampling filters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResampleFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    #[default]
+    Lanczos3,
+}
+
+/// RGBA colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    #[serde(default = "default_alpha")]
+    pub a: u8,
+}
+
+fn default_alpha() -> u8 {
+    255
+}
+
+impl Color {
+    /// Convert to an `image` pixel
+    pub fn to_rgba(self) -> image::Rgba<u8> {
+        image::Rgba([self.r, self.g, self.b, self.a])
+    }
+}
+
+/// Nine-point anchor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Canvas parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasParams {
+    pub mode: CanvasMode,
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub fill: CanvasFill,
+}
+
+/// Canvas sizing modes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasMode {
+    /// Absolute canvas size; smaller than the image crops it at the anchor
+    Size { width: u32, height: u32 },
+    Padding { top: u32, right: u32, bottom: u32, left: u32 },
+    /// Grow the canvas until it matches `width:height`, e.g. 1:1 or 4:5
+    AspectRatio { width: u32, height: u32 },
+}
+
+/// Canvas background fill
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CanvasFill {
+    #[default]
+    Transparent,
+    Color { color: Color },
+    /// The image itself, scaled to cover the canvas and blurred
+    Blur { radius: f32 },
+}
+
+/// Point in image coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Region drawn in image coordinates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Region {
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+    Polygon { points: Vec<Point> },
+    /// Brush stroke through `points` with a round tip
+    Stroke { points: Vec<Point>, radius: f32 },
+}
+
+/// Interpolation used when resampling warped pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    Nearest,
+    #[default]
+    Bilinear,
+    Bicubic,
+}
+
+/// Perspective correction parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerspectiveParams {
+    pub mode: PerspectiveMode,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+/// Perspective correction modes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PerspectiveMode {
+    /// Source corners (top-left, top-right, bottom-right, bottom-left) mapped to a rectangle.
+    /// The output size defaults to the average edge lengths of the quad.
+    Corners {
+        corners: [Point; 4],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u32>,
+    },
+    /// Keystone sliders (-1.0 to 1.0). Positive vertical widens the top,
+    /// positive horizontal widens the left side.
+    Keystone { vertical: f32, horizontal: f32 },
+}
+
+/// Lens correction parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LensParams {
+    /// Radial distortion coefficients (-1.0 to 1.0). Negative k1 corrects
+    /// barrel distortion, positive k1 corrects pincushion distortion.
+    #[serde(default)]
+    pub k1: f32,
+    #[serde(default)]
+    pub k2: f32,
+    #[serde(default)]
+    pub k3: f32,
+    /// Lateral chromatic aberration: red channel scale relative to green (0.9-1.1)
+    #[serde(default = "default_channel_scale")]
+    pub red_scale: f32,
+    /// Lateral chromatic aberration: blue channel scale relative to green (0.9-1.1)
+    #[serde(default = "default_channel_scale")]
+    pub blue_scale: f32,
+    /// Vignetting compensation (0.0 = none, 2.0 = triple corner brightness)
+    #[serde(default)]
+    pub vignetting: f32,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+fn default_channel_scale() -> f32 {
+    1.0
+}
+
+/// Auto-trim parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoTrimParams {
+    /// Maximum per-channel difference from the border colour (0-255)
+    #[serde(default)]
+    pub tolerance: u8,
+}
+
+/// Auto-straighten parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoStraightenParams {
+    /// Largest tilt in degrees that will be corrected (0.0-45.0)
+    #[serde(default = "default_max_angle")]
+    pub max_angle: f32,
+}
+
+fn default_max_angle() -> f32 {
+    10.0
+}
+
+/// Content-aware resize (seam carving) parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentAwareResizeParams {
+    pub width: u32,
+    pub height: u32,
+    /// Regions seams must avoid, in image pixels
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protect_mask: Vec<CropRect>,
+}
+
+/// Smart crop parameters
+///
+/// Either `aspect` or both `width` and `height` must be set. With a size the
+/// crop uses its aspect ratio and is then resized to exactly that size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmartCropParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<AspectRatio>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Window picked when the operation was applied; replays reuse it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rect: Option<CropRect>,
+}
+
+/// Spot healing parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealParams {
+    /// Areas to fill
+    pub regions: Vec<Region>,
+    /// Neighbourhood radius in pixels used to fill each pixel (1-20)
+    #[serde(default = "default_heal_radius")]
+    pub radius: u32,
+}
+
+fn default_heal_radius() -> u32 {
+    5
+}
+
+/// Clone stamp parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloneStampParams {
+    /// Strokes applied in order, each sampling the result of the previous ones
+    pub strokes: Vec<CloneStroke>,
+}
+
+/// Single clone stamp stroke
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloneStroke {
+    /// Offset from each painted pixel to the pixel it copies, rounded to whole pixels
+    pub source_offset: Point,
+    /// Brush diameter in pixels
+    pub size: f32,
+    /// Fraction of the brush radius painted at full strength (0.0 to 1.0)
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    /// Stroke opacity (0.0 to 1.0)
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// Brush path in image coordinates
+    pub points: Vec<Point>,
+}
+
+fn default_hardness() -> f32 {
+    0.5
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// Red-eye removal parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedEyeParams {
+    pub mode: RedEyeMode,
+}
+
+/// Where to remove red-eye
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedEyeMode {
+    /// Correct the given circles
+    Manual { eyes: Vec<Circle> },
+    /// Detect round red blobs; resolved to `Manual` when added to history
+    Auto,
+}
+
+/// Circle in image coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f32,
+}
+
+/// Mask limiting where an operation applies
+///
+/// Layers are combined in order starting from an empty mask. Where the mask
+/// is 1.0 the operation applies fully, where it is 0.0 the input is kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mask {
+    pub layers: Vec<MaskLayer>,
+    /// Invert the combined mask
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// Single mask shape and how it combines with the layers before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaskLayer {
+    pub shape: MaskShape,
+    #[serde(default)]
+    pub mode: MaskMode,
+    /// Invert this layer before combining
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// How a mask layer combines with the layers before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskMode {
+    #[default]
+    Add,
+    Subtract,
+    Intersect,
+}
+
+/// Mask shapes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaskShape {
+    /// Full strength at `start`, fading to nothing at `end`
+    Linear { start: Point, end: Point },
+    /// Ellipse rotated by `angle` degrees, softened over the outer `feather` fraction (0.0 to 1.0)
+    Radial {
+        center: Point,
+        radius_x: f32,
+        radius_y: f32,
+        #[serde(default)]
+        angle: f32,
+        #[serde(default)]
+        feather: f32,
+    },
+    /// Soft round brush along a path
+    Brush {
+        points: Vec<Point>,
+        radius: f32,
+        #[serde(default = "default_hardness")]
+        hardness: f32,
+    },
+    /// Pixels whose luminance (0.0 to 1.0) lies in `min..=max`, fading over `feather` outside it
+    Luminance {
+        min: f32,
+        max: f32,
+        #[serde(default)]
+        feather: f32,
+    },
+    /// Pixels within `tolerance` of a colour (0.0 to 1.0 of the RGB range), fading over `feather`
+    Color {
+        color: Color,
+        tolerance: f32,
+        #[serde(default)]
+        feather: f32,
+    },
+    /// Pixels inside a selection
+    Selection { selection: Selection },
+}
+
+/// Selection in normalised image coordinates (0.0 to 1.0)
+///
+/// Shapes are independent of resolution, so a selection survives resizes and
+/// is rasterised against whatever image it is applied to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub shape: SelectionShape,
+    /// Select everything outside the shape instead
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// Selection shapes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SelectionShape {
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+    /// Ellipse inscribed in the given rectangle
+    Ellipse { x: f32, y: f32, width: f32, height: f32 },
+    /// Polygon lasso
+    Lasso { points: Vec<Point> },
+    /// Pixels whose channels all lie within `tolerance` of the seed pixel
+    MagicWand {
+        seed: Point,
+        tolerance: u8,
+        /// Only select pixels connected to the seed
+        #[serde(default = "default_contiguous")]
+        contiguous: bool,
+    },
+}
+
+fn default_contiguous() -> bool {
+    true
+}
+
+/// Erase parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EraseParams {
+    /// Pixels to make fully transparent
+    pub selection: Selection,
+}
+
+/// Horizontal alignment of text lines against the text position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Outline drawn around text
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextStroke {
+    /// Outline width in pixels (1-50)
+    pub width: f32,
+    pub color: Color,
+}
+
+/// Shadow drawn beneath text and its outline
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextShadow {
+    /// Shadow offset in pixels, not affected by rotation
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// Gaussian blur sigma in pixels (0-50)
+    #[serde(default)]
+    pub blur: f32,
+    pub color: Color,
+}
+
+/// Text overlay parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextParams {
+    /// Text to draw; `\n` starts a new line
+    pub text: String,
+    /// Font file name, looked up in the font directories
+    pub font: String,
+    /// Font size in pixels (1-2000)
+    pub size: f32,
+    pub color: Color,
+    /// Top of the first line, at its left edge, centre or right edge depending on `align`
+    pub position: Point,
+    #[serde(default)]
+    pub align: TextAlign,
+    /// Clockwise rotation in degrees around `position`
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<TextStroke>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<TextShadow>,
+}
+
+/// Watermark parameters
+///
+/// Sizes and distances are fractions of the image's short edge so the same
+/// watermark looks alike on images of any resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatermarkParams {
+    pub mark: WatermarkMark,
+    #[serde(default)]
+    pub placement: WatermarkPlacement,
+    /// Opacity (0.0 to 1.0)
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+}
+
+/// What the watermark shows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatermarkMark {
+    /// Logo file, usually a PNG with transparency
+    Image {
+        path: String,
+        /// Length of the logo's long side
+        #[serde(default = "default_logo_scale")]
+        scale: f32,
+    },
+    /// Logo as base64 image data, which `Image` marks are recorded as so
+    /// that replaying history does not read the file again
+    EmbeddedImage {
+        data: String,
+        #[serde(default = "default_logo_scale")]
+        scale: f32,
+    },
+    Text {
+        text: String,
+        /// Font file name, looked up in the font directories
+        font: String,
+        color: Color,
+        /// Font size
+        #[serde(default = "default_text_mark_scale")]
+        scale: f32,
+    },
+}
+
+fn default_logo_scale() -> f32 {
+    0.2
+}
+
+fn default_text_mark_scale() -> f32 {
+    0.05
+}
+
+/// Where the watermark is drawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatermarkPlacement {
+    /// Single mark at one of nine positions
+    Anchored {
+        anchor: Anchor,
+        /// Distance from the image edges
+        #[serde(default = "default_watermark_margin")]
+        margin: f32,
+    },
+    /// Marks repeated over the whole image in staggered rows
+    Tiled {
+        /// Gap between neighbouring marks
+        #[serde(default = "default_watermark_spacing")]
+        spacing: f32,
+        /// Clockwise rotation of the rows in degrees
+        #[serde(default = "default_watermark_angle")]
+        angle: f32,
+    },
+}
+
+impl Default for WatermarkPlacement {
+    fn default() -> Self {
+        Self::Anchored {
+            anchor: Anchor::BottomRight,
+            margin: default_watermark_margin(),
+        }
+    }
+}
+
+fn default_watermark_margin() -> f32 {
+    0.03
+}
+
+fn default_watermark_spacing() -> f32 {
+    0.1
+}
+
+fn default_watermark_angle() -> f32 {
+    -30.0
+}
+
+/// Shape drawing parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawParams {
+    /// Shapes drawn in order, later shapes on top
+    pub shapes: Vec<Shape>,
+}
+
+/// Single shape with its outline and fill
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub geometry: ShapeGeometry,
+    /// Outline colour; lines and arrows need one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<Color>,
+    /// Outline width in pixels, centred on the shape's edge
+    #[serde(default = "default_stroke_width")]
+    pub stroke_width: f32,
+    /// Interior colour of rectangles and ellipses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Color>,
+    #[serde(default = "default_antialias")]
+    pub antialias: bool,
+}
+
+fn default_stroke_width() -> f32 {
+    2.0
+}
+
+fn default_antialias() -> bool {
+    true
+}
+
+/// Shape outline in image coordinates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeGeometry {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        /// Corner radius in pixels; 0 for square corners
+        #[serde(default)]
+        corner_radius: f32,
+    },
+    Ellipse {
+        center: Point,
+        radius_x: f32,
+        radius_y: f32,
+    },
+    /// Straight line with round ends
+    Line { start: Point, end: Point },
+    /// Line ending in a filled arrowhead at `end`
+    Arrow {
+        start: Point,
+        end: Point,
+        /// Arrowhead length in pixels; defaults to four times the stroke width
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        head_size: Option<f32>,
+    },
+}
+
+/// Redaction parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactParams {
+    /// Areas to hide
+    pub regions: Vec<Region>,
+    pub mode: RedactMode,
+}
+
+/// How redacted areas are hidden
+///
+/// Every mode replaces the covered pixels outright, so none of the original
+/// values can be recovered from the result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedactMode {
+    /// Solid colour, including its alpha
+    Fill { color: Color },
+    /// Averages of `block_size` pixel squares aligned to the image origin (2-1000)
+    Pixelate { block_size: u32 },
+    /// Gaussian blur of a pixelated copy, so the blur cannot be deconvolved (1-200)
+    Blur { radius: f32 },
+}
+
+/// Frame parameters
+///
+/// From the inside out the result is the image, an inner stroke over its
+/// edge, an outer stroke around it, the border, and then the drop shadow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border: Option<FrameBorder>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<FrameStroke>,
+    /// Radius in pixels of the outer corners; 0 keeps them square
+    #[serde(default)]
+    pub corner_radius: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<FrameShadow>,
+    /// Colour showing outside rounded corners and behind the shadow; transparent when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+}
+
+/// Solid border around the image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameBorder {
+    pub width: BorderWidth,
+    pub color: Color,
+}
+
+/// Border widths in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BorderWidth {
+    Uniform { width: u32 },
+    Sides { top: u32, right: u32, bottom: u32, left: u32 },
+    /// Instant-photo border with a bottom margin three and a half times `width`
+    Polaroid { width: u32 },
+}
+
+/// Line along the image edge
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameStroke {
+    /// Line width in pixels
+    pub width: u32,
+    pub color: Color,
+    #[serde(default)]
+    pub position: StrokePosition,
+}
+
+/// Which side of the image edge a frame stroke lies on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokePosition {
+    /// Over the image's outermost pixels, keeping its size
+    #[default]
+    Inner,
+    /// Around the image, growing the result
+    Outer,
+}
+
+/// Drop shadow cast by the framed image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameShadow {
+    /// Shadow offset in pixels
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// Gaussian blur sigma in pixels (0-100)
+    #[serde(default)]
+    pub blur: f32,
+    pub color: Color,
+}
+
+/// Chroma key parameters
+///
+/// Colour distances are measured in RGB and scaled so 1.0 spans black to
+/// white. Pixels within `tolerance` become transparent, fading back to opaque
+/// over a further `softness`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChromaKeyParams {
+    pub mode: ChromaKeyMode,
+    /// Distance keyed out completely (0.0 to 1.0)
+    pub tolerance: f32,
+    /// Distance beyond the tolerance over which pixels fade back in (0.0 to 1.0)
+    #[serde(default)]
+    pub softness: f32,
+}
+
+/// What gets keyed out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChromaKeyMode {
+    /// Every pixel near a key colour, such as a green screen
+    Color {
+        color: Color,
+        /// How much of the key colour's cast to remove from kept pixels (0.0 to 1.0)
+        #[serde(default)]
+        spill: f32,
+    },
+    /// Uniform background connected to the image corners, flood-filled from each corner
+    Background,
+}
+
+/// Replace colour parameters
+///
+/// Pixels near the source colour in HSL are shifted by the difference between
+/// the source and target colours, so shading within the matched area survives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplaceColorParams {
+    pub source: ColorSource,
+    pub target: Color,
+    /// Hue difference in degrees matched fully (0-180); ignored for a neutral source
+    #[serde(default = "default_hue_tolerance")]
+    pub hue_tolerance: f32,
+    /// Saturation difference matched fully (0.0 to 1.0)
+    #[serde(default = "default_tone_tolerance")]
+    pub saturation_tolerance: f32,
+    /// Lightness difference matched fully (0.0 to 1.0)
+    #[serde(default = "default_tone_tolerance")]
+    pub lightness_tolerance: f32,
+    /// Falloff beyond each tolerance as a fraction of it (0.0 to 1.0)
+    #[serde(default)]
+    pub fuzziness: f32,
+}
+
+fn default_hue_tolerance() -> f32 {
+    20.0
+}
+
+fn default_tone_tolerance() -> f32 {
+    0.3
+}
+
+/// Where the colour to replace comes from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ColorSource {
+    Color { color: Color },
+    /// Pixel under a point in image coordinates
+    Sample { point: Point },
+}
+
+/// Alpha channel operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlphaOperation {
+    /// Composite onto a solid colour, leaving an opaque image
+    Flatten { color: Color },
+    /// Replace the image with its alpha channel as an opaque grayscale image
+    Extract,
+    /// Take the alpha channel from another source
+    Replace {
+        source: AlphaSource,
+        #[serde(default)]
+        invert: bool,
+    },
+    /// Make alpha opaque at or above `level` and transparent below it
+    Threshold { level: u8 },
+    /// Soften the alpha channel with a gaussian blur of `radius` pixels (0.1-100)
+    Feather { radius: f32 },
+    /// Multiply colour channels by alpha
+    Premultiply,
+    /// Divide colour channels by alpha, undoing a premultiply
+    Unpremultiply,
+    /// Crop to the pixels whose alpha is above `threshold`
+    Trim {
+        #[serde(default)]
+        threshold: u8,
+    },
+}
+
+/// Where a replacement alpha channel comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlphaSource {
+    /// Luminance of the image itself
+    Luminance,
+    /// Another file stretched to the image size, using its alpha if it has one
+    /// and its luminance otherwise
+    File { path: String },
+    /// Grayscale base64 image data stretched to the image size, which `File`
+    /// sources are recorded as so that replaying history does not read the
+    /// file again
+    Embedded { data: String },
+}
+
+/// Palette quantisation parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizeParams {
+    pub palette: PaletteSource,
+    #[serde(default)]
+    pub dither: DitherMode,
+}
+
+impl Default for QuantizeParams {
+    fn default() -> Self {
+        Self { palette: PaletteSource::MedianCut { colors: 256 }, dither: DitherMode::None }
+    }
+}
+
+/// Where the palette comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaletteSource {
+    /// Split the colour space at channel medians until there are `colors` boxes (2-256)
+    MedianCut { colors: u32 },
+    /// Refine a median cut palette of `colors` entries with k-means (2-256)
+    KMeans { colors: u32 },
+    /// Fixed palette from a GIMP `.gpl` file (at most 256 colours)
+    File { path: String },
+    /// Fixed palette of 1-256 colours, which `File` sources are recorded as so
+    /// that replaying history does not read the file again
+    Colors { colors: Vec<[u8; 3]> },
+}
+
+/// How quantisation error is hidden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    /// Map every pixel to its nearest palette colour
+    #[default]
+    None,
+    /// Diffuse each pixel's error onto its unvisited neighbours
+    FloydSteinberg,
+    /// Offset pixels by a 4x4 ordered Bayer threshold matrix
+    Bayer4,
+    /// Offset pixels by an 8x8 ordered Bayer threshold matrix
+    Bayer8,
+}
+
+/// Pixel art upscalers, kept apart from the photographic resize filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PixelArtScaler {
+    /// Repeat every pixel `factor` times in each direction (1-16)
+    Nearest { factor: u32 },
+    /// EPX / AdvMAME2x: doubles the size, rounding off stair steps
+    Scale2x,
+    /// AdvMAME3x: triples the size, rounding off stair steps
+    Scale3x,
+    /// 2xBR: doubles the size, blending corners along detected edges
+    Xbr,
+}
+
 /// Edit operation structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EditOperation {
     pub id: String,
     pub operation: OperationType,
     pub timestamp: i64,
+    /// Restrict the operation to part of the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<Mask>,
+}
+
+impl EditOperation {
+    /// Create an unmasked edit operation with a fresh id and the current time
+    pub fn new(operation: OperationType) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or(0);
+
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            operation,
+            timestamp,
+            mask: None,
+        }
+    }
 }
@@ -45,7 +45,6 @@ pub fn encode_image_as_jpeg(img: &DynamicImage, quality: u8) -> Result<String, A
 }
 
 /// Decode Base64 string to image
-#[allow(dead_code)]
 pub fn decode_image(base64_str: &str) -> Result<DynamicImage, AppError> {
     // Strip data URL prefix if present
     let base64_data = if let Some(comma_pos) = base64_str.find(',') {
@@ -24,7 +24,6 @@ pub fn resize_to_fit(img: &DynamicImage, max_width: u32, max_height: u32) -> Dyn
 }
 
 /// Validate image dimensions
-#[allow(dead_code)]
 pub fn validate_dimensions(width: u32, height: u32) -> Result<(), String> {
     const MAX_DIMENSION: u32 = 16384;
 